@@ -0,0 +1,60 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::storage::{self, StorageUsage, breaker};
+
+/// Returns the circuit breaker state of every persistent storage tier.
+pub async fn get_backend_status() -> impl IntoResponse {
+    // Building the tiers registers their breakers
+    let _ = storage::backend();
+    Json(breaker::snapshots())
+}
+
+/// Size of the persistent backend, or why it could not be measured.
+#[derive(Serialize)]
+pub struct UsageResponse {
+    #[serde(flatten)]
+    pub usage: StorageUsage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// GET /admin/api/backends/usage
+///
+/// Returns the entries and bytes stored for this `app_id` in the persistent
+/// backend. With several tiers the figures are summed, so an entry kept in
+/// two tiers counts twice.
+pub async fn get_backend_usage() -> Response {
+    let Some(backend) = storage::backend() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    match backend.usage().await {
+        Ok(usage) => Json(UsageResponse { usage, error: None }).into_response(),
+        Err(e) => {
+            tracing::warn!("⚠️ Failed to measure the persistent backend: {}", e);
+            let body = Json(UsageResponse {
+                usage: StorageUsage::default(),
+                error: Some(e.to_string()),
+            });
+            (StatusCode::BAD_GATEWAY, body).into_response()
+        }
+    }
+}
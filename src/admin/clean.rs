@@ -12,19 +12,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::memory::memory::MEMORY_CACHE;
-use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use crate::cache_key::{self, CacheKey, source_uri};
+use crate::config::KeyStrategy;
+use crate::memory::memory::{CachedResponse, MEMORY_CACHE, peek_from_memory};
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap, Uri,
+};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
 
-// Individual and full backend deletion
-use crate::storage::azure::delete_all_from_cache as delete_all_azure;
-use crate::storage::gcs::delete_all_from_cache as delete_all_gcs;
-use crate::storage::local::delete_all_from_cache as delete_all_local;
-use crate::storage::s3::delete_all_from_cache as delete_all_s3;
+use crate::storage::purge::{self, PurgeScope};
+use crate::storage::{self, StorageBackend, StorageResult};
 
 #[derive(Deserialize)]
 pub struct InvalidateParams {
     pub backend: Option<bool>,
+    /// Soft purge: flag entries stale instead of deleting them.
+    pub soft: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -32,41 +46,363 @@ struct SuccessResponse {
     message: String,
 }
 
-/// DELETE /cache?backend=true
+/// DELETE /cache?backend=true&soft=true
 pub async fn invalidate_handler(Query(params): Query<InvalidateParams>) -> impl IntoResponse {
     let backend_enabled = params.backend.unwrap_or(false);
+    let soft = params.soft.unwrap_or(false);
 
-    // 🧠 Clear memory cache
-    let mut memory = MEMORY_CACHE.write().await;
-    let count = memory.len();
-    memory.clear();
-    tracing::info!("🧨 Cleared all {count} entries from in-memory cache");
-
-    // ☁️ Optionally clear all backends
-    if backend_enabled {
-        let futures = vec![
-            tokio::spawn(async { delete_all_azure().await }),
-            tokio::spawn(async { delete_all_gcs().await }),
-            tokio::spawn(async { delete_all_s3().await }),
-            tokio::spawn(async { delete_all_local().await }),
-        ];
-
-        for task in futures {
-            if let Err(e) = task.await {
-                tracing::warn!("⚠️ A backend deletion task failed: {:?}", e);
+    // 🧠 Clear (or soft-purge) memory cache
+    if soft {
+        let count = MEMORY_CACHE.mark_purged_where(|_, _| true).await;
+        tracing::info!("🧽 Soft-purged all {count} entries of the in-memory cache");
+    } else {
+        let count = MEMORY_CACHE.len().await;
+        MEMORY_CACHE.clear().await;
+        tracing::info!("🧨 Cleared all {count} entries from in-memory cache");
+    }
+
+    // ☁️ Optionally clear the persistent backend
+    if backend_enabled && let Some(backend) = storage::backend() {
+        if soft {
+            match purge::record(backend.as_ref(), vec![PurgeScope::All]).await {
+                Ok(()) => tracing::info!("🧽 Soft-purged every entry in the {} backend", backend.name()),
+                Err(e) => tracing::warn!("⚠️ Soft purge in the {} backend failed: {}", backend.name(), e),
+            }
+        } else {
+            match backend.delete_all().await {
+                Ok(keys) => {
+                    tracing::info!("🧹 Deleted {} entries in the {} backend", keys.len(), backend.name());
+                    // Nothing is left for the recorded soft purges to cover
+                    if let Err(e) = purge::clear(backend.as_ref()).await {
+                        tracing::warn!("⚠️ Failed to clear the soft purge rules: {}", e);
+                    }
+                }
+                Err(e) => tracing::warn!("⚠️ Deletion in the {} backend failed: {}", backend.name(), e),
             }
         }
-
-        tracing::info!("🧹 Requested full deletion from all persistent backends");
     }
 
+    let message = match (backend_enabled, soft) {
+        (true, false) => "Cleared in-memory cache and requested deletion from the persistent backend",
+        (false, false) => "Cleared in-memory cache only",
+        (true, true) => "Soft-purged the in-memory cache and the persistent backend",
+        (false, true) => "Soft-purged the in-memory cache only",
+    };
     let body = Json(SuccessResponse {
-        message: if backend_enabled {
-            format!("Cleared in-memory cache and requested deletion from all backends")
-        } else {
-            format!("Cleared in-memory cache only")
-        },
+        message: message.to_string(),
     });
 
     (StatusCode::OK, body)
 }
+
+/// Query string of the targeted invalidation endpoints.
+#[derive(Deserialize, Default)]
+pub struct PurgeParams {
+    /// Soft purge: flag matching entries stale (see `CachedResponse::purged`)
+    /// instead of deleting them, so they are still served if the downstream fails.
+    pub soft: Option<bool>,
+}
+
+/// Body of `POST /admin/api/invalidate/url`.
+#[derive(Deserialize)]
+pub struct InvalidateUrlRequest {
+    /// Request path and query (`/api/items?page=2`); a full URL is reduced to them.
+    pub url: String,
+    /// Request headers that are part of the cache key, as the client sends them.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// Body of `POST /admin/api/invalidate/keys`.
+#[derive(Deserialize)]
+pub struct InvalidateKeysRequest {
+    /// Cache keys, as listed by the backend or logged by the proxy.
+    pub keys: Vec<String>,
+}
+
+/// Body of `POST /admin/api/invalidate/paths`: exactly one of the fields.
+#[derive(Deserialize)]
+pub struct InvalidatePathsRequest {
+    /// Literal prefix of the (normalized) request URI, e.g. `/api/products/`.
+    pub prefix: Option<String>,
+    /// Regular expression searched in the (normalized) request URI.
+    pub regex: Option<String>,
+}
+
+/// Body of `POST /admin/api/purge/tags`.
+#[derive(Deserialize)]
+pub struct PurgeTagsRequest {
+    /// Cache tags, as listed by the upstream in its tag headers.
+    pub tags: Vec<String>,
+}
+
+/// Number of entries removed (or soft-purged) by a targeted invalidation.
+#[derive(Serialize)]
+pub struct InvalidationResponse {
+    /// Entries removed from the in-memory cache.
+    pub memory: usize,
+    /// Entries removed from the persistent backend (a key held by several
+    /// tiers counts once). Absent for a soft purge, which is recorded as a rule
+    /// checked when entries are read (see `storage::purge`) and counts nothing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<usize>,
+    /// Why the backend could not be cleaned, if it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Runs `delete` against the persistent backend, if there is one.
+async fn from_backend<F, Fut>(delete: F) -> StorageResult<Option<usize>>
+where
+    F: FnOnce(Arc<dyn StorageBackend>) -> Fut,
+    Fut: Future<Output = StorageResult<usize>>,
+{
+    match storage::backend() {
+        Some(backend) => delete(backend).await.map(Some),
+        None => Ok(Some(0)),
+    }
+}
+
+/// Records a soft purge of `scopes` in the persistent backend, if there is one.
+async fn soft_purge_backend(scopes: Vec<PurgeScope>) -> StorageResult<Option<usize>> {
+    match storage::backend() {
+        Some(backend) => purge::record(backend.as_ref(), scopes).await.map(|()| None),
+        None => Ok(Some(0)),
+    }
+}
+
+/// Removes, or soft-purges, the in-memory entries for which `matches` is `true`.
+async fn from_memory(soft: bool, matches: impl Fn(&str, &CachedResponse) -> bool) -> usize {
+    if soft {
+        MEMORY_CACHE.mark_purged_where(matches).await
+    } else {
+        MEMORY_CACHE.remove_where(matches).await
+    }
+}
+
+/// Builds the response of a targeted invalidation: `200 OK`, or `502 Bad
+/// Gateway` if the backend failed (entries removed from memory stay removed).
+fn invalidation_response(
+    what: &str,
+    soft: bool,
+    memory: usize,
+    backend: StorageResult<Option<usize>>,
+) -> Response {
+    let action = if soft { "Soft-purge" } else { "Invalidation" };
+    let (status, backend, error) = match backend {
+        Ok(deleted) => (StatusCode::OK, deleted, None),
+        Err(e) => {
+            tracing::warn!("⚠️ {} of {} in the persistent backend failed: {}", action, what, e);
+            (StatusCode::BAD_GATEWAY, None, Some(e.to_string()))
+        }
+    };
+    match backend {
+        Some(count) => tracing::info!(
+            "🧨 {} of {}: {} entries in memory, {} in the persistent backend",
+            action,
+            what,
+            memory,
+            count
+        ),
+        None => tracing::info!("🧨 {} of {}: {} entries in memory", action, what, memory),
+    }
+    (status, Json(InvalidationResponse { memory, backend, error })).into_response()
+}
+
+fn bad_request(message: impl Into<String>) -> Response {
+    let body = Json(SuccessResponse {
+        message: message.into(),
+    });
+    (StatusCode::BAD_REQUEST, body).into_response()
+}
+
+/// POST /admin/api/invalidate/url?soft=true
+///
+/// Removes the entry a request for `url` with `headers` is served from: its
+/// key is derived exactly as `proxy_handler` does. With the `vary` key
+/// strategy, the variants listed by the vary record under that key go too.
+pub async fn invalidate_url_handler(
+    Query(params): Query<PurgeParams>,
+    Json(request): Json<InvalidateUrlRequest>,
+) -> Response {
+    let soft = params.soft.unwrap_or(false);
+    let uri = match request.url.parse::<Uri>() {
+        Ok(uri) => uri.path_and_query().map_or("/".to_string(), |pq| pq.to_string()),
+        Err(e) => return bad_request(format!("Invalid url '{}': {e}", request.url)),
+    };
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &request.headers {
+        match (HeaderName::try_from(name.as_str()), HeaderValue::try_from(value.as_str())) {
+            (Ok(name), Ok(value)) => {
+                headers.append(name, value);
+            }
+            _ => return bad_request(format!("Invalid header '{name}: {value}'")),
+        }
+    }
+
+    let base = CacheKey::new(cache_key::key_source(&uri, &headers, None));
+    let mut keys = vec![base.key.clone()];
+    if cache_key::strategy() == KeyStrategy::Vary {
+        keys.extend(recorded_variants(&base).await);
+    }
+
+    let memory = from_memory(soft, |key, _| keys.iter().any(|k| k == key)).await;
+    let backend = if soft {
+        soft_purge_backend(vec![PurgeScope::Keys(keys.clone())]).await
+    } else {
+        let keys = &keys;
+        from_backend(|backend| async move {
+            let mut deleted = 0;
+            for key in keys {
+                deleted += usize::from(backend.delete(key).await?);
+            }
+            Ok(deleted)
+        })
+        .await
+    };
+
+    invalidation_response(&format!("url '{uri}'"), soft, memory, backend)
+}
+
+/// Variant keys listed by the vary records under `base`, in memory and in the
+/// persistent backend (another replica may have stored more variants).
+async fn recorded_variants(base: &CacheKey) -> Vec<String> {
+    let mut variants = peek_from_memory(&base.key)
+        .await
+        .map(|record| cache_key::recorded_variants(&record))
+        .unwrap_or_default();
+    if let Some(backend) = storage::backend() {
+        match backend.get(&base.key).await {
+            Ok(Some(record)) => {
+                for key in cache_key::recorded_variants(&record) {
+                    if !variants.contains(&key) {
+                        variants.push(key);
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("⚠️ Failed to load the vary record of '{}': {}", base.source, e),
+        }
+    }
+    variants
+}
+
+/// POST /admin/api/invalidate/keys?soft=true
+///
+/// Removes the entries stored under the given cache keys.
+pub async fn invalidate_keys_handler(
+    Query(params): Query<PurgeParams>,
+    Json(request): Json<InvalidateKeysRequest>,
+) -> Response {
+    let soft = params.soft.unwrap_or(false);
+    let mut memory = 0;
+    for key in &request.keys {
+        let found = if soft {
+            MEMORY_CACHE.mark_purged(key).await
+        } else {
+            MEMORY_CACHE.pop(key).await.is_some()
+        };
+        memory += usize::from(found);
+    }
+
+    let keys = &request.keys;
+    let backend = if soft {
+        soft_purge_backend(vec![PurgeScope::Keys(keys.clone())]).await
+    } else {
+        from_backend(|backend| async move {
+            let mut deleted = 0;
+            for key in keys {
+                deleted += usize::from(backend.delete(key).await?);
+            }
+            Ok(deleted)
+        })
+        .await
+    };
+
+    invalidation_response(&format!("{} keys", keys.len()), soft, memory, backend)
+}
+
+/// POST /admin/api/invalidate/paths?soft=true
+///
+/// Removes every entry whose request URI starts with `prefix` or matches
+/// `regex`. URIs are compared after key template normalization, without the
+/// headers part of the key, and cacheable POSTs are included.
+pub async fn invalidate_paths_handler(
+    Query(params): Query<PurgeParams>,
+    Json(request): Json<InvalidatePathsRequest>,
+) -> Response {
+    let soft = params.soft.unwrap_or(false);
+    match (request.prefix, request.regex) {
+        (Some(prefix), None) => {
+            if prefix.is_empty() || prefix.contains('|') {
+                return bad_request("prefix must be non-empty and cannot contain '|'");
+            }
+
+            let memory =
+                from_memory(soft, |_, entry| source_uri(&entry.key_source).starts_with(&prefix))
+                    .await;
+            let backend = if soft {
+                soft_purge_backend(vec![PurgeScope::UriPrefix(prefix.clone())]).await
+            } else {
+                let prefix = &prefix;
+                from_backend(|backend| async move {
+                    let plain = backend.delete_source_prefix(prefix).await?.len();
+                    Ok(plain + backend.delete_source_prefix(&format!("POST {prefix}")).await?.len())
+                })
+                .await
+            };
+
+            invalidation_response(&format!("prefix '{prefix}'"), soft, memory, backend)
+        }
+        (None, Some(regex)) => {
+            let pattern = match Regex::new(&regex) {
+                Ok(pattern) => pattern,
+                Err(e) => return bad_request(format!("Invalid regex: {e}")),
+            };
+
+            let memory =
+                from_memory(soft, |_, entry| pattern.is_match(source_uri(&entry.key_source)))
+                    .await;
+            let backend = if soft {
+                soft_purge_backend(vec![PurgeScope::UriRegex(regex.clone())]).await
+            } else {
+                let pattern = &pattern;
+                from_backend(|backend| async move {
+                    Ok(backend.delete_source_matching(pattern).await?.len())
+                })
+                .await
+            };
+
+            invalidation_response(&format!("regex '{regex}'"), soft, memory, backend)
+        }
+        _ => bad_request("Exactly one of prefix or regex is required"),
+    }
+}
+
+/// POST /admin/api/purge/tags?soft=true
+///
+/// Removes every entry tagged with at least one of `tags` (see `cache_tags`).
+pub async fn purge_tags_handler(
+    Query(params): Query<PurgeParams>,
+    Json(request): Json<PurgeTagsRequest>,
+) -> Response {
+    let soft = params.soft.unwrap_or(false);
+    if request.tags.is_empty() || request.tags.iter().any(|tag| tag.trim().is_empty()) {
+        return bad_request("tags must be a non-empty list of non-empty tags");
+    }
+
+    let tags = &request.tags;
+    let memory = if soft {
+        MEMORY_CACHE.mark_tagged_purged(tags).await
+    } else {
+        MEMORY_CACHE.remove_tagged(tags).await
+    };
+    let backend = if soft {
+        soft_purge_backend(vec![PurgeScope::Tags(tags.clone())]).await
+    } else {
+        from_backend(|backend| async move { Ok(backend.delete_tagged(tags).await?.len()) }).await
+    };
+
+    invalidation_response(&format!("tags {}", tags.join(", ")), soft, memory, backend)
+}
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod backends;
 pub mod clean;
 pub mod status_memory;
 pub mod ui;
\ No newline at end of file
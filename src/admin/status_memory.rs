@@ -16,7 +16,6 @@ use chrono::Utc;
 use axum::{Json, response::IntoResponse};
 use serde::Serialize;
 use crate::memory::memory::MEMORY_CACHE;
-use crate::config::CONFIG;
 use std::collections::HashMap;
 
 #[derive(Serialize)]
@@ -24,30 +23,32 @@ pub struct CacheEntry {
     pub inserted_at: String,
     pub size_bytes: usize,
     pub ttl_remaining_secs: i64,
+    /// Human-readable source the SHA-256 key was derived from.
+    pub key_source: String,
+    /// Cache tags the entry can be purged by.
+    pub tags: Vec<String>,
+    /// Soft-purged: revalidated on the next request.
+    pub purged: bool,
 }
 
 pub async fn get_memory_cache_status() -> impl IntoResponse {
-    let cache = MEMORY_CACHE.read().await;
     let now = Utc::now();
 
-    // Read TTL from config
-    let ttl_secs = CONFIG
-        .get()
-        .map(|c| c.cache.ttl_seconds)
-        .unwrap_or(300); // default fallback
-
-    let entries: HashMap<String, CacheEntry> = cache
+    let entries: HashMap<String, CacheEntry> = MEMORY_CACHE
         .iter()
         .map(|(key, value)| {
-            let elapsed = now.signed_duration_since(value.inserted_at).num_seconds();
-            let ttl_remaining = ttl_secs as i64 - elapsed;
+            // Each entry carries its own freshness lifetime
+            let ttl_remaining = value.expires_at.signed_duration_since(now).num_seconds();
 
             (
-                key.clone(),
+                key.to_string(),
                 CacheEntry {
                     inserted_at: value.inserted_at.to_rfc3339(),
                     size_bytes: value.body.len(),
                     ttl_remaining_secs: ttl_remaining.max(0),
+                    key_source: value.key_source.clone(),
+                    tags: value.tags.clone(),
+                    purged: value.purged,
                 },
             )
         })
@@ -0,0 +1,422 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Cache key derivation.
+///
+/// A key is the SHA-256 of a human-readable *key source*:
+/// `{normalized uri}|{headers}` (plus cookies and a body hash when configured).
+/// The URI is normalized by the first matching `cache_key.path_rules` template.
+///
+/// With the default `all_headers` strategy the headers are every non-ignored
+/// request header. With the `vary` strategy the *base key* only covers
+/// `cache_key.header_allowlist`; the request headers named in the upstream
+/// `Vary` header then select a *variant key* under that base. The variant list
+/// of each base key is kept under the base key itself: a response that does
+/// not vary is stored there, otherwise a *vary record* listing the variant
+/// keys stored so far is. Both live in memory and the persistent backend like
+/// any entry, so the list is evicted with `MEMORY_CACHE` and survives restarts.
+/// The `Vary` list of every base key seen since startup is also remembered on
+/// its own, so only the first request for a base key after a restart looks it
+/// up in the persistent backend.
+use hyper::HeaderMap;
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::config::{CONFIG, CacheKeyRule, KeyStrategy};
+use crate::memory::memory::{self, CachedResponse};
+use crate::proxy::{hash_uri, load_from_backend};
+
+/// Most base keys whose `Vary` list is remembered.
+const KNOWN_VARY_CAPACITY: u64 = 100_000;
+
+/// `Vary` list of the base keys seen since startup, empty if their response
+/// does not vary (or none was found).
+static KNOWN_VARY: Lazy<Cache<String, Vec<String>>> =
+    Lazy::new(|| Cache::new(KNOWN_VARY_CAPACITY));
+
+/// A cache key together with the human-readable source it was hashed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheKey {
+    pub key: String,
+    pub source: String,
+}
+
+impl CacheKey {
+    /// Hashes a key source into a cache key.
+    pub fn new(source: String) -> Self {
+        CacheKey {
+            key: hash_uri(&source),
+            source,
+        }
+    }
+}
+
+/// Returns the configured key strategy.
+pub fn strategy() -> KeyStrategy {
+    CONFIG
+        .get()
+        .map(|c| c.cache_key.strategy)
+        .unwrap_or_default()
+}
+
+/// Returns the first key template whose pattern matches the URI.
+fn rule_for(uri: &str) -> Option<&'static CacheKeyRule> {
+    CONFIG.get()?.cache_key.path_rules.iter().find(|rule| {
+        Regex::new(&rule.pattern)
+            .map(|re| re.is_match(uri))
+            .unwrap_or(false)
+    })
+}
+
+/// Builds the base key source of a request, exactly as `proxy_handler` does.
+///
+/// # Arguments
+/// - `uri`: Request path and query.
+/// - `headers`: Request headers.
+/// - `post_body`: Body of a cacheable POST, hashed into the key.
+pub fn key_source(uri: &str, headers: &HeaderMap, post_body: Option<&[u8]>) -> String {
+    let rule = rule_for(uri);
+    let uri = match rule {
+        Some(rule) => normalize_uri(uri, rule),
+        None => uri.to_string(),
+    };
+
+    let mut source = match post_body {
+        Some(body) => format!(
+            "POST {}|{}|{:x}",
+            uri,
+            relevant_headers(headers, rule),
+            Sha256::digest(body)
+        ),
+        None => format!("{}|{}", uri, relevant_headers(headers, rule)),
+    };
+
+    if let Some(rule) = rule {
+        let cookies = selected_cookies(headers, &rule.include_cookies);
+        if !cookies.is_empty() {
+            source.push_str("|cookies:");
+            source.push_str(&cookies);
+        }
+    }
+
+    source
+}
+
+/// Returns the request URI a key source was built from (normalized by its key
+/// template), without the `POST ` marker and body hash of cacheable POSTs.
+pub fn source_uri(source: &str) -> &str {
+    let source = source.strip_prefix("POST ").unwrap_or(source);
+    source.split('|').next().unwrap_or(source)
+}
+
+/// Applies a key template to a URI: lowercases the path and filters and
+/// sorts the query parameters as configured.
+pub fn normalize_uri(uri: &str, rule: &CacheKeyRule) -> String {
+    let (path, query) = match uri.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (uri, None),
+    };
+
+    let path = if rule.lowercase_path {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    };
+
+    let mut params = query
+        .unwrap_or("")
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .filter(|p| {
+            let name = p.split_once('=').map(|(n, _)| n).unwrap_or(p);
+            let dropped = rule.drop_params.iter().any(|d| param_matches(name, d));
+            let allowed = rule
+                .param_allowlist
+                .as_ref()
+                .is_none_or(|list| list.iter().any(|a| a == name));
+            !dropped && allowed
+        })
+        .collect::<Vec<_>>();
+
+    if rule.sort_query {
+        params.sort();
+    }
+
+    if params.is_empty() {
+        path
+    } else {
+        format!("{}?{}", path, params.join("&"))
+    }
+}
+
+/// Matches a query parameter name against a pattern with an optional trailing `*`.
+fn param_matches(name: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+/// Returns the selected cookies as sorted `name=value` pairs joined by `;`.
+fn selected_cookies(headers: &HeaderMap, names: &[String]) -> String {
+    let mut cookies = headers
+        .get_all("cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|c| c.trim().split_once('='))
+        .filter(|(name, _)| names.iter().any(|n| n == name))
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>();
+    cookies.sort();
+    cookies.join(";")
+}
+
+/// Builds the header part of the key source as sorted `name:value` pairs
+/// joined by `;`, selecting headers according to the key strategy plus the
+/// template's `include_headers`.
+pub fn relevant_headers(headers: &HeaderMap, rule: Option<&CacheKeyRule>) -> String {
+    let Some(cfg) = CONFIG.get() else {
+        return String::new();
+    };
+
+    let included = rule.map(|r| r.include_headers.as_slice()).unwrap_or_default();
+    let listed = |name: &str, list: &[String]| list.iter().any(|h| h.eq_ignore_ascii_case(name));
+
+    let include: Box<dyn Fn(&str) -> bool> = match cfg.cache_key.strategy {
+        KeyStrategy::AllHeaders => {
+            let ignored = cfg.ignored_headers_set();
+            Box::new(move |name| !ignored.contains(name) || listed(name, included))
+        }
+        KeyStrategy::Vary => {
+            let allowed = &cfg.cache_key.header_allowlist;
+            Box::new(move |name| listed(name, allowed) || listed(name, included))
+        }
+    };
+
+    // Extract and normalize headers, keeping only the selected ones
+    let mut headers_kv = headers
+        .iter()
+        .map(|(k, v)| {
+            (
+                k.as_str().to_ascii_lowercase(),      // normalize key
+                v.to_str().unwrap_or("").to_string(), // safe string conversion
+            )
+        })
+        .filter(|(k, _)| include(k))
+        .collect::<Vec<_>>();
+
+    // Sort headers alphabetically to ensure deterministic key
+    headers_kv.sort_by(|a, b| a.0.cmp(&b.0));
+
+    headers_kv
+        .iter()
+        .map(|(k, v)| format!("{}:{}", k, v))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Parses the `Vary` header of a response into lowercased, sorted header names.
+///
+/// # Returns
+/// - `Some(vec![])` if the response does not vary.
+/// - `Some(names)` with the headers the response varies on.
+/// - `None` for `Vary: *`, which makes the response uncacheable.
+pub fn vary_from_headers(headers: &HeaderMap) -> Option<Vec<String>> {
+    parse_vary(headers.get_all("vary").iter().map(|v| v.to_str().unwrap_or("")))
+}
+
+/// Returns the `Vary` list of a cached entry; empty if it does not vary.
+pub fn entry_vary(entry: &CachedResponse) -> Vec<String> {
+    let values = entry
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("vary"))
+        .map(|(_, value)| value.as_str());
+    parse_vary(values).unwrap_or_default()
+}
+
+/// Parses `Vary` header values into lowercased, sorted header names
+/// (`None` for `*`).
+fn parse_vary<'a>(values: impl Iterator<Item = &'a str>) -> Option<Vec<String>> {
+    let mut names = Vec::new();
+    for value in values {
+        for name in value.split(',') {
+            let name = name.trim().to_ascii_lowercase();
+            if name == "*" {
+                return None;
+            }
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names.sort();
+    Some(names)
+}
+
+/// Computes the variant key selected by `vary` for a request under `base`.
+/// A response that does not vary is stored under the base key itself.
+pub fn variant(base: &CacheKey, vary: &[String], headers: &HeaderMap) -> CacheKey {
+    if vary.is_empty() {
+        return base.clone();
+    }
+
+    let values = vary
+        .iter()
+        .map(|name| {
+            let value = headers
+                .get_all(name.as_str())
+                .iter()
+                .map(|v| v.to_str().unwrap_or(""))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{}:{}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(";");
+
+    CacheKey::new(format!("{}|vary|{}", base.source, values))
+}
+
+/// Resolves the key used to look up a request. With the `vary` strategy the
+/// `Vary` list of the entry under `base` picks the variant; otherwise (or if no
+/// response has been stored yet) the base key is used.
+///
+/// The base entry is looked up in memory first, then in the remembered `Vary`
+/// lists. Only a base key not seen since startup is loaded from the
+/// persistent backend (into memory), so variants stored before a restart are
+/// still found; concurrent requests for it share that one lookup.
+pub async fn resolve(base: &CacheKey, headers: &HeaderMap) -> CacheKey {
+    if strategy() != KeyStrategy::Vary {
+        return base.clone();
+    }
+
+    let vary = match memory::peek_from_memory(&base.key).await {
+        Some(entry) => Some(entry_vary(&entry)),
+        None => {
+            KNOWN_VARY
+                .optionally_get_with(base.key.clone(), load_vary(base))
+                .await
+        }
+    };
+    variant(base, &vary.unwrap_or_default(), headers)
+}
+
+/// Loads the entry under `base` from the persistent backend into memory and
+/// returns its `Vary` list. `None` if the lookup failed, so the next request
+/// tries again.
+async fn load_vary(base: &CacheKey) -> Option<Vec<String>> {
+    match load_from_backend(&base.key).await {
+        Ok(Some(entry)) => {
+            let vary = entry_vary(&entry);
+            memory::load_into_memory(vec![(base.key.clone(), entry)]).await;
+            Some(vary)
+        }
+        Ok(None) => Some(Vec::new()),
+        Err(e) => {
+            tracing::warn!("⚠️ Failed to load the vary record of '{}': {}", base.source, e);
+            None
+        }
+    }
+}
+
+/// Remembers the `Vary` list of a response stored for `base` (see `resolve`).
+pub async fn remember_vary(base: &CacheKey, entry: &CachedResponse) {
+    if strategy() == KeyStrategy::Vary {
+        KNOWN_VARY.insert(base.key.clone(), entry_vary(entry)).await;
+    }
+}
+
+/// Builds the vary record stored under `base` when `entry` is stored under
+/// `variant`: the `Vary` header, and as body the keys of the variants stored
+/// so far (those of `previous`, the record it replaces, plus `variant`), one
+/// per line. It lives as long as the longest-lived of them, so it is swept
+/// with the last one. It is never served, since a base key holding a `Vary`
+/// list always resolves to a variant.
+pub fn vary_record(
+    base: &CacheKey,
+    variant: &CacheKey,
+    entry: &CachedResponse,
+    previous: Option<&CachedResponse>,
+) -> CachedResponse {
+    let mut variants = previous.map(recorded_variants).unwrap_or_default();
+    if !variants.contains(&variant.key) {
+        variants.push(variant.key.clone());
+    }
+    let expires_at = previous
+        .map_or(entry.expires_at, |previous| previous.expires_at.max(entry.expires_at));
+
+    CachedResponse {
+        body: variants.join("\n").into(),
+        headers: vec![("vary".to_string(), entry_vary(entry).join(", "))],
+        expires_at,
+        key_source: base.source.clone(),
+        tags: vec![],
+        purged: false,
+        ..entry.clone()
+    }
+}
+
+/// Returns the variant keys listed by a vary record (none for an entry that
+/// does not vary).
+pub fn recorded_variants(record: &CachedResponse) -> Vec<String> {
+    if entry_vary(record).is_empty() {
+        return Vec::new();
+    }
+    String::from_utf8_lossy(&record.body)
+        .lines()
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Decides the key a downstream response is stored under. Responses stored
+/// under a variant key also need a `vary_record` under `base`.
+///
+/// # Returns
+/// - `Some(key)` with the (variant) key to store the response under.
+/// - `None` if the response carries `Vary: *` and must not be cached.
+pub fn storage_key(
+    base: &CacheKey,
+    lookup: &CacheKey,
+    response_headers: &HeaderMap,
+    request_headers: &HeaderMap,
+) -> Option<CacheKey> {
+    if strategy() != KeyStrategy::Vary {
+        return Some(lookup.clone());
+    }
+
+    let vary = vary_from_headers(response_headers)?;
+    Some(variant(base, &vary, request_headers))
+}
+
+/// Returns `true` if a response fetched by another request for `lookup`
+/// is also valid for this request, i.e. it selects the same variant.
+pub fn same_variant(
+    base: &CacheKey,
+    lookup: &CacheKey,
+    response_headers: &HeaderMap,
+    request_headers: &HeaderMap,
+) -> bool {
+    if strategy() != KeyStrategy::Vary {
+        return true;
+    }
+    match vary_from_headers(response_headers) {
+        Some(vary) => variant(base, &vary, request_headers).key == lookup.key,
+        None => false,
+    }
+}
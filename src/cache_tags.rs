@@ -0,0 +1,77 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Cache tags (surrogate keys).
+///
+/// The upstream lists the tags of a response in the headers configured under
+/// `cache_tags.headers` (e.g. `Surrogate-Key: product-42 category-7`). They
+/// are recorded with the entry when it is stored, indexed by the in-memory
+/// cache and persisted in the blob envelope, so every entry carrying a tag can
+/// be purged at once.
+use hyper::HeaderMap;
+
+use crate::config::CONFIG;
+
+/// Returns `true` if `name` is one of the configured tag headers.
+pub fn is_tag_header(name: &str) -> bool {
+    match CONFIG.get() {
+        Some(config) => config
+            .cache_tags
+            .headers
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(name)),
+        None => name.eq_ignore_ascii_case("surrogate-key"),
+    }
+}
+
+/// Tags listed in the tag headers of a response, sorted and deduplicated.
+/// Tags are separated by whitespace or commas.
+pub fn from_headers(headers: &[(String, String)]) -> Vec<String> {
+    let mut tags: Vec<String> = headers
+        .iter()
+        .filter(|(name, _)| is_tag_header(name))
+        .flat_map(|(_, value)| value.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Returns `true` if `cache_tags.strip_from_response` is set.
+pub fn strip_enabled() -> bool {
+    CONFIG
+        .get()
+        .is_some_and(|c| c.cache_tags.strip_from_response)
+}
+
+/// Removes the tag headers from stored `(name, value)` pairs if
+/// `cache_tags.strip_from_response` is set.
+pub fn strip_pairs(headers: &mut Vec<(String, String)>) {
+    if strip_enabled() {
+        headers.retain(|(name, _)| !is_tag_header(name));
+    }
+}
+
+/// Removes the tag headers from a response going to a client if
+/// `cache_tags.strip_from_response` is set.
+pub fn strip_response_headers(headers: &mut HeaderMap) {
+    let Some(config) = CONFIG.get().filter(|c| c.cache_tags.strip_from_response) else {
+        return;
+    };
+    for name in &config.cache_tags.headers {
+        headers.remove(name.as_str());
+    }
+}
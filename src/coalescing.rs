@@ -0,0 +1,117 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Request coalescing (single-flight) for concurrent misses on the same cache key.
+///
+/// The first request for a key becomes the *leader* and performs the upstream fetch.
+/// Requests arriving while the fetch is in flight become *followers* and wait for the
+/// leader's response instead of hitting the downstream service themselves.
+use bytes::Bytes;
+use hyper::{Body, HeaderMap, Response, StatusCode};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::error::Elapsed;
+
+/// Upstream response shared by the leader with all of its followers.
+#[derive(Clone)]
+pub struct SharedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl SharedResponse {
+    /// Builds a fresh `Response` for one follower.
+    pub fn to_response(&self) -> Response<Body> {
+        let mut resp = Response::new(Body::from(self.body.clone()));
+        *resp.status_mut() = self.status;
+        *resp.headers_mut() = self.headers.clone();
+        resp
+    }
+}
+
+/// Outcome broadcast by the leader: `None` means the upstream fetch failed.
+pub type FlightResult = Option<Arc<SharedResponse>>;
+
+/// In-flight upstream fetches, indexed by cache key.
+static IN_FLIGHT: Lazy<Mutex<HashMap<String, broadcast::Sender<FlightResult>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Role of a request after joining the in-flight table.
+pub enum Flight {
+    /// This request must perform the upstream fetch and then `complete` the guard.
+    Leader(FlightGuard),
+    /// Another request is already fetching this key; wait for its result.
+    Follower(broadcast::Receiver<FlightResult>),
+}
+
+/// Held by the leader while its upstream fetch is in flight.
+/// Dropping it without calling `complete` releases followers with no response,
+/// so they fall back to the cache.
+pub struct FlightGuard {
+    key: String,
+    tx: Option<broadcast::Sender<FlightResult>>,
+}
+
+impl FlightGuard {
+    /// Removes the key from the in-flight table and fans the result out to followers.
+    pub fn complete(mut self, result: FlightResult) {
+        if let Some(tx) = self.tx.take() {
+            IN_FLIGHT.lock().unwrap().remove(&self.key);
+            let _ = tx.send(result);
+        }
+    }
+}
+
+impl Drop for FlightGuard {
+    fn drop(&mut self) {
+        if self.tx.take().is_some() {
+            IN_FLIGHT.lock().unwrap().remove(&self.key);
+        }
+    }
+}
+
+/// Joins the in-flight fetch for `key`, becoming its leader if none exists.
+pub fn join(key: &str) -> Flight {
+    let mut in_flight = IN_FLIGHT.lock().unwrap();
+
+    if let Some(tx) = in_flight.get(key) {
+        return Flight::Follower(tx.subscribe());
+    }
+
+    let (tx, _) = broadcast::channel(1);
+    in_flight.insert(key.to_string(), tx.clone());
+    Flight::Leader(FlightGuard {
+        key: key.to_string(),
+        tx: Some(tx),
+    })
+}
+
+/// Waits up to `timeout` for the leader's response.
+///
+/// # Returns
+/// - `Ok(Some(SharedResponse))` if the leader fetched a response in time.
+/// - `Ok(None)` if the leader failed or was cancelled.
+/// - `Err(Elapsed)` if the timeout expired first.
+pub async fn wait(
+    mut rx: broadcast::Receiver<FlightResult>,
+    timeout: Duration,
+) -> Result<FlightResult, Elapsed> {
+    tokio::time::timeout(timeout, rx.recv())
+        .await
+        .map(|r| r.ok().flatten())
+}
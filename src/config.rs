@@ -16,22 +16,75 @@ use once_cell::sync::OnceCell;
 use serde::Deserialize;
 use std::{collections::HashSet, error::Error, fs};
 
+use crate::rules::conditional::CONDITIONAL_HEADERS;
+
 /// Supported persistent storage backends for the cache.
-#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum StorageBackend {
     Gcs,
     S3,
     Azure,
+    #[default]
     Local,
+    Redis,
+    Sqlite,
+}
+
+/// How the proxy uses cached entries on the normal request path.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMode {
+    /// Always forward to the downstream; the cache is only used as a fallback
+    /// on failures, latency failover or concurrency saturation.
+    #[default]
+    Fallback,
+    /// Serve fresh entries (before their `expires_at`, taken from the upstream
+    /// `Cache-Control`/`Expires` or `ttl_seconds` otherwise) straight from
+    /// memory and only forward on miss, expiry or a soft purge.
+    ReadThrough,
+}
+
+/// Where the memory figures behind `cache.memory_threshold` come from.
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MemorySource {
+    /// The container's cgroup limit and usage when a limit is set, otherwise
+    /// the host figures.
+    #[default]
+    Auto,
+    /// The cgroup (v2 or v1) memory limit and usage; falls back to the host
+    /// figures if no limit is found.
+    Cgroup,
+    /// The resident set size of the CacheBolt process against the cgroup limit,
+    /// or the host total if there is none.
+    Process,
+    /// Host-wide used and total memory.
+    System,
+}
+
+/// What to do with entries whose freshness lifetime (`expires_at`) has passed.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiredPolicy {
+    /// Expired entries are never served and are dropped on lookup.
+    Reject,
+    /// Expired entries are only served as a fallback (downstream errors,
+    /// latency failover, concurrency saturation), unless `must-revalidate` was set.
+    #[default]
+    ServeStaleOnError,
 }
 
 /// Cache-related settings for memory usage and re-cache policies.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct CacheSettings {
     /// Memory usage threshold as a percentage (e.g., 80 = 80%).
     pub memory_threshold: usize,
 
+    /// Where memory usage and limit are read from for `memory_threshold`.
+    #[serde(default)]
+    pub memory_source: MemorySource,
+
     /// Percentage of fallback requests that should attempt revalidation.
     #[serde(default)]
     pub refresh_percentage: u8,
@@ -39,6 +92,46 @@ pub struct CacheSettings {
     /// Time-to-live (TTL) for cached responses in seconds.
     #[serde(default)]
     pub ttl_seconds: u64,
+
+    /// Whether fresh entries are served directly (`read_through`) or only used
+    /// as a fallback (`fallback`, default).
+    #[serde(default)]
+    pub mode: CacheMode,
+
+    /// How expired entries are treated on lookup.
+    #[serde(default)]
+    pub expired_policy: ExpiredPolicy,
+
+    /// How long past `expires_at` a stale entry may still be served on error
+    /// (0 = no limit). Only used with `serve_stale_on_error`.
+    #[serde(default)]
+    pub max_stale_seconds: u64,
+
+    /// Interval of the background sweeper that purges unservable entries
+    /// from memory (0 disables the sweeper).
+    #[serde(default = "default_sweep_interval_seconds")]
+    pub sweep_interval_seconds: u64,
+
+    /// Largest response body that is cached, in bytes (0 = no limit).
+    /// Larger bodies are streamed to the client without being cached.
+    #[serde(default = "default_max_cacheable_body_bytes")]
+    pub max_cacheable_body_bytes: usize,
+
+    /// Memory budget of the in-memory cache in bytes, counting bodies, headers
+    /// and keys (0 = no limit). A TinyLFU admission policy picks which entries
+    /// are evicted (or rejected) to stay under it.
+    #[serde(default)]
+    pub max_memory_bytes: usize,
+}
+
+/// Default interval for the expired-entry sweeper
+fn default_sweep_interval_seconds() -> u64 {
+    60
+}
+
+/// Default limit for caching response bodies (10 MiB)
+fn default_max_cacheable_body_bytes() -> usize {
+    10 * 1024 * 1024
 }
 
 /// Describes latency thresholds per path to decide when to fallback to the cache.
@@ -52,7 +145,7 @@ pub struct MaxLatencyRule {
 }
 
 /// Fallback configuration based on request latency.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct LatencyFailover {
     /// Default latency limit in milliseconds if no rule matches.
     pub default_max_latency_ms: u64,
@@ -62,9 +155,445 @@ pub struct LatencyFailover {
     pub path_rules: Vec<MaxLatencyRule>,
 }
 
+/// Whether upstream `Cache-Control` / `Expires` directives decide what and how long to cache.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheControlPolicy {
+    /// Honor `no-store`, `private`, `max-age`, `s-maxage`, `must-revalidate` and `Expires`.
+    #[default]
+    Respect,
+    /// Ignore upstream directives and cache every 2xx for `cache.ttl_seconds`.
+    Override,
+}
+
+/// Per-path override of the upstream cache directive policy.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CacheControlRule {
+    /// Regex pattern to match request paths (e.g., ^/api/products).
+    pub pattern: String,
+
+    /// Policy applied to responses for matching paths.
+    pub policy: CacheControlPolicy,
+}
+
+/// Configuration for honoring upstream caching headers.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CacheControlSettings {
+    /// Policy used if no path rule matches.
+    #[serde(default)]
+    pub default_policy: CacheControlPolicy,
+
+    /// Specific path-based rules, applied in order.
+    #[serde(default)]
+    pub path_rules: Vec<CacheControlRule>,
+}
+
+/// Per-path stale serving windows.
+#[derive(Debug, Deserialize, Clone)]
+pub struct StaleRule {
+    /// Regex pattern to match request paths (e.g., ^/api/products).
+    pub pattern: String,
+
+    /// Seconds past expiry during which a stale entry is served while it is
+    /// refreshed in the background.
+    #[serde(default)]
+    pub stale_while_revalidate_seconds: u64,
+
+    /// Seconds past expiry during which a stale entry is served if the
+    /// downstream fails, times out or answers with a 5xx.
+    #[serde(default)]
+    pub stale_if_error_seconds: u64,
+}
+
+/// Configuration for `stale-while-revalidate` / `stale-if-error` behavior.
+/// Windows default to 0 (disabled); upstream `Cache-Control` extensions of
+/// the same name take precedence when the path respects upstream directives.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct StaleSettings {
+    /// Default stale-while-revalidate window in seconds if no rule matches.
+    #[serde(default)]
+    pub stale_while_revalidate_seconds: u64,
+
+    /// Default stale-if-error window in seconds if no rule matches.
+    #[serde(default)]
+    pub stale_if_error_seconds: u64,
+
+    /// Max time (ms) to wait for downstream response headers before treating
+    /// the request as failed (0 = no timeout).
+    #[serde(default)]
+    pub upstream_timeout_ms: u64,
+
+    /// Specific path-based rules, applied in order.
+    #[serde(default)]
+    pub path_rules: Vec<StaleRule>,
+}
+
+/// How the request side of the cache key is built.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStrategy {
+    /// URI plus every request header not listed in `ignored_headers`.
+    #[default]
+    AllHeaders,
+    /// URI plus `header_allowlist`, plus the request headers named in the
+    /// upstream response's `Vary` header.
+    Vary,
+}
+
+/// Per-route template for the URI part of the cache key.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CacheKeyRule {
+    /// Regex pattern to match request URIs (e.g., ^/search).
+    pub pattern: String,
+
+    /// Sort query parameters so their order doesn't matter.
+    #[serde(default)]
+    pub sort_query: bool,
+
+    /// Query parameters removed from the key; a trailing `*` matches a
+    /// prefix (e.g. `utm_*`, `fbclid`).
+    #[serde(default)]
+    pub drop_params: Vec<String>,
+
+    /// If set, only these query parameters are kept in the key.
+    #[serde(default)]
+    pub param_allowlist: Option<Vec<String>>,
+
+    /// Lowercase the path (the query string is left untouched).
+    #[serde(default)]
+    pub lowercase_path: bool,
+
+    /// Cookies whose values are added to the key.
+    #[serde(default)]
+    pub include_cookies: Vec<String>,
+
+    /// Request headers added to the key on top of those selected by `strategy`.
+    #[serde(default)]
+    pub include_headers: Vec<String>,
+}
+
+/// Configuration of cache key derivation.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CacheKeySettings {
+    /// Which request headers take part in the key.
+    #[serde(default)]
+    pub strategy: KeyStrategy,
+
+    /// Request headers always included in the key with the `vary` strategy
+    /// (case-insensitive).
+    #[serde(default)]
+    pub header_allowlist: Vec<String>,
+
+    /// Route-specific key templates, applied in order (first match wins).
+    #[serde(default)]
+    pub path_rules: Vec<CacheKeyRule>,
+}
+
+/// Opt-in rule that enables caching of POST requests for matching paths.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PostCacheRule {
+    /// Regex pattern to match request paths (e.g., ^/graphql).
+    pub pattern: String,
+}
+
+/// Caching of POST requests (e.g. GraphQL or search endpoints), keyed on a
+/// hash of the request body. POST is never cached unless a rule matches.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PostCaching {
+    /// Largest request body (bytes) buffered for hashing; larger requests
+    /// are proxied without caching.
+    #[serde(default = "default_post_max_body_bytes")]
+    pub max_body_bytes: usize,
+
+    /// Paths whose POST responses may be cached, applied in order.
+    #[serde(default)]
+    pub path_rules: Vec<PostCacheRule>,
+}
+
+impl Default for PostCaching {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: default_post_max_body_bytes(),
+            path_rules: vec![],
+        }
+    }
+}
+
+/// Default limit for buffering cacheable POST bodies (1 MiB)
+fn default_post_max_body_bytes() -> usize {
+    1024 * 1024
+}
+
+/// Settings for de-duplicating concurrent upstream fetches of the same cache key.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RequestCoalescing {
+    /// Whether concurrent misses for the same key share a single upstream fetch.
+    #[serde(default = "default_coalescing_enabled")]
+    pub enabled: bool,
+
+    /// How long (ms) followers wait for the leader before falling back to the cache.
+    #[serde(default = "default_coalescing_wait_timeout_ms")]
+    pub wait_timeout_ms: u64,
+}
+
+impl Default for RequestCoalescing {
+    fn default() -> Self {
+        Self {
+            enabled: default_coalescing_enabled(),
+            wait_timeout_ms: default_coalescing_wait_timeout_ms(),
+        }
+    }
+}
+
+/// Coalescing is on unless explicitly disabled
+fn default_coalescing_enabled() -> bool {
+    true
+}
+
+/// Default follower wait before falling back to the cache
+fn default_coalescing_wait_timeout_ms() -> u64 {
+    5000
+}
+
+/// Startup warm-up of the in-memory cache from the persistent backend.
+#[derive(Debug, Deserialize, Clone)]
+pub struct WarmupSettings {
+    /// Whether the most recent persisted entries are loaded into memory at startup.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Most recent entries to load (0 = no limit).
+    #[serde(default = "default_warmup_max_entries")]
+    pub max_entries: usize,
+
+    /// Memory budget for the warm-up in bytes, counted like `cache.max_memory_bytes`
+    /// (0 = no limit besides the cache budget itself).
+    #[serde(default)]
+    pub max_bytes: usize,
+
+    /// Number of entries fetched from the backend concurrently.
+    #[serde(default = "default_warmup_concurrency")]
+    pub concurrency: usize,
+}
+
+impl Default for WarmupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_entries: default_warmup_max_entries(),
+            max_bytes: 0,
+            concurrency: default_warmup_concurrency(),
+        }
+    }
+}
+
+/// Default number of entries loaded by the warm-up
+fn default_warmup_max_entries() -> usize {
+    1000
+}
+
+/// Default number of concurrent backend fetches during the warm-up
+fn default_warmup_concurrency() -> usize {
+    8
+}
+
+/// How writes reach the persistent storage tiers.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WritePolicy {
+    /// Every entry is written to all tiers (default).
+    #[default]
+    WriteThrough,
+    /// Entries are written to the first tier right away and to the lower
+    /// tiers after `storage.write_back_delay_ms`; rewrites in between are coalesced.
+    WriteBack,
+}
+
+/// Ordered persistent storage tiers below the in-memory cache.
+#[derive(Debug, Deserialize, Clone)]
+pub struct StorageSettings {
+    /// Tiers from fastest to slowest, e.g. `[local, s3]`. Lookups go through
+    /// them in order and hits are promoted to the tiers above.
+    /// Empty = `storage_backend` alone.
+    #[serde(default)]
+    pub tiers: Vec<StorageBackend>,
+
+    /// Write-through (default) or write-back.
+    #[serde(default)]
+    pub write_policy: WritePolicy,
+
+    /// Delay before write-back entries reach the lower tiers, in milliseconds.
+    #[serde(default = "default_write_back_delay_ms")]
+    pub write_back_delay_ms: u64,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            tiers: Vec::new(),
+            write_policy: WritePolicy::default(),
+            write_back_delay_ms: default_write_back_delay_ms(),
+        }
+    }
+}
+
+/// Default write-back delay for the lower storage tiers
+fn default_write_back_delay_ms() -> u64 {
+    5000
+}
+
+/// What the persistence queue does with a new entry when it is full.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueFullPolicy {
+    /// The entry is spilled to the journal, or dropped without one (default).
+    /// The request never waits on the persistent backend.
+    #[default]
+    Drop,
+    /// The request waits until the queue has room.
+    Block,
+}
+
+/// Background queue that writes new cache entries to the persistent storage.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PersistenceSettings {
+    /// Maximum number of entries waiting to be persisted.
+    #[serde(default = "default_persist_queue_capacity")]
+    pub queue_capacity: usize,
+
+    /// What happens to new entries while the queue is full: `drop` or `block`.
+    #[serde(default)]
+    pub full_policy: QueueFullPolicy,
+
+    /// Retries of a failed write before it is spilled or dropped (0 = no retries).
+    #[serde(default = "default_persist_max_retries")]
+    pub max_retries: u32,
+
+    /// Delay before the first retry, in milliseconds; doubled on every retry.
+    #[serde(default = "default_persist_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+
+    /// Upper bound of the retry delay, in milliseconds.
+    #[serde(default = "default_persist_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+
+    /// Local file where entries that could not be persisted are spilled and
+    /// replayed from on the next start; queued entries are written ahead to
+    /// `<journal_path>.wal`. Empty = no journal.
+    #[serde(default)]
+    pub journal_path: String,
+
+    /// How long shutdown waits for the queue to drain, in seconds.
+    #[serde(default = "default_persist_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for PersistenceSettings {
+    fn default() -> Self {
+        Self {
+            queue_capacity: default_persist_queue_capacity(),
+            full_policy: QueueFullPolicy::default(),
+            max_retries: default_persist_max_retries(),
+            initial_backoff_ms: default_persist_initial_backoff_ms(),
+            max_backoff_ms: default_persist_max_backoff_ms(),
+            journal_path: String::new(),
+            shutdown_timeout_secs: default_persist_shutdown_timeout_secs(),
+        }
+    }
+}
+
+/// Default number of queued persistence jobs
+fn default_persist_queue_capacity() -> usize {
+    100
+}
+
+/// Default retries of a failed persistent write
+fn default_persist_max_retries() -> u32 {
+    3
+}
+
+/// Default delay before the first retry
+fn default_persist_initial_backoff_ms() -> u64 {
+    200
+}
+
+/// Default upper bound of the retry delay
+fn default_persist_max_backoff_ms() -> u64 {
+    5000
+}
+
+/// Default time allowed to drain the queue on shutdown
+fn default_persist_shutdown_timeout_secs() -> u64 {
+    10
+}
+
+/// Cache tags (surrogate keys) that group entries for purging.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CacheTagSettings {
+    /// Response headers listing the tags of an entry, separated by spaces or commas.
+    #[serde(default = "default_tag_headers")]
+    pub headers: Vec<String>,
+
+    /// Remove the tag headers from responses sent to clients. Tags are still
+    /// recorded with the entry.
+    #[serde(default)]
+    pub strip_from_response: bool,
+}
+
+impl Default for CacheTagSettings {
+    fn default() -> Self {
+        Self {
+            headers: default_tag_headers(),
+            strip_from_response: false,
+        }
+    }
+}
+
+/// Default tag header
+fn default_tag_headers() -> Vec<String> {
+    vec!["surrogate-key".into()]
+}
+
+/// Layout and disk quota of the `local` backend.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LocalStorageSettings {
+    /// Directory holding the cache files, one subdirectory per `app_id`.
+    #[serde(default = "default_local_root")]
+    pub root: String,
+
+    /// Disk quota of this app's cache files in bytes (0 = unlimited). Past it,
+    /// the least recently read entries are removed.
+    #[serde(default)]
+    pub max_bytes: u64,
+
+    /// Share of `max_bytes` the cleanup brings usage back down to, in percent.
+    #[serde(default = "default_local_cleanup_target_percent")]
+    pub cleanup_target_percent: u8,
+}
+
+impl Default for LocalStorageSettings {
+    fn default() -> Self {
+        Self {
+            root: default_local_root(),
+            max_bytes: 0,
+            cleanup_target_percent: default_local_cleanup_target_percent(),
+        }
+    }
+}
+
+/// Default directory of the `local` backend
+fn default_local_root() -> String {
+    "storage/cache".into()
+}
+
+/// Default usage left after a quota cleanup
+fn default_local_cleanup_target_percent() -> u8 {
+    90
+}
+
 /// Main configuration structure loaded from a YAML file.
 /// Defines all tunable behavior of the application.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct Config {
     /// Application identifier, used for namespacing cache keys or logs.
     pub app_id: String,
@@ -78,6 +607,14 @@ pub struct Config {
     /// Azure Blob Storage container name.
     pub azure_container: String,
 
+    /// Redis connection URL, e.g. `redis://:password@host:6379/0` (used by the `redis` backend).
+    #[serde(default)]
+    pub redis_url: String,
+
+    /// SQLite database file holding every entry (used by the `sqlite` backend).
+    #[serde(default = "default_sqlite_path")]
+    pub sqlite_path: String,
+
     /// Max number of concurrent requests allowed by the proxy.
     pub max_concurrent_requests: usize,
 
@@ -90,8 +627,50 @@ pub struct Config {
     /// Latency-based failover rules.
     pub latency_failover: LatencyFailover,
 
-    /// Backend to use for persistent cache storage.
-    pub storage_backend: StorageBackend,
+    /// Whether upstream `Cache-Control` / `Expires` headers are respected, per path.
+    #[serde(default)]
+    pub cache_control: CacheControlSettings,
+
+    /// Stale-while-revalidate and stale-if-error windows, per path.
+    #[serde(default)]
+    pub stale: StaleSettings,
+
+    /// How cache keys are derived from requests.
+    #[serde(default)]
+    pub cache_key: CacheKeySettings,
+
+    /// Opt-in caching of POST requests, per path.
+    #[serde(default)]
+    pub post_caching: PostCaching,
+
+    /// Response headers holding cache tags, for purging entries by tag.
+    #[serde(default)]
+    pub cache_tags: CacheTagSettings,
+
+    /// Single-flight de-duplication of concurrent misses on the same key.
+    #[serde(default)]
+    pub request_coalescing: RequestCoalescing,
+
+    /// Optional warm-up of the in-memory cache from the persistent backend at startup.
+    #[serde(default)]
+    pub warmup: WarmupSettings,
+
+    /// Backend to use for persistent cache storage when `storage.tiers` is empty.
+    /// Required unless `storage.tiers` is set.
+    #[serde(default)]
+    pub storage_backend: Option<StorageBackend>,
+
+    /// Ordered persistent storage tiers, replacing `storage_backend` when set.
+    #[serde(default)]
+    pub storage: StorageSettings,
+
+    /// Retries, overflow policy and journal of the persistence queue.
+    #[serde(default)]
+    pub persistence: PersistenceSettings,
+
+    /// Root directory and disk quota of the `local` backend.
+    #[serde(default)]
+    pub local_storage: LocalStorageSettings,
 
     /// Number of allowed failures for a storage backend before treating it as unhealthy.
     /// Must be a positive integer (0 is allowed to disable the circuit breaker).
@@ -113,6 +692,11 @@ pub struct Config {
     pub admin_port: u16,
 }
 
+/// Default database file of the `sqlite` backend
+fn default_sqlite_path() -> String {
+    "storage/cachebolt.sqlite3".into()
+}
+
 /// Default port for proxy service
 fn default_proxy_port() -> u16 {
     3000
@@ -142,18 +726,36 @@ impl Config {
         // Deserialize YAML into the Config struct
         let parsed: Config = serde_yaml::from_str(&contents)?;
 
-        // Validate required fields based on selected backend
-        match parsed.storage_backend {
-            StorageBackend::Gcs if parsed.gcs_bucket.trim().is_empty() => {
-                return Err("GCS backend selected but gcs_bucket is empty.".into());
-            }
-            StorageBackend::S3 if parsed.s3_bucket.trim().is_empty() => {
-                return Err("S3 backend selected but s3_bucket is empty.".into());
+        // Validate required fields based on the selected backends
+        let tiers = parsed.storage_tiers();
+        if tiers.is_empty() {
+            return Err("storage_backend is required unless storage.tiers is set.".into());
+        }
+        for (i, tier) in tiers.iter().enumerate() {
+            match tier {
+                StorageBackend::Gcs if parsed.gcs_bucket.trim().is_empty() => {
+                    return Err("GCS backend selected but gcs_bucket is empty.".into());
+                }
+                StorageBackend::S3 if parsed.s3_bucket.trim().is_empty() => {
+                    return Err("S3 backend selected but s3_bucket is empty.".into());
+                }
+                StorageBackend::Azure if parsed.azure_container.trim().is_empty() => {
+                    return Err("Azure backend selected but azure_container is empty.".into());
+                }
+                StorageBackend::Redis if parsed.redis_url.trim().is_empty() => {
+                    return Err("Redis backend selected but redis_url is empty.".into());
+                }
+                StorageBackend::Sqlite if parsed.sqlite_path.trim().is_empty() => {
+                    return Err("SQLite backend selected but sqlite_path is empty.".into());
+                }
+                StorageBackend::Local if parsed.local_storage.root.trim().is_empty() => {
+                    return Err("Local backend selected but local_storage.root is empty.".into());
+                }
+                _ => {}
             }
-            StorageBackend::Azure if parsed.azure_container.trim().is_empty() => {
-                return Err("Azure backend selected but azure_container is empty.".into());
+            if tiers[..i].contains(tier) {
+                return Err(format!("storage.tiers lists {tier:?} more than once.").into());
             }
-            _ => {}
         }
 
         // Validate app_id
@@ -166,6 +768,24 @@ impl Config {
             return Err("cache.memory_threshold must be between 1 and 100.".into());
         }
 
+        // Validate the persistence queue (tokio channels cannot be empty)
+        if parsed.persistence.queue_capacity == 0 {
+            return Err("persistence.queue_capacity must be at least 1.".into());
+        }
+
+        // Validate the local disk quota cleanup target
+        let target = parsed.local_storage.cleanup_target_percent;
+        if target == 0 || target > 100 {
+            return Err("local_storage.cleanup_target_percent must be between 1 and 100.".into());
+        }
+
+        // Validate the cache tag headers
+        for name in &parsed.cache_tags.headers {
+            if hyper::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(format!("cache_tags.headers: '{name}' is not a valid header name.").into());
+            }
+        }
+
         // Log latency failover rules
         if parsed.latency_failover.path_rules.is_empty() {
             tracing::info!(
@@ -185,6 +805,16 @@ impl Config {
         Ok(parsed)
     }
 
+    /// Persistent storage tiers from fastest to slowest: `storage.tiers`, or
+    /// `storage_backend` alone when no tiers are configured. Empty if neither is set.
+    pub fn storage_tiers(&self) -> Vec<StorageBackend> {
+        if self.storage.tiers.is_empty() {
+            self.storage_backend.into_iter().collect()
+        } else {
+            self.storage.tiers.clone()
+        }
+    }
+
     /// Returns the list of headers to ignore (lowercased).
     pub fn ignored_headers_set(&self) -> HashSet<String> {
         let mut ignored = self
@@ -200,6 +830,11 @@ impl Config {
         ignored.insert("x-refresh-cache".to_string());
         ignored.insert("cache-control".to_string());
 
+        // Conditional headers are answered from the cache, never part of the key
+        for header in CONDITIONAL_HEADERS {
+            ignored.insert(header.to_string());
+        }
+
         ignored
     }
 }
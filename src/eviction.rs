@@ -16,7 +16,9 @@
 use std::time::Duration;
 use tokio::task;
 
-use crate::memory::memory::{MEMORY_CACHE, get_memory_usage_kib, maybe_evict_if_needed};
+use crate::config::CONFIG;
+use crate::memory::memory::{MEMORY_CACHE, get_memory_usage_kib, maybe_evict_if_needed, purge_expired};
+use crate::memory::pressure;
 
 /// Launches a continuous background task to monitor system memory usage and
 /// perform cache eviction dynamically under pressure.
@@ -24,7 +26,7 @@ use crate::memory::memory::{MEMORY_CACHE, get_memory_usage_kib, maybe_evict_if_n
 /// The logic operates as follows:
 /// - Every second, it reads the current memory usage of the system.
 /// - If the current usage (in percent) exceeds the last observed usage,
-///   it triggers a check to evict entries from the in-memory LRU cache.
+///   it triggers a check to evict entries from the in-memory cache.
 /// - This complements the on-write eviction and adds adaptive behavior under load.
 ///
 /// This mechanism ensures the cache remains efficient and avoids OOM conditions,
@@ -38,11 +40,10 @@ where
 
         loop {
             let (used_kib, total_kib) = get_usage();
-            let current_percent = used_kib * 100 / total_kib;
+            let current_percent = pressure::percent(used_kib, total_kib);
 
             if current_percent > last_usage_percent {
-                let mut cache = MEMORY_CACHE.write().await;
-                maybe_evict_if_needed(&mut cache).await;
+                maybe_evict_if_needed(&MEMORY_CACHE).await;
             }
 
             last_usage_percent = current_percent;
@@ -53,7 +54,41 @@ where
     tracing::info!("🧠 Background memory eviction task started");
 }
 
-// Mantén esta para uso real
+/// Starts the background eviction task on the memory figures selected by
+/// `cache.memory_source`.
 pub fn start_background_eviction_task() {
+    let usage = pressure::current_usage();
+    tracing::info!(
+        "🧠 Measuring memory pressure from {} figures ({}% of {} KiB used)",
+        pressure::source_label(usage.source),
+        usage.percent(),
+        usage.total_kib
+    );
     start_background_eviction_task_with(get_memory_usage_kib);
 }
+
+/// Launches a background task that periodically purges entries that can no
+/// longer be served (see `cache.expired_policy` and `cache.max_stale_seconds`)
+/// from the in-memory cache.
+///
+/// The interval is read from `cache.sweep_interval_seconds`; 0 disables the task.
+pub fn start_expiry_sweeper_task() {
+    let interval_secs = CONFIG
+        .get()
+        .map(|c| c.cache.sweep_interval_seconds)
+        .unwrap_or(0);
+
+    if interval_secs == 0 {
+        tracing::info!("⌛ Expired entry sweeper disabled (interval = 0s)");
+        return;
+    }
+
+    task::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+            purge_expired().await;
+        }
+    });
+
+    tracing::info!("⌛ Expired entry sweeper started (every {}s)", interval_secs);
+}
@@ -1,3 +1,6 @@
+pub mod cache_key;
+pub mod cache_tags;
+pub mod coalescing;
 pub mod config;
 pub mod eviction;
 pub mod memory;
@@ -5,4 +8,9 @@ pub mod proxy;
 pub mod rules;
 pub mod storage;
 pub mod admin;
+pub mod warmup;
 
+
+/// Hooks for the integration tests; only built with the `test-util` feature.
+#[cfg(feature = "test-util")]
+pub mod test_util;
@@ -18,17 +18,21 @@
 // These are internal modules for handling the proxy logic, caching layers,
 // configuration loading, and in-memory eviction based on memory pressure.
 mod admin;
+mod cache_key;
+mod cache_tags;
+mod coalescing;
 mod config;
 mod eviction;
 mod memory;
 mod proxy;
 mod rules;
 mod storage;
+mod warmup;
 
 // ----------------------
 // External dependencies
 // ----------------------
-use axum::{Router, routing::delete, routing::get}; // Axum: Web framework for routing and request handling
+use axum::{Router, routing::any, routing::delete, routing::get, routing::post}; // Axum: Web framework for routing and request handling
 use hyper::Server; // Hyper: High-performance HTTP server
 use std::{net::SocketAddr, process::exit}; // Network + system utilities
 
@@ -36,15 +40,21 @@ use clap::Parser; // CLI argument parsing (via `--config`)
 use tracing::{error, info, warn}; // Structured logging macros
 use tracing_subscriber::EnvFilter; // Log filtering via LOG_LEVEL
 
-use crate::admin::clean::invalidate_handler;
+use crate::admin::backends::{get_backend_status, get_backend_usage};
+use crate::admin::clean::{
+    invalidate_handler, invalidate_keys_handler, invalidate_paths_handler, invalidate_url_handler,
+    purge_tags_handler,
+};
 use crate::admin::status_memory::get_memory_cache_status;
 use crate::admin::ui::{embedded_ui_handler, embedded_ui_index};
 // ----------------------
 // Internal dependencies
 // ----------------------
-use crate::config::{CONFIG, Config, StorageBackend}; // App-wide config definitions
-use crate::eviction::start_background_eviction_task; // Memory pressure eviction
-use crate::storage::{azure, gcs, s3}; // Persistent storage backends
+use crate::config::{CONFIG, Config}; // App-wide config definitions
+use crate::eviction::{start_background_eviction_task, start_expiry_sweeper_task}; // Memory pressure eviction + expiry sweeper
+use crate::storage::purge; // Soft purge rules of the persistent storage
+use crate::storage::queue::start_journal_replay; // Replay of entries spilled by the previous run
+use crate::warmup::start_warmup_task; // Background warm-up of the memory cache
 use metrics_exporter_prometheus::PrometheusBuilder;
 
 use hyper::http::{HeaderValue, Method, header};
@@ -81,7 +91,6 @@ struct Args {
 /// Initializes structured logging using the `LOG_LEVEL` environment variable.
 /// Falls back to "info" if not set. Avoids using `RUST_LOG` to provide
 /// a more consistent developer experience.
-
 fn init_logging(app_id: &str) {
     let filter = EnvFilter::try_new(std::env::var("LOG_LEVEL").unwrap_or_else(|_| "info".into()))
         .unwrap_or_else(|_| EnvFilter::new("info"));
@@ -96,46 +105,26 @@ fn init_logging(app_id: &str) {
 }
 
 /// -----------------------------------------
-/// BACKEND INITIALIZATION DISPATCHER
+/// BACKEND INITIALIZATION
 /// -----------------------------------------
-/// Based on the `storage_backend` defined in the loaded config,
-/// initializes the appropriate persistent cache client.
-/// Supports: GCS, S3, Azure Blob, and Local (no-op).
-
+/// Initializes the clients of the persistent storage tiers defined in the
+/// loaded config (`storage.tiers`, or `storage_backend` alone).
 async fn init_selected_backend() {
-    match CONFIG.get().map(|c| &c.storage_backend) {
-        Some(StorageBackend::Gcs) => {
-            // Initializes Google Cloud Storage client (authenticated via ADC or env vars)
-            let gcs_config = google_cloud_storage::client::ClientConfig::default()
-                .with_auth()
-                .await
-                .expect("❌ Failed to authenticate with GCS");
-
-            let client = google_cloud_storage::client::Client::new(gcs_config);
-            if gcs::GCS_CLIENT.set(client).is_err() {
-                warn!("⚠️ GCS_CLIENT was already initialized");
-            } else {
-                info!("✅ GCS client initialized successfully");
-            }
-        }
-        Some(StorageBackend::S3) => {
-            // Initializes AWS S3 client using env vars: AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, AWS_REGION
-            s3::init_s3_client().await;
-            info!("✅ AWS S3 client initialized successfully");
-        }
-        Some(StorageBackend::Azure) => {
-            // Initializes Azure Blob Storage using env vars: AZURE_STORAGE_ACCOUNT, AZURE_STORAGE_ACCESS_KEY
-            azure::init_azure_client();
-            info!("✅ Azure Blob client initialized successfully");
-        }
-        Some(StorageBackend::Local) => {
-            // No initialization needed for local file-based caching
-            info!("🗄 Local storage backend selected (no setup required).");
-        }
-        None => {
-            error!("❌ No storage backend configured. Terminating execution.");
-            exit(1);
-        }
+    let Some(backend) = storage::backend() else {
+        error!("❌ No storage backend configured. Terminating execution.");
+        exit(1);
+    };
+
+    if let Err(e) = backend.init().await {
+        error!("❌ Failed to initialize the {} backend: {e}", backend.name());
+        exit(1);
+    }
+    info!("✅ {} storage backend initialized successfully", backend.name());
+
+    match purge::load(backend.as_ref()).await {
+        Ok(0) => {}
+        Ok(count) => info!("🧽 Loaded {count} soft purge rules"),
+        Err(e) => warn!("⚠️ Failed to load the soft purge rules: {e}"),
     }
 }
 
@@ -179,41 +168,64 @@ async fn main() {
         .set(config)
         .expect("❌ CONFIG was already initialized");
 
-    // Ensure proxy's cached threshold reads the configured value now
-    crate::proxy::init_storage_backend_threshold();
-    crate::proxy::init_backend_retry_interval_config();
-
     // ------------------------------------------------------
-    // 5. Initialize persistent storage backend (GCS, S3, Azure, Local)
+    // 5. Initialize persistent storage tiers (GCS, S3, Azure, Local)
     // ------------------------------------------------------
     init_selected_backend().await;
 
+    // ------------------------------------------------------
+    // 5b. Warm the memory cache from the persistent backend
+    //     Runs in the background so the listener binds right away.
+    // ------------------------------------------------------
+    start_warmup_task();
+
+    // ------------------------------------------------------
+    // 5c. Requeue entries spilled to the persistence journal
+    //     by the previous run (no-op without a journal).
+    // ------------------------------------------------------
+    start_journal_replay();
+
     // ------------------------------------------------------
     // 6. Start the background memory eviction task
     //    This task monitors system memory usage and evicts
     //    in-memory cache entries when usage exceeds threshold.
+    //    The expiry sweeper purges entries past their lifetime.
     // ------------------------------------------------------
     start_background_eviction_task();
+    start_expiry_sweeper_task();
 
     // ------------------------------------------------------
     // 7. Define Axum router with a single wildcard route
-    //    All incoming GET requests will be handled by the proxy logic.
+    //    Requests of every method are handled by the proxy logic.
     // ------------------------------------------------------
     let cors = CorsLayer::new()
         .allow_origin("http://localhost:4321".parse::<HeaderValue>().unwrap()) // o use HeaderValue::from_static(...)
-        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_methods([
+            Method::GET,
+            Method::HEAD,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
         .allow_headers([header::CONTENT_TYPE]);
 
     // 8. Build Proxy Router (main traffic)
     let proxy_router = Router::new()
-        .route("/", get(proxy::proxy_handler))
-        .route("/*path", get(proxy::proxy_handler))
+        .route("/", any(proxy::proxy_handler))
+        .route("/*path", any(proxy::proxy_handler))
         .layer(cors.clone());
 
     // 9. Build Admin Router (admin + metrics)
     let admin_router = Router::new()
         .route("/admin/api/cache", delete(invalidate_handler))
+        .route("/admin/api/invalidate/url", post(invalidate_url_handler))
+        .route("/admin/api/invalidate/keys", post(invalidate_keys_handler))
+        .route("/admin/api/invalidate/paths", post(invalidate_paths_handler))
+        .route("/admin/api/purge/tags", post(purge_tags_handler))
         .route("/admin/api/status", get(get_memory_cache_status))
+        .route("/admin/api/backends", get(get_backend_status))
+        .route("/admin/api/backends/usage", get(get_backend_usage))
         .route("/admin", get(embedded_ui_index))
         .route("/admin/", get(embedded_ui_index))
         .route("/admin/*path", get(embedded_ui_handler))
@@ -234,9 +246,24 @@ async fn main() {
         admin_addr, admin_addr
     );
 
-    // 11. Start both servers concurrently
-    let proxy_server = Server::bind(&proxy_addr).serve(proxy_router.into_make_service());
-    let admin_server = Server::bind(&admin_addr).serve(admin_router.into_make_service());
+    // 11. Start both servers concurrently; both stop accepting new
+    //     connections on Ctrl+C / SIGTERM and finish in-flight requests.
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(());
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("🛑 Shutdown signal received, stopping servers");
+        let _ = shutdown_tx.send(());
+    });
+    let wait_for_shutdown = |mut rx: tokio::sync::watch::Receiver<()>| async move {
+        let _ = rx.changed().await;
+    };
+
+    let proxy_server = Server::bind(&proxy_addr)
+        .serve(proxy_router.into_make_service())
+        .with_graceful_shutdown(wait_for_shutdown(shutdown_rx.clone()));
+    let admin_server = Server::bind(&admin_addr)
+        .serve(admin_router.into_make_service())
+        .with_graceful_shutdown(wait_for_shutdown(shutdown_rx));
 
     // ------------------------------------------------------
     // 12. Start serving HTTP requests using Axum and Hyper
@@ -249,4 +276,43 @@ async fn main() {
     if let Err(e) = admin_result {
         error!("❌ Admin server exited with error: {}", e);
     }
+
+    // ------------------------------------------------------
+    // 13. Persist what is still queued before exiting
+    // ------------------------------------------------------
+    storage::queue::shutdown().await;
+}
+
+/// ---------------------------
+/// SHUTDOWN SIGNAL
+/// ---------------------------
+/// Resolves on Ctrl+C, or on SIGTERM on Unix (e.g. `docker stop`, Kubernetes).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("❌ Failed to listen for Ctrl+C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("❌ Failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
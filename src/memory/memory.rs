@@ -12,49 +12,471 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::config::CONFIG;
+use crate::config::{CONFIG, ExpiredPolicy};
+use crate::memory::pressure;
 use bytes::Bytes;
-use lru::LruCache;
+use moka::future::Cache;
+use moka::notification::RemovalCause;
+use moka::ops::compute::Op;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
 use once_cell::sync::Lazy;
-use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
 use std::sync::Arc;
-use sysinfo::System;
-use tokio::sync::RwLock;
+use metrics::{counter, gauge};
 use tracing::info;
 use chrono::{DateTime, Utc}; 
 
 /// Structure representing an HTTP response cached in memory.
-/// This includes the full response body and a simplified list of headers.
+/// This includes the full response body and a simplified list of headers,
+/// plus the freshness lifetime resolved when the entry was stored.
 #[derive(Clone)]
 pub struct CachedResponse {
+    /// Status of the downstream response; only 2xx responses are cached.
+    pub status: u16,
     pub body: Bytes,
     pub headers: Vec<(String, String)>,
-    #[allow(dead_code)]
     pub inserted_at: DateTime<Utc>,
+    /// Point in time after which the entry is no longer fresh.
+    pub expires_at: DateTime<Utc>,
+    /// Upstream sent `must-revalidate`: never serve once stale without revalidating.
+    pub must_revalidate: bool,
+    /// Seconds past `expires_at` the entry may be served while being refreshed.
+    pub stale_while_revalidate: u64,
+    /// Seconds past `expires_at` the entry may be served if the downstream fails.
+    pub stale_if_error: u64,
+    /// Human-readable source the cache key was hashed from.
+    pub key_source: String,
+    /// Cache tags from the configured tag headers, sorted (see `cache_tags`).
+    pub tags: Vec<String>,
+    /// Soft-purged by the admin API: never fresh nor served while revalidating,
+    /// so the next request goes downstream, but still served when the
+    /// downstream fails, within the entry's original lifetime and stale windows.
+    pub purged: bool,
 }
 
-/// Type alias for the thread-safe, shared in-memory cache structure.
-/// It uses Tokio's `RwLock` and an `Arc` to enable concurrent reads and mutation across tasks.
-type SharedCache = Arc<RwLock<LruCache<String, CachedResponse, RandomState>>>;
+impl CachedResponse {
+    /// Returns the value of a stored response header (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Approximate memory held by the entry: body, headers, key source, tags
+    /// and the struct itself. Used for the `cache.max_memory_bytes` budget.
+    pub fn size_bytes(&self) -> usize {
+        let headers = self
+            .headers
+            .iter()
+            .map(|(k, v)| k.len() + v.len() + 2 * std::mem::size_of::<String>())
+            .sum::<usize>();
+        let tags = self
+            .tags
+            .iter()
+            .map(|t| t.len() + std::mem::size_of::<String>())
+            .sum::<usize>();
+        std::mem::size_of::<Self>() + self.body.len() + headers + self.key_source.len() + tags
+    }
+
+    /// Returns `true` if the entry carries an `ETag` or `Last-Modified`
+    /// validator and can therefore be revalidated with a conditional request.
+    pub fn has_validators(&self) -> bool {
+        self.header("etag").is_some() || self.header("last-modified").is_some()
+    }
+
+    /// Returns `true` while the entry is within its freshness lifetime and
+    /// has not been soft-purged.
+    pub fn is_fresh(&self) -> bool {
+        !self.purged && self.within_lifetime()
+    }
+
+    /// Returns `true` before `expires_at`, whether or not the entry was soft-purged.
+    fn within_lifetime(&self) -> bool {
+        Utc::now() < self.expires_at
+    }
+
+    /// Returns `true` if the entry is stale but still inside the given window
+    /// (seconds past `expires_at`). `must-revalidate` entries are never served stale.
+    fn within_stale_window(&self, window_secs: u64) -> bool {
+        !self.must_revalidate
+            && window_secs > 0
+            && Utc::now() < self.expires_at + chrono::Duration::seconds(window_secs as i64)
+    }
+
+    /// Returns `true` if the stale entry may be served while it is refreshed
+    /// in the background (stale-while-revalidate).
+    pub fn can_serve_while_revalidating(&self) -> bool {
+        !self.purged && self.within_stale_window(self.stale_while_revalidate)
+    }
+
+    /// Returns `true` if the entry may be served in place of a failed downstream
+    /// response (stale-if-error). Only entries with a stale-if-error window opt in;
+    /// for those, fresh and soft-purged unexpired entries always qualify.
+    pub fn can_serve_on_error(&self) -> bool {
+        self.stale_if_error > 0
+            && (self.within_lifetime() || self.within_stale_window(self.stale_if_error))
+    }
+
+    /// Returns `true` if the entry may still be served, either because it is
+    /// fresh, inside one of its stale windows, or because `cache.expired_policy`
+    /// allows serving it stale. Soft-purged entries stay servable on the same terms.
+    pub fn is_servable(&self) -> bool {
+        if self.within_lifetime()
+            || self.within_stale_window(self.stale_while_revalidate.max(self.stale_if_error))
+        {
+            return true;
+        }
+
+        let (policy, max_stale_secs) = CONFIG
+            .get()
+            .map(|c| (c.cache.expired_policy, c.cache.max_stale_seconds))
+            .unwrap_or_default();
+
+        match policy {
+            ExpiredPolicy::Reject => false,
+            ExpiredPolicy::ServeStaleOnError => {
+                !self.must_revalidate
+                    && (max_stale_secs == 0
+                        || Utc::now()
+                            < self.expires_at + chrono::Duration::seconds(max_stale_secs as i64))
+            }
+        }
+    }
+}
+
+/// Concurrent cache of responses bounded by the bytes held by its entries.
+///
+/// Backed by `moka`, which shards its hash table and records accesses without
+/// taking a cache-wide lock, so lookups do not serialize on each other. Entries
+/// are weighted by `entry_weight`; when `cache.max_memory_bytes` is set, a
+/// TinyLFU admission policy decides whether a new entry may evict existing ones,
+/// so a burst of one-off keys does not flush frequently requested entries.
+///
+/// Bookkeeping (evictions, size counters) is applied lazily by `moka`; `len`
+/// and `bytes` flush it first so they report exact figures.
+///
+/// A tag index maps every cache tag to the keys of the entries carrying it.
+/// Removed entries leave the index through the eviction listener; an entry
+/// replaced by one with other tags may linger until its old tags are purged,
+/// so purges check the tags of the current entry.
+pub struct MemoryCache {
+    entries: Cache<String, CachedResponse>,
+    max_bytes: usize,
+    tags: Arc<TagIndex>,
+}
+
+/// Keys of the entries carrying each tag.
+type TagIndex = DashMap<String, HashSet<String>>;
+
+/// Drops `key` from the index entries of `tags`.
+fn unindex(index: &TagIndex, key: &str, tags: &[String]) {
+    for tag in tags {
+        if let Entry::Occupied(mut keys) = index.entry(tag.clone()) {
+            keys.get_mut().remove(key);
+            if keys.get().is_empty() {
+                keys.remove();
+            }
+        }
+    }
+}
+
+impl MemoryCache {
+    /// Creates an empty cache bounded by `cache.max_memory_bytes`.
+    pub fn new() -> Self {
+        let max_bytes = max_memory_bytes();
+        let tags = Arc::new(TagIndex::new());
+        let index = Arc::clone(&tags);
+        let mut builder = Cache::builder()
+            .weigher(|key: &String, value: &CachedResponse| {
+                u32::try_from(entry_weight(key, value)).unwrap_or(u32::MAX)
+            })
+            .eviction_listener(move |key, value: CachedResponse, cause| {
+                if cause == RemovalCause::Size {
+                    counter!("cachebolt_memory_budget_evictions_total").increment(1);
+                    info!("🧹 Evicted key '{}' from MEMORY_CACHE to stay within budget", key);
+                }
+                // The replacing entry has already indexed its own tags
+                if cause != RemovalCause::Replaced {
+                    unindex(&index, &key, &value.tags);
+                }
+            });
+        if max_bytes != usize::MAX {
+            builder = builder.max_capacity(max_bytes as u64);
+        }
+
+        MemoryCache {
+            entries: builder.build(),
+            max_bytes,
+            tags,
+        }
+    }
+
+    /// Number of entries in the cache.
+    pub async fn len(&self) -> usize {
+        self.entries.run_pending_tasks().await;
+        self.entries.entry_count() as usize
+    }
+
+    /// Returns `true` if the cache holds no entries. Does not flush pending
+    /// bookkeeping, so it may lag a few inserts behind.
+    pub fn is_empty(&self) -> bool {
+        self.entries.entry_count() == 0
+    }
+
+    /// Bytes currently held by the cache (keys plus `CachedResponse::size_bytes`).
+    pub async fn bytes(&self) -> usize {
+        self.entries.run_pending_tasks().await;
+        self.entries.weighted_size() as usize
+    }
+
+    /// Returns a copy of an entry and records the access for the admission policy.
+    pub async fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.entries.get(key).await
+    }
+
+    /// Iterates over the entries in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Arc<String>, CachedResponse)> + '_ {
+        self.entries.iter()
+    }
+
+    /// Inserts an entry, replacing any previous one under the same key. While
+    /// over `cache.max_memory_bytes`, the admission policy evicts cold entries
+    /// or rejects the new one. An entry larger than the whole budget is not
+    /// cached at all.
+    pub async fn put(&self, key: String, value: CachedResponse) {
+        let weight = entry_weight(&key, &value);
+
+        if weight > self.max_bytes {
+            self.entries.invalidate(&key).await;
+            counter!("cachebolt_memory_rejected_entries_total").increment(1);
+            info!(
+                "📦 Entry '{}' ({} bytes) exceeds cache.max_memory_bytes, not cached in memory",
+                key,
+                weight
+            );
+            return;
+        }
+
+        for tag in &value.tags {
+            self.tags.entry(tag.clone()).or_default().insert(key.clone());
+        }
+        self.entries.insert(key, value).await;
+        self.record();
+    }
+
+    /// Removes an entry.
+    pub async fn pop(&self, key: &str) -> Option<CachedResponse> {
+        let value = self.entries.remove(key).await;
+        self.record();
+        value
+    }
+
+    /// Removes every entry for which `matches(key, entry)` is `true` and
+    /// returns how many were removed.
+    pub async fn remove_where(&self, matches: impl Fn(&str, &CachedResponse) -> bool) -> usize {
+        let keys = self
+            .entries
+            .iter()
+            .filter(|(k, v)| matches(k, v))
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+
+        let mut removed = 0;
+        for key in keys {
+            if self.pop(&key).await.is_some() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Keys of the entries currently tagged with at least one of `tags`.
+    async fn tagged_keys(&self, tags: &[String]) -> Vec<String> {
+        let mut keys = HashSet::new();
+        for tag in tags {
+            if let Some(tagged) = self.tags.get(tag) {
+                keys.extend(tagged.iter().cloned());
+            }
+        }
+
+        let mut current = Vec::with_capacity(keys.len());
+        for key in keys {
+            match self.entries.get(&key).await {
+                Some(entry) if entry.tags.iter().any(|tag| tags.contains(tag)) => current.push(key),
+                // Stale index entry: the key was since stored with other tags
+                _ => unindex(&self.tags, &key, tags),
+            }
+        }
+        current
+    }
+
+    /// Removes every entry tagged with at least one of `tags` and returns how
+    /// many were removed.
+    pub async fn remove_tagged(&self, tags: &[String]) -> usize {
+        let mut removed = 0;
+        for key in self.tagged_keys(tags).await {
+            if self.pop(&key).await.is_some() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Soft-purges an entry (see `CachedResponse::purged`). Returns `false` if
+    /// there is no entry under `key`.
+    pub async fn mark_purged(&self, key: &str) -> bool {
+        let result = self
+            .entries
+            .entry_by_ref(key)
+            .and_compute_with(|current| async move {
+                match current.map(|entry| entry.into_value()) {
+                    Some(entry) if !entry.purged => Op::Put(CachedResponse {
+                        purged: true,
+                        ..entry
+                    }),
+                    _ => Op::Nop,
+                }
+            })
+            .await;
+        result.into_entry().is_some()
+    }
+
+    /// Soft-purges every entry for which `matches(key, entry)` is `true` and
+    /// returns how many matched.
+    pub async fn mark_purged_where(&self, matches: impl Fn(&str, &CachedResponse) -> bool) -> usize {
+        let keys = self
+            .entries
+            .iter()
+            .filter(|(k, v)| matches(k, v))
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+
+        let mut marked = 0;
+        for key in keys {
+            if self.mark_purged(&key).await {
+                marked += 1;
+            }
+        }
+        marked
+    }
+
+    /// Soft-purges every entry tagged with at least one of `tags` and returns
+    /// how many were found.
+    pub async fn mark_tagged_purged(&self, tags: &[String]) -> usize {
+        let mut marked = 0;
+        for key in self.tagged_keys(tags).await {
+            if self.mark_purged(&key).await {
+                marked += 1;
+            }
+        }
+        marked
+    }
+
+    /// Returns every key, from the oldest inserted entry to the newest. Used to
+    /// shed memory under pressure, where no access order is available.
+    pub fn keys_oldest_first(&self) -> Vec<Arc<String>> {
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|(k, v)| (v.inserted_at, k))
+            .collect::<Vec<_>>();
+        entries.sort_unstable();
+        entries.into_iter().map(|(_, k)| k).collect()
+    }
+
+    /// Removes every entry.
+    pub async fn clear(&self) {
+        self.entries.invalidate_all();
+        self.entries.run_pending_tasks().await;
+        self.record();
+    }
+
+    /// Publishes the current size of the cache. Figures may lag behind
+    /// pending evictions by a few operations.
+    fn record(&self) {
+        gauge!("cachebolt_memory_cache_bytes").set(self.entries.weighted_size() as f64);
+        gauge!("cachebolt_memory_cache_entries").set(self.entries.entry_count() as f64);
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bytes accounted for an entry stored under `key`.
+fn entry_weight(key: &str, value: &CachedResponse) -> usize {
+    key.len() + value.size_bytes()
+}
+
+/// Returns `cache.max_memory_bytes`, with 0 meaning no limit.
+fn max_memory_bytes() -> usize {
+    match CONFIG.get().map(|c| c.cache.max_memory_bytes) {
+        Some(0) | None => usize::MAX,
+        Some(max) => max,
+    }
+}
 
 /// Global singleton instance of the in-memory cache.
-/// Internally it uses an unbounded LRU (Least Recently Used) strategy and is guarded by a read-write lock.
-/// Eviction is not time-based or size-based but rather triggered by system memory usage thresholds.
-pub static MEMORY_CACHE: Lazy<SharedCache> = Lazy::new(|| {
-    info!("🧠 Initializing unbounded LRU MEMORY_CACHE with dynamic memory-based eviction");
-    Arc::new(RwLock::new(LruCache::unbounded_with_hasher(
-        RandomState::default(),
-    )))
+/// It is safe to share across tasks without an outer lock (see `MemoryCache`).
+/// Entries are evicted to stay within `cache.max_memory_bytes`, and also when
+/// system memory usage crosses `cache.memory_threshold`.
+pub static MEMORY_CACHE: Lazy<MemoryCache> = Lazy::new(|| {
+    info!("🧠 Initializing MEMORY_CACHE with TinyLFU admission and size- and memory-based eviction");
+    MemoryCache::new()
 });
 
 /// Attempts to retrieve a response from the in-memory cache.
-/// Returns `Some(CachedResponse)` if the key exists, otherwise `None`.
+/// Returns `Some(CachedResponse)` if the key exists and is still servable
+/// (fresh, or stale but allowed by `cache.expired_policy`), otherwise `None`.
+/// Unservable entries are dropped on lookup.
 ///
 /// # Arguments
 /// * `key` - A unique string key used to identify the cached response.
 pub async fn get_from_memory(key: &str) -> Option<CachedResponse> {
-    let mut cache = MEMORY_CACHE.write().await;
-    cache.get(key).cloned()
+    let entry = MEMORY_CACHE.get(key).await?;
+
+    if entry.is_servable() {
+        return Some(entry);
+    }
+
+    MEMORY_CACHE.pop(key).await;
+    counter!("cachebolt_expired_evictions_total").increment(1);
+    info!("⌛ Dropped expired key '{}' from MEMORY_CACHE", key);
+    None
+}
+
+/// Returns a copy of the entry stored under `key`, whether or not it is still
+/// servable. Used to revalidate expired entries with a conditional request.
+/// Unlike `get_from_memory`, unservable entries are kept.
+pub async fn peek_from_memory(key: &str) -> Option<CachedResponse> {
+    MEMORY_CACHE.get(key).await
+}
+
+/// Removes every entry that can no longer be served from the in-memory cache.
+///
+/// # Returns
+/// The number of entries purged.
+pub async fn purge_expired() -> usize {
+    let expired = MEMORY_CACHE
+        .iter()
+        .filter(|(_, v)| !v.is_servable())
+        .map(|(k, _)| k)
+        .collect::<Vec<_>>();
+
+    for key in &expired {
+        MEMORY_CACHE.pop(key).await;
+    }
+
+    if !expired.is_empty() {
+        counter!("cachebolt_expired_evictions_total").increment(expired.len() as u64);
+        info!("🧹 Purged {} expired entries from MEMORY_CACHE", expired.len());
+    }
+
+    expired.len()
 }
 
 /// Loads one or more entries into the in-memory cache and optionally triggers eviction if memory is constrained.
@@ -62,64 +484,71 @@ pub async fn get_from_memory(key: &str) -> Option<CachedResponse> {
 /// # Arguments
 /// * `data` - A vector of (key, CachedResponse) pairs to be inserted into the cache.
 pub async fn load_into_memory(data: Vec<(String, CachedResponse)>) {
-    let mut cache = MEMORY_CACHE.write().await;
-
     for (k, v) in data {
-        cache.put(k.clone(), v);
+        MEMORY_CACHE.put(k.clone(), v).await;
         
         info!("✅ Inserted key '{}' into MEMORY_CACHE", k);
     }
 
-    maybe_evict_if_needed(&mut cache).await;
+    maybe_evict_if_needed(&MEMORY_CACHE).await;
 }
 
-/// Monitors system memory usage and evicts LRU entries if usage exceeds the configured threshold.
+/// Entries evicted between two memory usage readings under pressure.
+const EVICTION_BATCH: usize = 64;
+
+/// Monitors system memory usage and evicts the oldest entries if usage exceeds the configured threshold.
 /// This function is designed to prevent the application from consuming too much system memory.
 ///
 /// The threshold is defined in `config.yaml` under `cache.memory_threshold`.
 ///
 /// # Arguments
-/// * `cache` - A mutable reference to the global LRU cache to perform eviction on.
-pub async fn maybe_evict_if_needed(cache: &mut LruCache<String, CachedResponse, RandomState>) {
+/// * `cache` - The cache to perform eviction on.
+pub async fn maybe_evict_if_needed(cache: &MemoryCache) {
+    // Nothing to evict, no need to query system memory. A lagging count
+    // only defers eviction to the next check.
+    if cache.is_empty() {
+        return;
+    }
+
     let config = CONFIG.get();
     let threshold_percent = config
         .map(|c| c.cache.memory_threshold)
         .unwrap_or(80);
 
-    let (used_kib, total_kib) = get_memory_usage_kib();
-    let usage_percent = used_kib * 100 / total_kib;
+    let usage_percent = pressure::current_usage().percent();
 
     if usage_percent >= threshold_percent as u64 {
         
         info!(
-            "⚠️ MEMORY_CACHE over threshold ({}% used). Cleaning LRU...",
-            usage_percent
+            "⚠️ MEMORY_CACHE over threshold ({}% used, cache holds {} bytes). Evicting oldest entries...",
+            usage_percent,
+            cache.bytes().await
         );
 
-        // Continue evicting entries until usage falls below threshold or the cache is empty
-        while (get_memory_usage_kib().0 * 100 / total_kib) >= threshold_percent as u64 {
-            if let Some((oldest_key, _)) = cache.pop_lru() {
-                
-                info!("🧹 Evicted key '{}' from MEMORY_CACHE", oldest_key);
-            } else {
-                
+        // Evict the oldest entries in batches until usage falls below threshold
+        // or the cache is empty, reading memory usage once per batch
+        let mut oldest = cache.keys_oldest_first().into_iter();
+        while pressure::current_usage().percent() >= threshold_percent as u64 {
+            let batch = oldest.by_ref().take(EVICTION_BATCH).collect::<Vec<_>>();
+            if batch.is_empty() {
                 break; // Nothing left to evict
             }
+            for key in batch {
+                if cache.pop(&key).await.is_some() {
+                    info!("🧹 Evicted key '{}' from MEMORY_CACHE", key);
+                }
+            }
         }
     }
 }
 
-/// Retrieves the current system memory usage statistics from the operating system.
+/// Retrieves the current memory usage from the source configured in
+/// `cache.memory_source` (cgroup, process RSS or host, see `pressure`).
 ///
 /// # Returns
 /// A tuple representing the used and total memory in KiB (kibibytes).
 /// * `(used_kib, total_kib)`
 pub fn get_memory_usage_kib() -> (u64, u64) {
-    let mut sys = System::new();
-    sys.refresh_memory();
-
-    let used = sys.used_memory(); // in KiB
-    let total = sys.total_memory(); // in KiB
-
-    (used, total)
-}
\ No newline at end of file
+    let usage = pressure::current_usage();
+    (usage.used_kib, usage.total_kib)
+}
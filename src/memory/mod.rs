@@ -12,4 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub mod memory;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod memory;
+pub mod pressure;
@@ -0,0 +1,181 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Memory pressure detection.
+///
+/// Host-wide figures describe the node, not the container: inside Kubernetes a
+/// pod can be OOM-killed at its limit while the node still looks half empty.
+/// This module reads the cgroup v2 / v1 memory controller (or the process RSS)
+/// so `cache.memory_threshold` is measured against the memory we can actually use.
+use metrics::gauge;
+use std::fs;
+use std::path::Path;
+use sysinfo::{ProcessesToUpdate, System, get_current_pid};
+
+use crate::config::{CONFIG, MemorySource};
+
+/// Mount point of the cgroup filesystem.
+pub const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Memory figures used for eviction decisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub used_kib: u64,
+    pub total_kib: u64,
+    /// Where the figures were read from (never `Auto`).
+    pub source: MemorySource,
+}
+
+impl MemoryUsage {
+    /// Usage as a percentage of the total.
+    pub fn percent(&self) -> u64 {
+        percent(self.used_kib, self.total_kib)
+    }
+}
+
+/// `used_kib` as a percentage of `total_kib`, guarding against a zero total.
+pub fn percent(used_kib: u64, total_kib: u64) -> u64 {
+    used_kib * 100 / total_kib.max(1)
+}
+
+/// Reads memory usage from the source configured in `cache.memory_source`
+/// and publishes it as gauges.
+pub fn current_usage() -> MemoryUsage {
+    let source = CONFIG
+        .get()
+        .map(|c| c.cache.memory_source)
+        .unwrap_or_default();
+    let usage = read_usage(source, Path::new(CGROUP_ROOT));
+
+    let label = source_label(usage.source);
+    gauge!("cachebolt_memory_used_bytes", "source" => label).set((usage.used_kib * 1024) as f64);
+    gauge!("cachebolt_memory_limit_bytes", "source" => label).set((usage.total_kib * 1024) as f64);
+
+    usage
+}
+
+/// Reads memory usage from `source`, looking for cgroup files under `cgroup_root`.
+///
+/// # Arguments
+/// - `source`: Configured memory source.
+/// - `cgroup_root`: Mount point of the cgroup filesystem.
+pub fn read_usage(source: MemorySource, cgroup_root: &Path) -> MemoryUsage {
+    match source {
+        MemorySource::Auto | MemorySource::Cgroup => {
+            cgroup_usage(cgroup_root).unwrap_or_else(system_usage)
+        }
+        MemorySource::Process => process_usage(cgroup_root),
+        MemorySource::System => system_usage(),
+    }
+}
+
+/// Reads the working set and limit of the cgroup mounted at `root`,
+/// trying cgroup v2 first and then v1. The working set excludes inactive
+/// page cache, which the kernel reclaims before hitting the limit.
+///
+/// # Returns
+/// `None` if no cgroup memory controller is found or no limit is set.
+pub fn cgroup_usage(root: &Path) -> Option<MemoryUsage> {
+    let limit = cgroup_limit_bytes(root)?;
+
+    let (usage, inactive) = if root.join("memory.max").exists() {
+        (
+            read_u64(&root.join("memory.current"))?,
+            stat_value(&root.join("memory.stat"), "inactive_file"),
+        )
+    } else {
+        let v1 = root.join("memory");
+        (
+            read_u64(&v1.join("memory.usage_in_bytes"))?,
+            stat_value(&v1.join("memory.stat"), "total_inactive_file"),
+        )
+    };
+
+    Some(MemoryUsage {
+        used_kib: usage.saturating_sub(inactive) / 1024,
+        total_kib: limit / 1024,
+        source: MemorySource::Cgroup,
+    })
+}
+
+/// Returns the cgroup memory limit in bytes, or `None` if unlimited.
+/// cgroup v2 reports `max`; v1 reports a huge number, so any limit at or
+/// above the host's memory is treated as no limit.
+fn cgroup_limit_bytes(root: &Path) -> Option<u64> {
+    let limit = match fs::read_to_string(root.join("memory.max")) {
+        Ok(v2) if v2.trim() == "max" => return None,
+        Ok(v2) => v2.trim().parse().ok()?,
+        Err(_) => read_u64(&root.join("memory").join("memory.limit_in_bytes"))?,
+    };
+
+    let mut sys = System::new();
+    sys.refresh_memory();
+    (limit < sys.total_memory()).then_some(limit)
+}
+
+/// Resident set size of this process against the cgroup limit (or host total).
+fn process_usage(cgroup_root: &Path) -> MemoryUsage {
+    let mut sys = System::new();
+    sys.refresh_memory();
+
+    let rss = get_current_pid().ok().and_then(|pid| {
+        sys.refresh_processes(ProcessesToUpdate::Some(&[pid]), false);
+        sys.process(pid).map(|p| p.memory())
+    });
+    let limit = cgroup_limit_bytes(cgroup_root).unwrap_or(sys.total_memory());
+
+    MemoryUsage {
+        used_kib: rss.unwrap_or_default() / 1024,
+        total_kib: limit / 1024,
+        source: MemorySource::Process,
+    }
+}
+
+/// Host-wide used and total memory.
+fn system_usage() -> MemoryUsage {
+    let mut sys = System::new();
+    sys.refresh_memory();
+
+    MemoryUsage {
+        used_kib: sys.used_memory() / 1024,
+        total_kib: sys.total_memory() / 1024,
+        source: MemorySource::System,
+    }
+}
+
+/// Reads a file holding a single integer.
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Reads one `name value` line from a cgroup `memory.stat` file (0 if absent).
+fn stat_value(path: &Path, name: &str) -> u64 {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Metric label for a memory source.
+pub fn source_label(source: MemorySource) -> &'static str {
+    match source {
+        MemorySource::Auto => "auto",
+        MemorySource::Cgroup => "cgroup",
+        MemorySource::Process => "process",
+        MemorySource::System => "system",
+    }
+}
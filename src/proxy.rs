@@ -12,26 +12,34 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use axum::response::IntoResponse;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use hyper::client::HttpConnector;
 use hyper_rustls::HttpsConnector;
 type HttpsClient = Client<HttpsConnector<HttpConnector>>;
-use hyper::{Body, Client, Request, Response};
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::body::HttpBody;
+use hyper::{Body, Client, HeaderMap, Method, Request, Response, StatusCode};
 use once_cell::sync::Lazy;
 use sha2::{Digest, Sha256};
 use std::sync::Arc;
-use tokio::sync::{Semaphore, mpsc};
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use tokio::time::Instant;
 
-use crate::config::{CONFIG, StorageBackend};
+use crate::cache_key::{self, CacheKey};
+use crate::cache_tags;
+use crate::coalescing::{self, Flight, FlightGuard, SharedResponse};
+use crate::config::{CONFIG, CacheMode};
 use crate::memory::memory;
 use crate::rules::bypass::should_bypass_cache;
+use crate::rules::cache_control::{Freshness, freshness_for_response};
+use crate::rules::conditional::{CONDITIONAL_HEADERS, Conditionals, NOT_MODIFIED_HEADERS};
+use crate::rules::methods::is_cacheable_method;
 use crate::rules::latency::{get_max_latency_for_path, mark_latency_fail, should_failover};
 use crate::rules::refresh::should_refresh;
-use crate::storage::{azure, gcs, local, s3};
+use crate::storage::{self, purge};
 
 use metrics::{counter, histogram};  //✅
-use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering}; 
 
 
 
@@ -51,6 +59,9 @@ pub static MAX_CONCURRENT_REQUESTS: Lazy<usize> = Lazy::new(|| {
 pub static SEMAPHORE: Lazy<Arc<Semaphore>> =
     Lazy::new(|| Arc::new(Semaphore::new(*MAX_CONCURRENT_REQUESTS)));
 
+/// Serializes vary record updates, so variants stored concurrently are all listed.
+static VARY_RECORDS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
 /// Shared HTTP client for all outbound requests
 static HTTP_CLIENT: Lazy<HttpsClient> = Lazy::new(|| {
     let https = hyper_rustls::HttpsConnectorBuilder::new()
@@ -63,106 +74,8 @@ static HTTP_CLIENT: Lazy<HttpsClient> = Lazy::new(|| {
 
 
 
-// Add an atomic counter for bucket access errors
-static BUCKET_ACCESS_ERRORS: Lazy<AtomicUsize> = Lazy::new(|| AtomicUsize::new(0));
-
-/// Cached threshold read (evaluated once, after CONFIG is set). 0 = disabled.
-static STORAGE_BACKEND_FAILURES_THRESHOLD: Lazy<usize> =
-    Lazy::new(|| CONFIG.get().map(|c| c.storage_backend_failures).unwrap_or(0));
-
-/// Force evaluation of the cached threshold (call from main after CONFIG.set(...))
-pub fn init_storage_backend_threshold() {
-    let _ = *STORAGE_BACKEND_FAILURES_THRESHOLD;
-}
-
-/// Cached backend retry interval (secs). Evaluated once after CONFIG.set(...) is called in main.
-/// 0 = disabled
-static BACKEND_RETRY_INTERVAL_SECS_CONFIG: Lazy<u64> =
-    Lazy::new(|| CONFIG.get().map(|c| c.backend_retry_interval_secs).unwrap_or(0));
-
-/// Force evaluation of the cached backend retry interval (call from main after CONFIG.set(...))
-pub fn init_backend_retry_interval_config() {
-    let _ = *BACKEND_RETRY_INTERVAL_SECS_CONFIG;
-}
-
-// Circuit breaker boolean: true = backend considered healthy, false = unhealthy.
-// Initialized true (will be set by the checker).
-pub(crate) static CIRCUIT_BREAKER: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
-
-
-/// Background task that persistently writes cache entries to the configured backend
-static CACHE_WRITER: Lazy<mpsc::Sender<(String, Bytes, Vec<(String, String)>)>> = Lazy::new(|| {
-    let (tx, mut rx) = mpsc::channel::<(String, Bytes, Vec<(String, String)>)>(100);
-    tokio::spawn(async move {
-        while let Some((key, data, headers)) = rx.recv().await {
-            let backend_label = CONFIG
-                .get()
-                .map(|c| format!("{:?}", c.storage_backend))
-                .unwrap_or("unknown".to_string());
-            counter!("cachebolt_persist_attempts_total", "backend" => backend_label.clone())
-                .increment(1);
-            match CONFIG.get().map(|c| &c.storage_backend) {
-                Some(StorageBackend::Azure) => azure::store_in_cache(key.clone(), data.clone(), headers.clone()).await,
-                Some(StorageBackend::Gcs) => gcs::store_in_cache(key.clone(), data.clone(), headers.clone()).await,
-                Some(StorageBackend::Local) => local::store_in_cache(key.clone(), data.clone(), headers.clone()).await,
-                Some(StorageBackend::S3) => {
-                    // If circuit breaker is tripped, skip S3 writes
-                    if CIRCUIT_BREAKER.load(Ordering::SeqCst) {
-                        tracing::warn!("Skipping S3 write because circuit breaker is tripped (key={})", key);
-                    } else if let Err(e) = s3::store_in_cache(key.clone(), data.clone(), headers.clone()).await {
-                        tracing::error!("❌ Error storing in S3: {}", e);
-                        if is_bucket_access_error(&*e) {
-                            let new_count = BUCKET_ACCESS_ERRORS.fetch_add(1, Ordering::Relaxed) + 1;
-                            let allowed = *STORAGE_BACKEND_FAILURES_THRESHOLD;
-                            tracing::warn!(
-                                "Bucket access error contado (store) {}/{} (key={}, circuit_breaker={})",
-                                new_count,
-                                allowed,
-                                key,
-                                CIRCUIT_BREAKER.load(Ordering::SeqCst)
-                            );
-                            if allowed > 0 && new_count as usize > allowed {
-                                // trip the breaker and start background recovery checker from s3 module
-                                CIRCUIT_BREAKER.store(true, Ordering::SeqCst);
-                                let interval = *BACKEND_RETRY_INTERVAL_SECS_CONFIG;
-                                tracing::error!(
-                                    "Bucket access errors exceeded threshold ({} > {}). Tripping breaker and starting health checker ({}s)",
-                                    new_count,
-                                    allowed,
-                                    interval
-                                );
-                                // Reset counter before launching recovery checker so it can be reused
-                                BUCKET_ACCESS_ERRORS.store(0, Ordering::Relaxed);
-                                // start checker in s3 module; it will set CIRCUIT_BREAKER=false on success
-                                crate::storage::s3::start_s3_health_checker(interval);
-                            }
-                        }
-                    }
-                }
-                None => {
-                    tracing::error!("CONFIG not initialized. Unable to persist cache.");
-                }
-            }
-        }
-    });
-    tx
-});
-
-
-/// Determines if an error is related to bucket access issues (network, permissions, etc.) using the link
-/// https://docs.rs/aws-sdk-s3/latest/aws_sdk_s3/error/type.SdkError.html
-/// To consider for the future to add complexity of SdkError using aws_smithy_ and aws_sdk_s3::operation::head_bucket::HeadBucketError
-fn is_bucket_access_error(e: &dyn std::error::Error) -> bool {
-    let msg = e.to_string().to_lowercase();
-    msg.contains("dispatch failure")
-        || msg.contains("connection refused")
-        || msg.contains("connect error")
-        || msg.contains("timeout")
-        || msg.contains("error accessing bucket")
-}
-
 /// Main proxy handler that receives incoming requests and delegates to downstream or cache
-pub async fn proxy_handler(req: Request<Body>) -> impl IntoResponse {
+pub async fn proxy_handler(mut req: Request<Body>) -> impl IntoResponse {
     let uri = req.uri().to_string();
     tracing::debug!("🔗 Received request for URI: {}", uri);
 
@@ -174,70 +87,156 @@ pub async fn proxy_handler(req: Request<Body>) -> impl IntoResponse {
     // Increment total request counter for each URI
     counter!("cachebolt_proxy_requests_total", "uri" => uri.clone()).increment(1);
 
-    // Fetch ignored headers set from config (lowercased for comparison)
-    let ignored = CONFIG
-        .get()
-        .map(|c| c.ignored_headers_set().clone())
-        .unwrap_or_default();
-
-    // Extract and normalize headers, excluding those in the ignored set
-    let mut headers_kv = req
-        .headers()
-        .iter()
-        .filter(|(k, _)| {
-            let key_lower = k.as_str().to_ascii_lowercase();
-            !ignored.contains(&key_lower)
-        })
-        .map(|(k, v)| {
-            (
-                k.as_str().to_ascii_lowercase(),      // normalize key
-                v.to_str().unwrap_or("").to_string(), // safe string conversion
-            )
-        })
-        .collect::<Vec<_>>();
+    // Writes and other non-idempotent methods never touch the cache
+    let method = req.method().clone();
+    if !is_cacheable_method(&method, &uri) {
+        return passthrough(&uri, req).await;
+    }
 
-    // Sort headers alphabetically to ensure deterministic key
-    headers_kv.sort_by(|a, b| a.0.cmp(&b.0));
+    // Client validators are answered from the cache, never forwarded as-is
+    let conditionals = Conditionals::from_headers(req.headers());
 
-    // Join headers as "key:value" pairs separated by semicolons
-    let relevant_headers = headers_kv
-        .iter()
-        .map(|(k, v)| format!("{}:{}", k, v))
-        .collect::<Vec<_>>()
-        .join(";");
+    // Cacheable POSTs are buffered so their body can be part of the key
+    let post_body = if method == Method::POST {
+        let (buffered, body) = match buffer_post_body(req).await {
+            Ok(v) => v,
+            Err(resp) => return resp,
+        };
+        req = buffered;
+        match body {
+            Some(body) => Some(body),
+            None => {
+                tracing::info!("📦 POST body for '{}' too large to cache, passing through", uri);
+                return passthrough(&uri, req).await;
+            }
+        }
+    } else {
+        None
+    };
 
-    // Compose cache key from URI and relevant headers
-    let key_source = format!("{}|{}", uri, relevant_headers);
-    let key = hash_uri(&key_source);
-    tracing::debug!("🔑 Cache key generated: {}", key);
+    // Compose cache key from the normalized URI and relevant headers (plus the body hash for POST)
+    let base = CacheKey::new(cache_key::key_source(&uri, req.headers(), post_body.as_deref()));
+    // With the vary strategy, the recorded Vary list selects the variant
+    let lookup = cache_key::resolve(&base, req.headers()).await;
+    let key = lookup.key.clone();
+    let request_headers = req.headers().clone();
+    tracing::debug!("🔑 Cache key generated: {} ({})", key, lookup.source);
 
     //Refresh force by percetange hit rule
     let bypass_cache = should_bypass_cache(req.headers());
     let force_refresh = should_refresh(&key) || bypass_cache;
 
+    // Keep any cached copy (even expired) with validators to revalidate it upstream
+    let revalidation_candidate = if bypass_cache {
+        None
+    } else {
+        memory::peek_from_memory(&key)
+            .await
+            .filter(|cached| cached.has_validators())
+    };
+
+    // In read-through mode, serve fresh entries straight from memory
+    let mode = CONFIG.get().map(|c| c.cache.mode).unwrap_or_default();
+    if mode == CacheMode::ReadThrough
+        && !force_refresh
+        && let Some(cached) = memory::get_from_memory(&key).await
+    {
+        if cached.is_fresh() {
+            tracing::debug!("✅ Fresh hit from MEMORY_CACHE for '{}'", uri);
+            counter!("cachebolt_memory_hits_total", "uri" => uri.clone()).increment(1);
+            return respond_from_cache(&conditionals, cached);
+        }
+        if cached.can_serve_while_revalidating() {
+            tracing::debug!("♻️ Serving stale '{}' while revalidating", uri);
+            counter!("cachebolt_stale_while_revalidate_total", "uri" => uri.clone()).increment(1);
+            // Only one background refresh per key; later requests keep serving stale
+            if let Flight::Leader(guard) = coalescing::join(&key) {
+                // Cacheable bodies are already buffered; the client's body is not reused
+                let (parts, _) = req.into_parts();
+                let req = Request::from_parts(parts, Body::from(post_body.unwrap_or_default()));
+                spawn_revalidation(
+                    uri.clone(),
+                    base.clone(),
+                    lookup.clone(),
+                    req,
+                    cached.clone(),
+                    guard,
+                );
+            }
+            return respond_from_cache(&conditionals, cached);
+        }
+        tracing::debug!("⌛ Cached entry for '{}' expired, forwarding", uri);
+    }
+
     // If the URI is in failover mode, serve from cache
     if should_failover(&uri) && !force_refresh {
         tracing::info!("⚠️ Using fallback for '{}'", uri);
         counter!("cachebolt_failover_total", "uri" => uri.clone()).increment(1);
-        match try_cache(&key).await {
-            Ok(resp) => return resp,
-            Err(e) => {
-                tracing::error!("❌ Error in try_cache: {}", e);
-                return Response::builder()
-                    .status(500)
-                    .body(format!("Internal error: {}", e).into())
-                    .unwrap();
+        return serve_fallback(&key).await;
+    }
+
+    // Coalesce concurrent misses: only the leader goes downstream
+    let coalescing = CONFIG
+        .get()
+        .map(|c| c.request_coalescing.clone())
+        .unwrap_or_default();
+    let flight_guard = if coalescing.enabled && !bypass_cache {
+        match coalescing::join(&key) {
+            Flight::Leader(guard) => Some(guard),
+            Flight::Follower(rx) => {
+                counter!("cachebolt_coalesced_requests_total", "uri" => uri.clone()).increment(1);
+                tracing::debug!("🔗 Waiting on in-flight request for '{}'", uri);
+                let timeout = Duration::from_millis(coalescing.wait_timeout_ms);
+                match coalescing::wait(rx, timeout).await {
+                    Ok(Some(shared))
+                        if cache_key::same_variant(
+                            &base,
+                            &lookup,
+                            &shared.headers,
+                            &request_headers,
+                        ) =>
+                    {
+                        if conditionals.is_not_modified(
+                            header_str(&shared.headers, "etag"),
+                            header_str(&shared.headers, "last-modified"),
+                        ) {
+                            return not_modified_response(&header_pairs(&shared.headers));
+                        }
+                        return shared.to_response();
+                    }
+                    Ok(Some(_)) => {
+                        // The leader fetched a different variant; fetch our own
+                        tracing::debug!("🔀 In-flight response for '{}' is another variant", uri);
+                        None
+                    }
+                    Ok(None) => return serve_fallback(&key).await,
+                    Err(_) => {
+                        tracing::warn!("⌛ Timed out waiting on in-flight request for '{}'", uri);
+                        counter!("cachebolt_coalesced_timeouts_total", "uri" => uri.clone())
+                            .increment(1);
+                        return serve_fallback(&key).await;
+                    }
+                }
             }
         }
-    }
+    } else {
+        None
+    };
 
     // Try to acquire concurrency slot
     match SEMAPHORE.clone().try_acquire_owned() {
         Ok(_permit) => {
             let start = Instant::now();
 
-            // Reconstruct request from parts (to forward it with headers)
-            let (parts, body) = req.into_parts();
+            // Reconstruct request from parts (to forward it with headers),
+            // swapping client validators for those of our cached copy
+            let (mut parts, body) = req.into_parts();
+            for name in CONDITIONAL_HEADERS {
+                parts.headers.remove(name);
+            }
+            if let Some(cached) = &revalidation_candidate {
+                add_validators(&mut parts.headers, cached);
+            }
             let req = Request::from_parts(parts, body);
 
             match forward_request(&uri, req).await {
@@ -266,82 +265,120 @@ pub async fn proxy_handler(req: Request<Body>) -> impl IntoResponse {
                             .increment(1);
                     }
 
-                    // Split response into parts
                     let (mut parts, body) = resp.into_parts();
-                    let body_bytes = hyper::body::to_bytes(body).await.unwrap_or_default();
-
-                    parts.headers.remove("content-length");
-
-                    let headers_vec = parts
-                        .headers
-                        .iter()
-                        .map(|(k, v)| {
-                            (k.as_str().to_string(), v.to_str().unwrap_or("").to_string())
-                        })
-                        .collect::<Vec<_>>();
-
-                    // Cache response in memory and send to backend storage
-                    let cached_response = memory::CachedResponse {
-                        body: body_bytes.clone(),
-                        headers: headers_vec.clone(),
-                        inserted_at: chrono::Utc::now(),
-                    };
 
-                    let status = parts.status.as_u16();
-                    let is_success = (200..300).contains(&status);
-                    let exceeded_latency = elapsed_ms > threshold_ms;
-                    let fallback_active = should_failover(&uri);
-
-                    if !bypass_cache {
-                        if is_success && (exceeded_latency || !fallback_active) {
-                            memory::load_into_memory(vec![(key.clone(), cached_response)]).await;
-                            let _ = CACHE_WRITER
-                                .send((key.clone(), body_bytes.clone(), headers_vec))
-                                .await;
-                            counter!("cachebolt_memory_store_total", "uri" => uri.clone())
-                                .increment(1);
-                        } else {
-                            tracing::info!(
-                                "⚠️ Skipping cache store for '{}' (status: {}, exceeded_latency: {}, fallback_active: {})",
-                                uri,
-                                status,
-                                exceeded_latency,
-                                fallback_active
-                            );
+                    // The downstream confirmed our cached copy: refresh it without the body
+                    if parts.status == StatusCode::NOT_MODIFIED
+                        && let Some(cached) = revalidation_candidate
+                    {
+                        tracing::debug!("🔁 '{}' not modified upstream, refreshing entry", uri);
+                        counter!("cachebolt_revalidated_not_modified_total", "uri" => uri.clone())
+                            .increment(1);
+                        let refreshed = refresh_entry(&uri, &key, cached, &parts.headers).await;
+                        if let Some(guard) = flight_guard {
+                            guard.complete(Some(Arc::new(SharedResponse {
+                                status: StatusCode::from_u16(refreshed.status)
+                                    .unwrap_or(StatusCode::OK),
+                                headers: headers_to_map(&refreshed.headers),
+                                body: refreshed.body.clone(),
+                            })));
                         }
-                    } else {
+                        return respond_from_cache(&conditionals, refreshed);
+                    }
+
+                    // stale-if-error: prefer a cached copy over a downstream 5xx
+                    if parts.status.is_server_error()
+                        && !bypass_cache
+                        && let Some(stale) = load_stale_if_error(&key).await
+                    {
+                        tracing::warn!(
+                            "🩹 Downstream returned {} for '{}', serving stale entry",
+                            parts.status,
+                            uri
+                        );
+                        counter!("cachebolt_stale_if_error_total", "uri" => uri.clone())
+                            .increment(1);
+                        // Followers fall back to the same stale entry
+                        drop(flight_guard);
+                        return entry_response(stale);
+                    }
+
+                    // The response's Vary header decides which variant key it belongs to
+                    let store_key = if bypass_cache {
                         tracing::info!(
                             "⏩ Cache bypass activated for '{}' due to client header",
                             uri
                         );
+                        None
+                    } else {
+                        let store_key = cache_key::storage_key(
+                            &base,
+                            &lookup,
+                            &parts.headers,
+                            &request_headers,
+                        );
+                        if store_key.is_none() {
+                            tracing::info!("🚫 Skipping cache store for '{}' (Vary: *)", uri);
+                            counter!("cachebolt_uncacheable_responses_total", "uri" => uri.clone())
+                                .increment(1);
+                        }
+                        store_key
+                    };
+
+                    // Stream the body to the client while it is buffered for the
+                    // cache and for coalesced followers
+                    let tee = CacheTee {
+                        uri: uri.clone(),
+                        base: base.clone(),
+                        store_key,
+                        status: parts.status,
+                        headers: parts.headers.clone(),
+                        exceeded_latency: elapsed_ms > threshold_ms,
+                        guard: flight_guard,
+                    };
+                    let body = tee.stream(body);
+
+                    // Answer the client's own conditional request locally; the body
+                    // is still read into the cache in the background
+                    if parts.status == StatusCode::OK
+                        && conditionals.is_not_modified(
+                            header_str(&parts.headers, "etag"),
+                            header_str(&parts.headers, "last-modified"),
+                        )
+                    {
+                        return not_modified_response(&header_pairs(&parts.headers));
                     }
 
-                    Response::from_parts(parts, Body::from(body_bytes))
+                    cache_tags::strip_response_headers(&mut parts.headers);
+                    Response::from_parts(parts, body)
                 }
                 Err(_) => {
                     tracing::warn!("⛔ Downstream service failed for '{}'", uri);
                     counter!("cachebolt_downstream_failures_total", "uri" => uri.clone())
                         .increment(1);
-                    match try_cache(&key).await {
-                        Ok(resp) => resp,
-                        Err(e) => {
-                            tracing::error!("❌ Error in try_cache: {}", e);
-                            Response::builder()
-                                .status(500)
-                                .body(format!("Internal error: {}", e).into())
-                                .unwrap()
-                        }
+                    // Release followers so they fall back to the cache as well
+                    drop(flight_guard);
+                    if let Some(stale) = load_stale_if_error(&key).await {
+                        counter!("cachebolt_stale_if_error_total", "uri" => uri.clone())
+                            .increment(1);
+                        return entry_response(stale);
                     }
+                    serve_fallback(&key).await
                 }
             }
         }
         Err(_) => {
-            // If over concurrency limit, fallback to cache if possible
+            // If over concurrency limit, fallback to cache if possible. Only
+            // entries a normal hit could serve qualify: fresh, or inside their
+            // stale-while-revalidate window, and never soft-purged.
             counter!("cachebolt_rejected_due_to_concurrency_total", "uri" => uri.clone())
                 .increment(1);
-            if let Some(cached) = memory::get_from_memory(&key).await {
+            if let Some(cached) = memory::get_from_memory(&key)
+                .await
+                .filter(|c| c.is_fresh() || c.can_serve_while_revalidating())
+            {
                 counter!("cachebolt_memory_hits_total", "uri" => uri.clone()).increment(1);
-                build_response(cached.body.clone(), cached.headers.clone())
+                entry_response(cached)
             } else {
                 Response::builder()
                     .status(502)
@@ -352,71 +389,555 @@ pub async fn proxy_handler(req: Request<Body>) -> impl IntoResponse {
     }
 }
 
+/// Stores a downstream response in memory and the persistent backend, if its
+/// status, upstream cache directives and latency state allow it.
+///
+/// # Arguments
+/// - `uri`: Request URI, used for per-path rules and metrics.
+/// - `base`: Base key of the request; it gets a vary record if `key` is a variant.
+/// - `key`: Cache key to store the response under, with its source.
+/// - `status`: Response status.
+/// - `headers`: Response headers (`content-length` already removed).
+/// - `body`: Fully buffered response body.
+/// - `exceeded_latency`: Whether the downstream call exceeded its latency threshold.
+async fn store_response(
+    uri: &str,
+    base: &CacheKey,
+    key: &CacheKey,
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &Bytes,
+    exceeded_latency: bool,
+) {
+    let status = status.as_u16();
+    let is_success = (200..300).contains(&status);
+    let fallback_active = should_failover(uri);
+
+    // Upstream Cache-Control / Expires decide whether and how long to cache
+    let freshness = freshness_for_response(uri, headers);
+
+    if is_success && freshness.is_none() {
+        tracing::info!("🚫 Skipping cache store for '{}' (upstream forbids storing)", uri);
+        counter!("cachebolt_uncacheable_responses_total", "uri" => uri.to_string()).increment(1);
+    } else if let Some(freshness) = freshness
+        && is_success
+        && (exceeded_latency || !fallback_active)
+    {
+        // Cache response in memory and send to backend storage
+        let cached_response = new_entry(
+            status,
+            body.clone(),
+            header_pairs(headers),
+            freshness,
+            key.source.clone(),
+        );
+        cache_key::remember_vary(base, &cached_response).await;
+        // Variants are found through the vary record under their base key
+        if key.key != base.key {
+            let _guard = VARY_RECORDS.lock().await;
+            let previous = memory::peek_from_memory(&base.key).await;
+            let record = cache_key::vary_record(base, key, &cached_response, previous.as_ref());
+            persist_entry(&base.key, record).await;
+        }
+        persist_entry(&key.key, cached_response).await;
+        counter!("cachebolt_memory_store_total", "uri" => uri.to_string()).increment(1);
+    } else {
+        tracing::info!(
+            "⚠️ Skipping cache store for '{}' (status: {}, exceeded_latency: {}, fallback_active: {})",
+            uri,
+            status,
+            exceeded_latency,
+            fallback_active
+        );
+    }
+}
+
+/// Returns `cache.max_cacheable_body_bytes`, with 0 meaning no limit.
+fn max_cacheable_body_bytes() -> usize {
+    match CONFIG.get().map(|c| c.cache.max_cacheable_body_bytes) {
+        Some(0) | None => usize::MAX,
+        Some(max) => max,
+    }
+}
+
+/// A downstream response being streamed to the client and, at the same time,
+/// buffered for the cache and for coalesced followers.
+struct CacheTee {
+    uri: String,
+    base: CacheKey,
+    /// Key to store the response under; `None` if it must not be stored.
+    store_key: Option<CacheKey>,
+    status: StatusCode,
+    headers: HeaderMap,
+    exceeded_latency: bool,
+    guard: Option<FlightGuard>,
+}
+
+impl CacheTee {
+    /// Returns a body that streams `upstream` chunk by chunk while a background
+    /// task copies it into a buffer. Once the upstream body is complete, the
+    /// buffer is stored and handed to followers.
+    ///
+    /// Bodies larger than `cache.max_cacheable_body_bytes` are streamed but not
+    /// cached. A failed upstream read aborts the client body, so the client sees
+    /// a truncated response instead of a silently empty one, and nothing is cached.
+    /// If the client goes away, the body is still read to completion for the cache.
+    fn stream(mut self, mut upstream: Body) -> Body {
+        let (mut tx, body) = Body::channel();
+        self.headers.remove("content-length");
+        let max_bytes = max_cacheable_body_bytes();
+
+        tokio::spawn(async move {
+            let mut buffer = BytesMut::new();
+            let mut cacheable = true;
+            let mut client_connected = true;
+
+            while let Some(chunk) = upstream.data().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        tracing::warn!("⛔ Downstream body for '{}' was truncated: {}", self.uri, e);
+                        counter!("cachebolt_truncated_responses_total", "uri" => self.uri.clone())
+                            .increment(1);
+                        tx.abort();
+                        return;
+                    }
+                };
+
+                if cacheable && buffer.len() + chunk.len() > max_bytes {
+                    tracing::info!(
+                        "📦 Response for '{}' exceeds {} bytes, streaming without caching",
+                        self.uri,
+                        max_bytes
+                    );
+                    counter!("cachebolt_oversized_responses_total", "uri" => self.uri.clone())
+                        .increment(1);
+                    cacheable = false;
+                    buffer = BytesMut::new();
+                } else if cacheable {
+                    buffer.extend_from_slice(&chunk);
+                }
+
+                if client_connected && tx.send_data(chunk).await.is_err() {
+                    client_connected = false;
+                }
+                if !client_connected && !cacheable {
+                    return;
+                }
+            }
+
+            if cacheable {
+                self.complete(buffer.freeze()).await;
+            }
+            // The client body ends only now, so the entry is stored by the time it is read
+            drop(tx);
+        });
+
+        body
+    }
+
+    /// Stores the fully read body and fans it out to coalesced followers.
+    async fn complete(mut self, body: Bytes) {
+        if let Some(key) = &self.store_key {
+            store_response(
+                &self.uri,
+                &self.base,
+                key,
+                self.status,
+                &self.headers,
+                &body,
+                self.exceeded_latency,
+            )
+            .await;
+        }
+
+        if let Some(guard) = self.guard {
+            cache_tags::strip_response_headers(&mut self.headers);
+            guard.complete(Some(Arc::new(SharedResponse {
+                status: self.status,
+                headers: self.headers,
+                body,
+            })));
+        }
+    }
+}
+
+/// Reads a downstream body into memory for the cache.
+///
+/// # Returns
+/// - `Ok(Some(body))` with the complete body.
+/// - `Ok(None)` if it exceeds `cache.max_cacheable_body_bytes`.
+/// - `Err` if the body could not be read completely.
+async fn read_cacheable_body(mut body: Body) -> Result<Option<Bytes>, hyper::Error> {
+    let max_bytes = max_cacheable_body_bytes();
+    let mut buffer = BytesMut::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if buffer.len() + chunk.len() > max_bytes {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+    Ok(Some(buffer.freeze()))
+}
+
+/// Builds a cache entry that is fresh from now on for `freshness.ttl_secs`,
+/// tagged from its tag headers (which are dropped if they must not reach clients).
+fn new_entry(
+    status: u16,
+    body: Bytes,
+    mut headers: Vec<(String, String)>,
+    freshness: Freshness,
+    key_source: String,
+) -> memory::CachedResponse {
+    let now = chrono::Utc::now();
+    let tags = cache_tags::from_headers(&headers);
+    cache_tags::strip_pairs(&mut headers);
+    memory::CachedResponse {
+        status,
+        body,
+        headers,
+        inserted_at: now,
+        expires_at: now + chrono::Duration::seconds(freshness.ttl_secs as i64),
+        must_revalidate: freshness.must_revalidate,
+        stale_while_revalidate: freshness.stale_while_revalidate,
+        stale_if_error: freshness.stale_if_error,
+        key_source,
+        tags,
+        purged: false,
+    }
+}
+
+/// Loads an entry into memory and queues it for the persistent backend.
+async fn persist_entry(key: &str, entry: memory::CachedResponse) {
+    memory::load_into_memory(vec![(key.to_string(), entry.clone())]).await;
+    if let Some(queue) = storage::queue::queue() {
+        queue.enqueue(key.to_string(), entry).await;
+    }
+}
+
+/// Refreshes a cached entry after the downstream answered a conditional
+/// request with `304 Not Modified`: headers from the 304 replace the stored
+/// ones, the freshness lifetime restarts and the body is kept.
+///
+/// # Returns
+/// The refreshed entry. If the updated headers forbid storing, the entry is
+/// returned for this response only and not cached again.
+async fn refresh_entry(
+    uri: &str,
+    key: &str,
+    cached: memory::CachedResponse,
+    not_modified: &HeaderMap,
+) -> memory::CachedResponse {
+    let updates = header_pairs(not_modified)
+        .into_iter()
+        .filter(|(k, _)| !matches!(k.as_str(), "content-length" | "transfer-encoding" | "connection"))
+        .collect::<Vec<_>>();
+
+    let mut headers = cached.headers;
+    headers.retain(|(k, _)| !updates.iter().any(|(name, _)| k.eq_ignore_ascii_case(name)));
+    headers.extend(updates);
+
+    match freshness_for_response(uri, &headers_to_map(&headers)) {
+        Some(freshness) => {
+            let mut entry = new_entry(cached.status, cached.body, headers, freshness, cached.key_source);
+            // The stored copy may have had its tag headers stripped
+            if entry.tags.is_empty() {
+                entry.tags = cached.tags;
+            }
+            persist_entry(key, entry.clone()).await;
+            entry
+        }
+        None => {
+            cache_tags::strip_pairs(&mut headers);
+            memory::CachedResponse { headers, ..cached }
+        }
+    }
+}
+
+/// Adds `If-None-Match` / `If-Modified-Since` from a cached entry's validators.
+fn add_validators(headers: &mut HeaderMap, cached: &memory::CachedResponse) {
+    if let Some(etag) = cached.header("etag")
+        && let Ok(value) = HeaderValue::from_str(etag)
+    {
+        headers.insert("if-none-match", value);
+    }
+    if let Some(last_modified) = cached.header("last-modified")
+        && let Ok(value) = HeaderValue::from_str(last_modified)
+    {
+        headers.insert("if-modified-since", value);
+    }
+}
+
+/// Serves a cached entry, or a `304 Not Modified` if the client already holds it.
+fn respond_from_cache(conditionals: &Conditionals, cached: memory::CachedResponse) -> Response<Body> {
+    if conditionals.is_not_modified(cached.header("etag"), cached.header("last-modified")) {
+        counter!("cachebolt_not_modified_responses_total").increment(1);
+        return not_modified_response(&cached.headers);
+    }
+    entry_response(cached)
+}
+
+/// Composes the response for a cached entry, with the status it was stored with.
+fn entry_response(cached: memory::CachedResponse) -> Response<Body> {
+    let mut response = build_response(cached.body, cached.headers);
+    *response.status_mut() = StatusCode::from_u16(cached.status).unwrap_or(StatusCode::OK);
+    response
+}
+
+/// Composes a `304 Not Modified` carrying only the headers RFC 9110 allows.
+fn not_modified_response(headers: &[(String, String)]) -> Response<Body> {
+    let mut builder = Response::builder().status(StatusCode::NOT_MODIFIED);
+    for (name, value) in headers {
+        if NOT_MODIFIED_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            builder = builder.header(name, value);
+        }
+    }
+    builder.body(Body::empty()).unwrap()
+}
+
+/// Returns a header value as a string, if present and valid.
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// Converts a `HeaderMap` into the `(name, value)` pairs stored in cache entries.
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
+        .collect()
+}
+
+/// Rebuilds a `HeaderMap` from stored `(name, value)` pairs, skipping invalid ones.
+fn headers_to_map(pairs: &[(String, String)]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in pairs {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            map.append(name, value);
+        }
+    }
+    map
+}
+
+/// Refreshes a stale entry from the downstream in a background task
+/// (stale-while-revalidate). The client has already been served the stale copy.
+/// Requests coalesced on `guard` receive the refreshed response; if the refresh
+/// fails, they fall back to the cache.
+fn spawn_revalidation(
+    uri: String,
+    base: CacheKey,
+    key: CacheKey,
+    mut req: Request<Body>,
+    stale: memory::CachedResponse,
+    guard: FlightGuard,
+) {
+    tokio::spawn(async move {
+        let Ok(_permit) = SEMAPHORE.clone().try_acquire_owned() else {
+            tracing::warn!("⏳ Skipping background revalidation for '{}' (concurrency limit)", uri);
+            return;
+        };
+
+        for name in CONDITIONAL_HEADERS {
+            req.headers_mut().remove(name);
+        }
+        add_validators(req.headers_mut(), &stale);
+
+        let Ok(resp) = forward_request(&uri, req).await else {
+            tracing::warn!("⛔ Background revalidation failed for '{}'", uri);
+            counter!("cachebolt_revalidation_failures_total", "uri" => uri.clone()).increment(1);
+            return;
+        };
+
+        let (mut parts, body) = resp.into_parts();
+        parts.headers.remove("content-length");
+
+        if parts.status == StatusCode::NOT_MODIFIED {
+            counter!("cachebolt_revalidated_not_modified_total", "uri" => uri.clone()).increment(1);
+            let refreshed = refresh_entry(&uri, &key.key, stale, &parts.headers).await;
+            guard.complete(Some(Arc::new(SharedResponse {
+                status: StatusCode::from_u16(refreshed.status).unwrap_or(StatusCode::OK),
+                headers: headers_to_map(&refreshed.headers),
+                body: refreshed.body,
+            })));
+            return;
+        }
+
+        if parts.status.is_server_error() {
+            tracing::warn!(
+                "⛔ Background revalidation for '{}' returned {}, keeping stale entry",
+                uri,
+                parts.status
+            );
+            counter!("cachebolt_revalidation_failures_total", "uri" => uri.clone()).increment(1);
+            return;
+        }
+
+        let body_bytes = match read_cacheable_body(body).await {
+            Ok(Some(body)) => body,
+            Ok(None) => {
+                tracing::info!("📦 Revalidated response for '{}' too large to cache", uri);
+                counter!("cachebolt_oversized_responses_total", "uri" => uri.clone()).increment(1);
+                return;
+            }
+            Err(e) => {
+                tracing::warn!("⛔ Background revalidation body for '{}' was truncated: {}", uri, e);
+                counter!("cachebolt_revalidation_failures_total", "uri" => uri.clone()).increment(1);
+                return;
+            }
+        };
+
+        store_response(&uri, &base, &key, parts.status, &parts.headers, &body_bytes, false).await;
+        counter!("cachebolt_revalidations_total", "uri" => uri.clone()).increment(1);
+        cache_tags::strip_response_headers(&mut parts.headers);
+
+        guard.complete(Some(Arc::new(SharedResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body: body_bytes,
+        })));
+    });
+}
+
+/// Looks up an entry that may be served in place of a failed downstream
+/// response (stale-if-error), first in memory and then in the persistent backend.
+async fn load_stale_if_error(key: &str) -> Option<memory::CachedResponse> {
+    if let Some(cached) = memory::get_from_memory(key).await
+        && cached.can_serve_on_error()
+    {
+        return Some(cached);
+    }
+
+    let entry = load_from_backend(key).await.ok().flatten()?;
+    if !entry.can_serve_on_error() {
+        return None;
+    }
+    memory::load_into_memory(vec![(key.to_string(), entry.clone())]).await;
+    Some(entry)
+}
+
+/// Forwards a request that must never be cached (writes, or POST outside
+/// `post_caching.path_rules`), streaming its body downstream and the
+/// response back unchanged.
+async fn passthrough(uri: &str, req: Request<Body>) -> Response<Body> {
+    counter!("cachebolt_passthrough_requests_total", "method" => req.method().to_string())
+        .increment(1);
+
+    let Ok(_permit) = SEMAPHORE.clone().try_acquire_owned() else {
+        counter!("cachebolt_rejected_due_to_concurrency_total", "uri" => uri.to_string())
+            .increment(1);
+        return Response::builder()
+            .status(502)
+            .body("Too many concurrent requests".into())
+            .unwrap();
+    };
+
+    match forward_request(uri, req).await {
+        Ok(mut resp) => {
+            cache_tags::strip_response_headers(resp.headers_mut());
+            resp
+        }
+        Err(_) => {
+            tracing::warn!("⛔ Downstream service failed for '{}'", uri);
+            counter!("cachebolt_downstream_failures_total", "uri" => uri.to_string())
+                .increment(1);
+            Response::builder()
+                .status(502)
+                .body("Downstream error".into())
+                .unwrap()
+        }
+    }
+}
+
+/// Buffers the body of a cacheable POST request so it can be hashed into the cache key.
+///
+/// # Returns
+/// - `Ok((request, Some(body)))` with the request rebuilt around the buffered body.
+/// - `Ok((request, None))` if the body exceeds `post_caching.max_body_bytes`; the
+///   request should be passed through uncached.
+/// - `Err(Response)` with a 400 if the client body could not be read.
+async fn buffer_post_body(
+    req: Request<Body>,
+) -> Result<(Request<Body>, Option<Bytes>), Response<Body>> {
+    let max_body_bytes = CONFIG
+        .get()
+        .map(|c| c.post_caching.max_body_bytes)
+        .unwrap_or_default();
+
+    // Don't buffer bodies that are declared too large
+    let declared_len = req
+        .headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if declared_len.is_some_and(|len| len > max_body_bytes) {
+        return Ok((req, None));
+    }
+
+    let (parts, body) = req.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!("❌ Failed to read request body: {}", e);
+            return Err(Response::builder()
+                .status(400)
+                .body("Invalid request body".into())
+                .unwrap());
+        }
+    };
+
+    let req = Request::from_parts(parts, Body::from(body.clone()));
+    if body.len() > max_body_bytes {
+        return Ok((req, None));
+    }
+    Ok((req, Some(body)))
+}
+
+/// Serves a request from the cache via `try_cache`, mapping lookup errors to a 500
+async fn serve_fallback(key: &str) -> Response<Body> {
+    match try_cache(key).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("❌ Error in try_cache: {}", e);
+            Response::builder()
+                .status(500)
+                .body(format!("Internal error: {}", e).into())
+                .unwrap()
+        }
+    }
+}
+
 /// Attempts to retrieve response from memory or persistent cache
 pub async fn try_cache(key: &str) -> Result<Response<Body>, Box<dyn std::error::Error + Send + Sync>> {
     // Try memory first
     if let Some(cached) = memory::get_from_memory(key).await {
         tracing::info!("✅ Fallback hit from MEMORY_CACHE for '{}'", key);
         counter!("cachebolt_memory_fallback_hits_total").increment(1);
-        return Ok(build_response(cached.body.clone(), cached.headers.clone()));
+        return Ok(entry_response(cached));
     }
 
     // Then check persistent cache backend
-    let fallback = match CONFIG.get().map(|c| &c.storage_backend) {
-        Some(StorageBackend::Azure) => Ok(azure::load_from_cache(key).await),
-        Some(StorageBackend::Gcs) => Ok(gcs::load_from_cache(key).await),
-        Some(StorageBackend::Local) => Ok(local::load_from_cache(key).await),
-        Some(StorageBackend::S3) => {
-            // If circuit breaker tripped, skip S3 load and return None (fallback to upstream)
-            if CIRCUIT_BREAKER.load(Ordering::SeqCst) {
-                tracing::warn!("Skipping S3 load because circuit breaker is tripped (key={})", key);
-                Ok(None)
-            } else {
-                s3::load_from_cache(key).await
-                    .map(Some)
-                    .map_err(|e| {
-                        if is_bucket_access_error(&*e) {
-                            let new_count = BUCKET_ACCESS_ERRORS.fetch_add(1, Ordering::Relaxed) + 1;
-                            let allowed = *STORAGE_BACKEND_FAILURES_THRESHOLD;
-                            tracing::warn!(
-                                "Bucket access error contado (load) {}/{} (key={}, circuit_breaker={})",
-                                new_count,
-                                allowed,
-                                key,
-                                CIRCUIT_BREAKER.load(Ordering::SeqCst)
-                            );
-                            if allowed > 0 && new_count as usize > allowed {
-                                // trip the breaker and start health checker
-                                CIRCUIT_BREAKER.store(true, Ordering::SeqCst);
-                                let interval = *BACKEND_RETRY_INTERVAL_SECS_CONFIG;
-                                tracing::error!(
-                                    "Bucket access errors exceeded threshold ({} > {}). Tripping breaker and starting health checker ({}s)",
-                                    new_count,
-                                    allowed,
-                                    interval
-                                );
-                                // Reset counter before launching recovery checker so it can be reused
-                                BUCKET_ACCESS_ERRORS.store(0, Ordering::Relaxed);
-                                crate::storage::s3::start_s3_health_checker(interval);
-                            }
-                        }
-                        e
-                    })
-            }
+    let fallback = load_from_backend(key).await?;
+
+    // Persisted entries keep their original expiry; unservable ones count as a miss
+    let fallback = fallback.filter(|entry| {
+        let servable = entry.is_servable();
+        if !servable {
+            tracing::info!("⌛ Ignoring expired persistent entry for '{}'", key);
+            counter!("cachebolt_expired_fallback_rejects_total").increment(1);
         }
-        None => Ok(None),
-    }?;
+        servable
+    });
 
-    if let Some((data, headers)) = fallback {
+    if let Some(entry) = fallback {
         tracing::info!("✅ Fallback from persistent cache for '{}'", key);
         counter!("cachebolt_persistent_fallback_hits_total").increment(1);
-        let cached_response = memory::CachedResponse {
-            body: data.clone(),
-            headers: headers.clone(),
-            inserted_at: chrono::Utc::now(),
-        };
-        memory::load_into_memory(vec![(key.to_string(), cached_response)]).await;
-        Ok(build_response(data, headers))
+        let response = entry_response(entry.clone());
+        memory::load_into_memory(vec![(key.to_string(), entry)]).await;
+        Ok(response)
     } else {
         counter!("cachebolt_fallback_miss_total").increment(1);
         Ok(Response::builder()
@@ -426,12 +947,39 @@ pub async fn try_cache(key: &str) -> Result<Response<Body>, Box<dyn std::error::
     }
 }
 
+/// Loads an entry from the persistent storage tiers, honoring their circuit
+/// breakers.
+///
+/// # Returns
+/// - `Ok(Some(CachedResponse))` from the first tier holding `key`, flagged as
+///   purged if a recorded soft purge covers it (see `storage::purge`).
+/// - `Ok(None)` on a miss in every tier, or if they are all skipped.
+/// - `Err(..)` if no tier has the entry and a lookup failed.
+pub(crate) async fn load_from_backend(
+    key: &str,
+) -> Result<Option<memory::CachedResponse>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(backend) = storage::backend() else {
+        return Ok(None);
+    };
+
+    // Open circuit breakers make the storage tiers skip the lookup
+    let mut entry = backend.get(key).await?;
+    if let Some(entry) = &mut entry {
+        purge::apply(key, entry);
+    }
+    Ok(entry)
+}
+
 /// Composes a full HTTP response from body and headers
 pub fn build_response(body: Bytes, headers: Vec<(String, String)>) -> Response<Body> {
     let mut builder = Response::builder();
     let mut has_content_type = false;
+    let strip_tags = cache_tags::strip_enabled();
 
     for (name, value) in headers.iter() {
+        if strip_tags && cache_tags::is_tag_header(name) {
+            continue;
+        }
         if name.eq_ignore_ascii_case("content-type") {
             has_content_type = true;
         }
@@ -452,13 +1000,13 @@ pub fn hash_uri(uri: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
-/// Sends an outbound GET request to the downstream backend
-/// Sends an outbound GET request to the downstream backend, forwarding all headers except 'accept-encoding'.
+/// Sends an outbound request to the downstream backend with the original method and body,
+/// forwarding all headers except 'accept-encoding'.
 /// This prevents curl: (52) Empty reply from server errors caused by unsupported encodings.
 ///
 /// # Arguments
 /// - `uri`: The path to append to the downstream base URL.
-/// - `original_req`: The incoming Axum request, from which headers are forwarded.
+/// - `original_req`: The incoming Axum request, whose method, headers and body are forwarded.
 ///
 /// # Returns
 /// - `Ok(Response)` with the downstream response if successful.
@@ -482,10 +1030,14 @@ pub async fn forward_request(uri: &str, original_req: Request<Body>) -> Result<R
     let downstream_host = url::Url::parse(&cfg.downstream_base_url)
         .ok()
         .and_then(|u| u.host_str().map(|s| s.to_string()))
-        .unwrap_or_else(|| "".to_string());
+        .unwrap_or_default();
 
-    // Build the request, starting with the URL and GET method
-    let mut builder = Request::builder().uri(full_url.clone()).method("GET");
+    // Forward the original method; HEAD is sent as GET so the full response can be cached
+    let method = match original_req.method() {
+        &Method::HEAD => Method::GET,
+        m => m.clone(),
+    };
+    let mut builder = Request::builder().uri(full_url.clone()).method(method);
 
     // Copy all headers from the incoming request,
     // except for 'accept-encoding' and 'host'
@@ -504,8 +1056,8 @@ pub async fn forward_request(uri: &str, original_req: Request<Body>) -> Result<R
         builder = builder.header("Host", downstream_host);
     }
 
-    // Build the final request object with empty body
-    let req = match builder.body(Body::empty()) {
+    // Build the final request object, forwarding the original body
+    let req = match builder.body(original_req.into_body()) {
         Ok(req) => req,
         Err(e) => {
             tracing::error!("❌ Error building downstream request: {}", e);
@@ -513,8 +1065,19 @@ pub async fn forward_request(uri: &str, original_req: Request<Body>) -> Result<R
         }
     };
 
-    // Send the HTTP request to the downstream service
-    match HTTP_CLIENT.request(req).await {
+    // Send the HTTP request to the downstream service, bounded by stale.upstream_timeout_ms
+    let result = match cfg.stale.upstream_timeout_ms {
+        0 => HTTP_CLIENT.request(req).await,
+        ms => match tokio::time::timeout(Duration::from_millis(ms), HTTP_CLIENT.request(req)).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!("⌛ Request to downstream '{}' timed out after {}ms", full_url, ms);
+                return Err(());
+            }
+        },
+    };
+
+    match result {
         Ok(resp) => Ok(resp),
         Err(e) => {
             tracing::warn!("❌ Request to downstream '{}' failed: {}", full_url, e);
@@ -20,16 +20,16 @@ use hyper::HeaderMap;
 /// - The cache will be skipped for read and write.
 /// - The backend will be hit directly.
 pub fn should_bypass_cache(headers: &HeaderMap) -> bool {
-    if let Some(value) = headers.get("cache-control") {
-        if value.to_str().unwrap_or("").to_ascii_lowercase().contains("no-cache") {
-            return true;
-        }
+    if let Some(value) = headers.get("cache-control")
+        && value.to_str().unwrap_or("").to_ascii_lowercase().contains("no-cache")
+    {
+        return true;
     }
 
-    if let Some(value) = headers.get("x-bypass-cache") {
-        if value.to_str().unwrap_or("").to_ascii_lowercase() == "true" {
-            return true;
-        }
+    if let Some(value) = headers.get("x-bypass-cache")
+        && value.to_str().unwrap_or("").eq_ignore_ascii_case("true")
+    {
+        return true;
     }
 
     false
@@ -0,0 +1,215 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use hyper::HeaderMap;
+use regex::Regex;
+
+use crate::config::{CONFIG, CacheControlPolicy};
+
+/// Caching directives extracted from an upstream response.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UpstreamDirectives {
+    pub no_store: bool,
+    pub private: bool,
+    pub no_cache: bool,
+    pub must_revalidate: bool,
+    pub max_age: Option<u64>,
+    pub s_maxage: Option<u64>,
+    pub expires: Option<DateTime<Utc>>,
+    pub date: Option<DateTime<Utc>>,
+    pub age: Option<u64>,
+    pub stale_while_revalidate: Option<u64>,
+    pub stale_if_error: Option<u64>,
+}
+
+/// How long a response may be served as fresh, whether it must be
+/// revalidated once stale, and how long past expiry it may be served stale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Freshness {
+    pub ttl_secs: u64,
+    pub must_revalidate: bool,
+    pub stale_while_revalidate: u64,
+    pub stale_if_error: u64,
+}
+
+/// Stale serving windows, in seconds past expiry, configured for a path.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StaleWindows {
+    pub while_revalidate: u64,
+    pub if_error: u64,
+}
+
+impl UpstreamDirectives {
+    /// Parses `Cache-Control`, `Expires`, `Date` and `Age` from response headers.
+    /// Unknown directives are ignored; malformed `Expires` values are treated as
+    /// already expired, as RFC 9111 requires.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut directives = UpstreamDirectives::default();
+
+        for value in headers.get_all("cache-control") {
+            let value = value.to_str().unwrap_or("");
+            for directive in value.split(',') {
+                let directive = directive.trim();
+                let (name, arg) = match directive.split_once('=') {
+                    Some((n, a)) => (n.trim(), Some(a.trim().trim_matches('"'))),
+                    None => (directive, None),
+                };
+
+                match name.to_ascii_lowercase().as_str() {
+                    "no-store" => directives.no_store = true,
+                    "private" => directives.private = true,
+                    "no-cache" => directives.no_cache = true,
+                    "must-revalidate" | "proxy-revalidate" => directives.must_revalidate = true,
+                    "max-age" => directives.max_age = arg.and_then(|a| a.parse().ok()),
+                    "s-maxage" => directives.s_maxage = arg.and_then(|a| a.parse().ok()),
+                    "stale-while-revalidate" => {
+                        directives.stale_while_revalidate = arg.and_then(|a| a.parse().ok())
+                    }
+                    "stale-if-error" => {
+                        directives.stale_if_error = arg.and_then(|a| a.parse().ok())
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(value) = headers.get("expires") {
+            directives.expires = Some(
+                parse_http_date(value.to_str().unwrap_or(""))
+                    .unwrap_or(DateTime::<Utc>::MIN_UTC),
+            );
+        }
+
+        directives.date = headers
+            .get("date")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_http_date);
+
+        directives.age = headers
+            .get("age")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok());
+
+        directives
+    }
+
+    /// Returns `false` if a shared cache must not store the response.
+    pub fn is_storable(&self) -> bool {
+        !self.no_store && !self.private
+    }
+
+    /// Freshness lifetime in seconds as dictated by the upstream, if any.
+    /// `s-maxage` wins over `max-age`, which wins over `Expires`.
+    /// `no-cache` means the entry is stored but never considered fresh.
+    pub fn freshness_lifetime(&self) -> Option<u64> {
+        if self.no_cache {
+            return Some(0);
+        }
+
+        let lifetime = if let Some(secs) = self.s_maxage.or(self.max_age) {
+            secs
+        } else {
+            let expires = self.expires?;
+            let base = self.date.unwrap_or_else(Utc::now);
+            expires.signed_duration_since(base).num_seconds().max(0) as u64
+        };
+
+        Some(lifetime.saturating_sub(self.age.unwrap_or(0)))
+    }
+}
+
+/// Parses an HTTP date (IMF-fixdate, e.g. `Wed, 21 Oct 2015 07:28:00 GMT`).
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// Returns the upstream directive policy for the given URI.
+/// If the URI matches a custom regex rule from the config, that policy
+/// is returned. Otherwise, the configured default policy is used.
+pub fn get_cache_control_policy(uri: &str) -> CacheControlPolicy {
+    let Some(cfg) = CONFIG.get() else {
+        return CacheControlPolicy::default();
+    };
+    for rule in &cfg.cache_control.path_rules {
+        if let Ok(re) = Regex::new(&rule.pattern)
+            && re.is_match(uri)
+        {
+            return rule.policy;
+        }
+    }
+    cfg.cache_control.default_policy
+}
+
+/// Returns the stale-while-revalidate / stale-if-error windows for the given URI.
+/// If the URI matches a custom regex rule from the config, its windows are
+/// returned. Otherwise, the configured defaults are used.
+pub fn get_stale_windows(uri: &str) -> StaleWindows {
+    let Some(cfg) = CONFIG.get() else {
+        return StaleWindows::default();
+    };
+    for rule in &cfg.stale.path_rules {
+        if let Ok(re) = Regex::new(&rule.pattern)
+            && re.is_match(uri)
+        {
+            return StaleWindows {
+                while_revalidate: rule.stale_while_revalidate_seconds,
+                if_error: rule.stale_if_error_seconds,
+            };
+        }
+    }
+    StaleWindows {
+        while_revalidate: cfg.stale.stale_while_revalidate_seconds,
+        if_error: cfg.stale.stale_if_error_seconds,
+    }
+}
+
+/// Decides whether (and for how long) a successful response may be cached.
+///
+/// # Returns
+/// - `None` if the upstream forbids storing the response (`no-store` / `private`)
+///   and the path respects upstream directives.
+/// - `Some(Freshness)` otherwise. Without upstream lifetime information, or when
+///   the path overrides upstream directives, `cache.ttl_seconds` is used.
+///   Stale windows come from upstream `stale-while-revalidate` / `stale-if-error`
+///   when present, falling back to the `stale` config section.
+pub fn freshness_for_response(uri: &str, headers: &HeaderMap) -> Option<Freshness> {
+    let default_ttl = CONFIG.get().map(|c| c.cache.ttl_seconds).unwrap_or(0);
+    let windows = get_stale_windows(uri);
+
+    if get_cache_control_policy(uri) == CacheControlPolicy::Override {
+        return Some(Freshness {
+            ttl_secs: default_ttl,
+            must_revalidate: false,
+            stale_while_revalidate: windows.while_revalidate,
+            stale_if_error: windows.if_error,
+        });
+    }
+
+    let directives = UpstreamDirectives::from_headers(headers);
+    if !directives.is_storable() {
+        return None;
+    }
+
+    Some(Freshness {
+        ttl_secs: directives.freshness_lifetime().unwrap_or(default_ttl),
+        must_revalidate: directives.must_revalidate,
+        stale_while_revalidate: directives
+            .stale_while_revalidate
+            .unwrap_or(windows.while_revalidate),
+        stale_if_error: directives.stale_if_error.unwrap_or(windows.if_error),
+    })
+}
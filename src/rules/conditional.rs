@@ -0,0 +1,82 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use hyper::HeaderMap;
+
+use crate::rules::cache_control::parse_http_date;
+
+/// Request headers that make a GET conditional. They never take part in the
+/// cache key and are evaluated by CacheBolt instead of the downstream.
+pub const CONDITIONAL_HEADERS: [&str; 2] = ["if-none-match", "if-modified-since"];
+
+/// Response headers kept in a `304 Not Modified` (RFC 9110 §15.4.5).
+pub const NOT_MODIFIED_HEADERS: [&str; 6] = [
+    "cache-control",
+    "content-location",
+    "date",
+    "etag",
+    "expires",
+    "vary",
+];
+
+/// Validators sent by a client in a conditional GET / HEAD.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Conditionals {
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<DateTime<Utc>>,
+}
+
+impl Conditionals {
+    /// Extracts `If-None-Match` and `If-Modified-Since` from request headers.
+    /// Unparseable dates are ignored, as RFC 9110 requires.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Conditionals {
+            if_none_match: headers
+                .get("if-none-match")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string()),
+            if_modified_since: headers
+                .get("if-modified-since")
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_http_date),
+        }
+    }
+
+    /// Returns `true` if the client's copy matches a response with the given
+    /// validators, i.e. it can be answered with `304 Not Modified`.
+    /// `If-None-Match` takes precedence; `If-Modified-Since` is only evaluated
+    /// when it is absent.
+    pub fn is_not_modified(&self, etag: Option<&str>, last_modified: Option<&str>) -> bool {
+        if let Some(if_none_match) = &self.if_none_match {
+            let Some(etag) = etag else {
+                return false;
+            };
+            return if_none_match
+                .split(',')
+                .map(str::trim)
+                .any(|candidate| candidate == "*" || weak_eq(candidate, etag));
+        }
+
+        match (self.if_modified_since, last_modified.and_then(parse_http_date)) {
+            (Some(since), Some(modified)) => modified <= since,
+            _ => false,
+        }
+    }
+}
+
+/// Weak entity-tag comparison: `W/"a"` matches `"a"`.
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim().trim_start_matches("W/")
+}
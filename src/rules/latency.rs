@@ -56,10 +56,10 @@ pub fn mark_latency_fail(uri: &str) {
 pub fn get_max_latency_for_path(uri: &str) -> u64 {
     let cfg = CONFIG.get().expect("CONFIG not initialized");
     for rule in &cfg.latency_failover.path_rules {
-        if let Ok(re) = Regex::new(&rule.pattern) {
-            if re.is_match(uri) {
-                return rule.max_latency_ms;
-            }
+        if let Ok(re) = Regex::new(&rule.pattern)
+            && re.is_match(uri)
+        {
+            return rule.max_latency_ms;
         }
     }
     cfg.latency_failover.default_max_latency_ms
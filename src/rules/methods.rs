@@ -0,0 +1,42 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use hyper::Method;
+use regex::Regex;
+
+use crate::config::CONFIG;
+
+/// Returns `true` if POST requests to the given URI may be cached.
+/// POST caching is opt-in: the URI must match one of `post_caching.path_rules`.
+pub fn should_cache_post(uri: &str) -> bool {
+    let Some(cfg) = CONFIG.get() else {
+        return false;
+    };
+    cfg.post_caching.path_rules.iter().any(|rule| {
+        Regex::new(&rule.pattern)
+            .map(|re| re.is_match(uri))
+            .unwrap_or(false)
+    })
+}
+
+/// Returns `true` if a request with this method may be served from or stored
+/// in the cache. `GET` and `HEAD` always are; `POST` only for opted-in paths.
+/// Every other method (PUT, PATCH, DELETE, OPTIONS, ...) is passed through.
+pub fn is_cacheable_method(method: &Method, uri: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD => true,
+        Method::POST => should_cache_post(uri),
+        _ => false,
+    }
+}
@@ -14,4 +14,7 @@
 
 pub mod latency;
 pub mod refresh;
-pub mod bypass;
\ No newline at end of file
+pub mod bypass;
+pub mod cache_control;
+pub mod methods;
+pub mod conditional;
\ No newline at end of file
@@ -36,7 +36,7 @@ pub fn should_refresh(key: &str) -> bool {
     *counter += 1;
 
     let modulus = 100 / percentage.max(1);
-    let should = *counter % modulus as u64 == 0;
+    let should = (*counter).is_multiple_of(modulus as u64);
 
     if should {
         info!("🔄 Refresh triggered for key '{}' after {} hits", key, counter);
@@ -15,28 +15,19 @@
 // Azure SDK dependencies for Blob storage access
 use azure_storage::StorageCredentials;
 use azure_storage_blobs::prelude::*;
-use bytes::Bytes;
 use once_cell::sync::OnceCell;
 use std::env;
 use tracing::{error, info, warn};
 
 use crate::config::CONFIG;
+use crate::memory::memory::CachedResponse;
+use crate::storage::{StorageBackend, StorageResult, StoredObject, blob, timestamp_to_datetime};
+use async_trait::async_trait;
+use bytes::Bytes;
 
-use serde::{Serialize, Deserialize};
-use base64::engine::general_purpose::STANDARD;
-use base64::Engine;
 use std::error::Error;
 use futures::StreamExt;
 
-/// Structure used to store a cached object in Azure Blob Storage.
-/// - `body`: base64-encoded content (response body).
-/// - `headers`: original response headers.
-#[derive(Serialize, Deserialize)]
-struct CachedBlob {
-    body: String,
-    headers: Vec<(String, String)>,
-}
-
 /// Global singleton instance of the Azure Blob client.
 /// It is lazily initialized and shared across all tasks.
 static AZURE_CLIENT: OnceCell<BlobServiceClient> = OnceCell::new();
@@ -46,7 +37,6 @@ static AZURE_CLIENT: OnceCell<BlobServiceClient> = OnceCell::new();
 /// - `AZURE_STORAGE_ACCESS_KEY`
 ///
 /// This function should be called only once at startup.
-
 pub fn init_azure_client() {
     if AZURE_CLIENT.get().is_none() {
         // Retrieve Azure credentials from environment variables
@@ -68,66 +58,48 @@ pub fn init_azure_client() {
 ///
 /// # Arguments
 /// - `key`: The cache key used as the blob's name.
-/// - `data`: The raw response body as bytes.
-/// - `headers`: The response headers to store along with the body.
-
-pub async fn store_in_cache(key: String, data: Bytes, headers: Vec<(String, String)>) {
+/// - `entry`: The cached response (body, headers and expiry metadata) to store.
+///
+/// # Returns
+/// - `Ok(())` once the blob is uploaded.
+/// - `Err(...)` if the client is not ready, encoding fails, or the upload fails.
+pub async fn store_in_cache(key: String, entry: CachedResponse) -> Result<(), Box<dyn Error + Send + Sync>> {
     // Retrieve the global Azure client
-    let client = match AZURE_CLIENT.get() {
-        Some(c) => c,
-        None => {
-            error!("Azure client not initialized");
-            return;
-        }
-    };
+    let client = AZURE_CLIENT.get().ok_or("Azure client not initialized")?;
 
     // Retrieve the Azure container name from config
-    let container = match CONFIG.get() {
-        Some(cfg) => cfg.azure_container.clone(),
-        None => {
-            error!("CONFIG not initialized; cannot read azure_container");
-            return;
-        }
-    };
+    let container = CONFIG
+        .get()
+        .ok_or("CONFIG not initialized; cannot read azure_container")?
+        .azure_container
+        .clone();
 
     // Get blob client from the container and key
     let blob_client = client
         .container_client(container.clone())
         .blob_client(key.clone());
 
-    // Encode the body to base64 and prepare the blob content
-    let blob = CachedBlob {
-        body: STANDARD.encode(&data),
-        headers,
-    };
-
-    // Serialize the struct into JSON
-    let json = match serde_json::to_vec(&blob) {
-        Ok(j) => j,
-        Err(e) => {
-            error!("❌ Failed to serialize cache for key '{}': {}", key, e);
-            return;
-        }
-    };
+    let encoded = blob::encode(&entry).map_err(|e| {
+        error!("❌ Failed to encode cache for key '{}': {}", key, e);
+        e
+    })?;
 
     // Upload the blob to Azure
-    let result = blob_client
-        .put_block_blob(json)
-        .content_type("application/json")
+    blob_client
+        .put_block_blob(encoded)
+        .content_type("application/octet-stream")
         .into_future()
-        .await;
+        .await
+        .map_err(|e| {
+            error!("❌ Failed to store key '{}' in Azure Blob Storage: {}", key, e);
+            e
+        })?;
 
-    // Log upload result
-    match result {
-        Ok(_) => info!(
-            "✅ Key '{}' stored in Azure Blob Storage container '{}'",
-            key, container
-        ),
-        Err(e) => error!(
-            "❌ Failed to store key '{}' in Azure Blob Storage: {}",
-            key, e
-        ),
-    }
+    info!(
+        "✅ Key '{}' stored in Azure Blob Storage container '{}'",
+        key, container
+    );
+    Ok(())
 }
 
 /// Retrieves cached data from Azure Blob Storage for a given key.
@@ -136,10 +108,9 @@ pub async fn store_in_cache(key: String, data: Bytes, headers: Vec<(String, Stri
 /// - `key`: The cache key (blob name) to retrieve.
 ///
 /// # Returns
-/// - `Some(Bytes, headers)` on success
-/// - `None` if the blob was not found or deserialization failed
-
-pub async fn load_from_cache(key: &str) -> Option<(Bytes, Vec<(String, String)>)> {
+/// - `Some(CachedResponse)` on success (expired entries included)
+/// - `None` if the blob was not found or decoding failed
+pub async fn load_from_cache(key: &str) -> Option<CachedResponse> {
     let client = AZURE_CLIENT.get()?; // Get Azure client
     let container = CONFIG.get()?.azure_container.clone(); // Get container name
 
@@ -155,20 +126,11 @@ pub async fn load_from_cache(key: &str) -> Option<(Bytes, Vec<(String, String)>)
                 key, container
             );
 
-            // Attempt to deserialize the JSON-encoded CachedBlob
-            match serde_json::from_slice::<CachedBlob>(&data) {
-                Ok(blob) => {
-                    // Decode the base64-encoded body
-                    match STANDARD.decode(&blob.body) {
-                        Ok(decoded_body) => Some((Bytes::from(decoded_body), blob.headers)),
-                        Err(e) => {
-                            error!("❌ Failed to decode base64 body for key '{}': {}", key, e);
-                            None
-                        }
-                    }
-                }
+            // Legacy blobs are plain (not gzipped) JSON
+            match blob::decode_any(&data, false) {
+                Ok(entry) => Some(entry),
                 Err(e) => {
-                    error!("❌ Failed to parse JSON cache for key '{}': {}", key, e);
+                    error!("❌ Failed to decode cache for key '{}': {}", key, e);
                     None
                 }
             }
@@ -186,10 +148,9 @@ pub async fn load_from_cache(key: &str) -> Option<(Bytes, Vec<(String, String)>)
 /// Deletes all cached entries from Azure Blob Storage (prefix: "cache/{app_id}/").
 ///
 /// # Returns
-/// - `Ok(count)` with number of blobs deleted on success.
+/// - `Ok(keys)` with the keys (blob names) deleted on success.
 /// - `Err(...)` if listing or deletion fails.
-/// use futures::StreamExt;
-pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Sync>> {
+pub async fn delete_all_from_cache() -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
     let client = AZURE_CLIENT
         .get()
         .ok_or("Azure client not initialized")?;
@@ -206,20 +167,21 @@ pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Syn
         .list_blobs()
         .into_stream();
 
-    let mut deleted = 0;
+    let mut deleted = Vec::new();
 
     while let Some(result) = stream.next().await {
         let result = result?;
         let blobs = result.blobs.blobs();
 
-        for blob in blobs {
+        // Metadata blobs (`meta/...`) are not entries
+        for blob in blobs.filter(|blob| !is_meta(&blob.name)) {
             let blob_name = blob.name.clone();
             let blob_client = container_client.blob_client(blob_name.clone());
 
             match blob_client.delete().into_future().await {
                 Ok(_) => {
-                    deleted += 1;
                     info!("🗑️ Deleted blob '{}' from container '{}'", blob_name, container);
+                    deleted.push(blob_name);
                 }
                 Err(e) => {
                     warn!("⚠️ Failed to delete blob '{}': {}", blob_name, e);
@@ -228,6 +190,172 @@ pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Syn
         }
     }
 
-    info!("✅ Azure: Deleted {deleted} blobs from container '{}'", container);
+    info!("✅ Azure: Deleted {} blobs from container '{}'", deleted.len(), container);
     Ok(deleted)
 }
+
+/// Lists the cached entries stored in the Azure Blob Storage container.
+/// Blobs are named after their cache key, so every blob but the metadata
+/// ones is listed.
+///
+/// # Returns
+/// - `Ok(objects)` with one `StoredObject` per blob.
+/// - `Err(...)` if listing fails.
+pub async fn list_cache_entries() -> Result<Vec<StoredObject>, Box<dyn Error + Send + Sync>> {
+    let client = AZURE_CLIENT
+        .get()
+        .ok_or("Azure client not initialized")?;
+
+    let config = CONFIG
+        .get()
+        .ok_or("CONFIG not initialized")?;
+
+    let container_client = client.container_client(config.azure_container.clone());
+    let mut stream = container_client.list_blobs().into_stream();
+    let mut listed = Vec::new();
+
+    while let Some(result) = stream.next().await {
+        let result = result?;
+        for blob in result.blobs.blobs().filter(|blob| !is_meta(&blob.name)) {
+            listed.push(StoredObject {
+                key: blob.name.clone(),
+                last_modified: timestamp_to_datetime(Some(
+                    blob.properties.last_modified.unix_timestamp(),
+                )),
+                size: blob.properties.content_length,
+            });
+        }
+    }
+
+    Ok(listed)
+}
+
+/// Deletes the blob of a single key from the Azure Blob Storage container.
+///
+/// # Returns
+/// - `Ok(true)` if the blob was deleted.
+/// - `Ok(false)` if there was no blob for the key.
+/// - `Err(...)` if the deletion fails.
+pub async fn delete_from_cache(key: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let client = AZURE_CLIENT.get().ok_or("Azure client not initialized")?;
+    let container = &CONFIG.get().ok_or("CONFIG not initialized")?.azure_container;
+
+    let deleted = client
+        .container_client(container.clone())
+        .blob_client(key)
+        .delete()
+        .into_future()
+        .await;
+    match deleted {
+        Ok(_) => {}
+        Err(e) if matches!(
+            e.kind(),
+            azure_storage::ErrorKind::HttpResponse { status, .. } if u16::from(*status) == 404
+        ) => return Ok(false),
+        Err(e) => return Err(e.into()),
+    }
+
+    info!("🗑️ Deleted blob '{}' from container '{}'", key, container);
+    Ok(true)
+}
+
+/// Single connectivity check to the configured Azure container.
+pub async fn check_container_connection() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let client = AZURE_CLIENT.get().ok_or("Azure client not initialized")?;
+    let container = &CONFIG.get().ok_or("CONFIG not initialized")?.azure_container;
+
+    client
+        .container_client(container.clone())
+        .get_properties()
+        .into_future()
+        .await?;
+    Ok(())
+}
+
+/// Whether a blob holds metadata (see `load_meta`) rather than an entry.
+/// Cache keys never contain a `/`.
+fn is_meta(blob_name: &str) -> bool {
+    blob_name.contains('/')
+}
+
+/// Loads the metadata blob `meta/{app_id}/{name}`. `Ok(None)` if it does not exist.
+pub async fn load_meta(name: &str) -> Result<Option<Vec<u8>>, Box<dyn Error + Send + Sync>> {
+    let client = AZURE_CLIENT.get().ok_or("Azure client not initialized")?;
+    let config = CONFIG.get().ok_or("CONFIG not initialized")?;
+
+    let loaded = client
+        .container_client(config.azure_container.clone())
+        .blob_client(format!("meta/{}/{}", config.app_id, name))
+        .get_content()
+        .await;
+    match loaded {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if matches!(
+            e.kind(),
+            azure_storage::ErrorKind::HttpResponse { status, .. } if u16::from(*status) == 404
+        ) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Stores the metadata blob `meta/{app_id}/{name}`.
+pub async fn store_meta(name: &str, data: Vec<u8>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let client = AZURE_CLIENT.get().ok_or("Azure client not initialized")?;
+    let config = CONFIG.get().ok_or("CONFIG not initialized")?;
+
+    client
+        .container_client(config.azure_container.clone())
+        .blob_client(format!("meta/{}/{}", config.app_id, name))
+        .put_block_blob(data)
+        .content_type("application/json")
+        .into_future()
+        .await?;
+    Ok(())
+}
+
+/// Azure Blob Storage backend: one blob envelope per key, named after the key.
+pub struct AzureBackend;
+
+#[async_trait]
+impl StorageBackend for AzureBackend {
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+
+    async fn init(&self) -> StorageResult<()> {
+        init_azure_client();
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+        Ok(load_from_cache(key).await)
+    }
+
+    async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+        store_in_cache(key.to_string(), entry).await
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        delete_from_cache(key).await
+    }
+
+    async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+        list_cache_entries().await
+    }
+
+    async fn delete_all(&self) -> StorageResult<Vec<String>> {
+        delete_all_from_cache().await
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        check_container_connection().await
+    }
+
+    async fn get_meta(&self, name: &str) -> StorageResult<Option<Bytes>> {
+        Ok(load_meta(name).await?.map(Bytes::from))
+    }
+
+    async fn put_meta(&self, name: &str, data: Bytes) -> StorageResult<()> {
+        store_meta(name, data.to_vec()).await
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Versioned binary envelope shared by every persistent backend.
+///
+/// Layout:
+///
+/// ```text
+/// "CBLT" | version: u8 | header length: u32 LE | header (bincode) | gzip(body)
+/// ```
+///
+/// The header carries the status code, response headers, expiry metadata, cache
+/// tags, the soft-purge flag and a SHA-256 of the uncompressed body, checked on
+/// read. Older versions are still decoded: version 1 headers carry no tags
+/// (they are taken from the stored tag headers), and neither version 1 nor 2
+/// headers carry the soft-purge flag. Blobs written before
+/// the envelope (base64 bodies in JSON, gzipped or not) are still decoded by
+/// `decode_legacy_json`.
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bincode::{Decode, Encode};
+use bytes::Bytes;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+use crate::cache_tags;
+use crate::memory::memory::CachedResponse;
+use crate::storage::{EntryMeta, StorageResult, entry_from_parts, timestamp_to_datetime};
+
+/// First bytes of every envelope.
+pub const MAGIC: &[u8; 4] = b"CBLT";
+
+/// Current envelope version.
+pub const VERSION: u8 = 3;
+
+/// Magic, version and header length.
+const PREFIX_LEN: usize = MAGIC.len() + 1 + 4;
+
+/// Everything but the body, bincode-encoded after the prefix. Each version
+/// appends fields after those of the previous one.
+#[derive(Encode)]
+struct EnvelopeHeader {
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// Unix timestamps (seconds).
+    inserted_at: i64,
+    expires_at: i64,
+    must_revalidate: bool,
+    stale_while_revalidate: u64,
+    stale_if_error: u64,
+    key_source: String,
+    /// SHA-256 of the uncompressed body.
+    content_hash: [u8; 32],
+    /// Since version 2.
+    tags: Vec<String>,
+    /// Since version 3.
+    purged: bool,
+}
+
+/// The fields every version starts with.
+#[derive(Decode)]
+struct HeaderV1 {
+    status: u16,
+    headers: Vec<(String, String)>,
+    inserted_at: i64,
+    expires_at: i64,
+    must_revalidate: bool,
+    stale_while_revalidate: u64,
+    stale_if_error: u64,
+    key_source: String,
+    content_hash: [u8; 32],
+}
+
+/// Encodes an entry into the current envelope version.
+pub fn encode(entry: &CachedResponse) -> StorageResult<Vec<u8>> {
+    let header = EnvelopeHeader {
+        status: entry.status,
+        headers: entry.headers.clone(),
+        inserted_at: entry.inserted_at.timestamp(),
+        expires_at: entry.expires_at.timestamp(),
+        must_revalidate: entry.must_revalidate,
+        stale_while_revalidate: entry.stale_while_revalidate,
+        stale_if_error: entry.stale_if_error,
+        key_source: entry.key_source.clone(),
+        content_hash: Sha256::digest(&entry.body).into(),
+        tags: entry.tags.clone(),
+        purged: entry.purged,
+    };
+    let header = bincode::encode_to_vec(&header, bincode::config::standard())?;
+
+    let mut out = Vec::with_capacity(PREFIX_LEN + header.len() + entry.body.len() / 2);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&u32::try_from(header.len())?.to_le_bytes());
+    out.extend_from_slice(&header);
+
+    // The compressed body is appended after the header
+    let mut encoder = GzEncoder::new(out, Compression::default());
+    encoder.write_all(&entry.body)?;
+    Ok(encoder.finish()?)
+}
+
+/// Returns `true` if `raw` starts like an envelope (as opposed to a legacy blob).
+pub fn is_envelope(raw: &[u8]) -> bool {
+    raw.len() >= PREFIX_LEN && raw.starts_with(MAGIC)
+}
+
+/// Decodes an envelope, verifying its version and content hash.
+pub fn decode(raw: &[u8]) -> StorageResult<CachedResponse> {
+    if !is_envelope(raw) {
+        return Err("not a CacheBolt blob envelope".into());
+    }
+
+    let version = raw[MAGIC.len()];
+    if !(1..=VERSION).contains(&version) {
+        return Err(format!("unsupported blob envelope version {version}").into());
+    }
+
+    let header_len = u32::from_le_bytes(raw[MAGIC.len() + 1..PREFIX_LEN].try_into()?) as usize;
+    let header_end = PREFIX_LEN
+        .checked_add(header_len)
+        .filter(|end| *end <= raw.len())
+        .ok_or("truncated blob envelope header")?;
+
+    // Fields added by later versions follow the version 1 ones
+    let fields = &raw[PREFIX_LEN..header_end];
+    let config = bincode::config::standard();
+    let (header, mut read): (HeaderV1, usize) = bincode::decode_from_slice(fields, config)?;
+    let tags = if version >= 2 {
+        let (tags, len) = bincode::decode_from_slice(&fields[read..], config)?;
+        read += len;
+        tags
+    } else {
+        cache_tags::from_headers(&header.headers)
+    };
+    let purged = version >= 3 && bincode::decode_from_slice(&fields[read..], config)?.0;
+    if !(200..300).contains(&header.status) {
+        return Err(format!("unexpected status {} in blob envelope", header.status).into());
+    }
+
+    let mut body = Vec::new();
+    GzDecoder::new(&raw[header_end..]).read_to_end(&mut body)?;
+    if Sha256::digest(&body).as_slice() != header.content_hash {
+        return Err("blob envelope content hash mismatch".into());
+    }
+
+    Ok(CachedResponse {
+        status: header.status,
+        body: Bytes::from(body),
+        headers: header.headers,
+        inserted_at: timestamp_to_datetime(Some(header.inserted_at)),
+        expires_at: timestamp_to_datetime(Some(header.expires_at)),
+        must_revalidate: header.must_revalidate,
+        stale_while_revalidate: header.stale_while_revalidate,
+        stale_if_error: header.stale_if_error,
+        key_source: header.key_source,
+        tags,
+        purged,
+    })
+}
+
+/// Layout written by the local, GCS and Azure backends before the envelope:
+/// JSON with a base64-encoded body and the expiry metadata flattened in.
+#[derive(Serialize, Deserialize)]
+pub struct LegacyBlob {
+    pub body: String,
+    pub headers: Vec<(String, String)>,
+    #[serde(flatten)]
+    pub meta: EntryMeta,
+}
+
+/// Decodes a legacy JSON blob, gzipped (local, GCS) or plain (Azure).
+pub fn decode_legacy_json(raw: &[u8], gzipped: bool) -> StorageResult<CachedResponse> {
+    let json = if gzipped {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(raw).read_to_end(&mut decompressed)?;
+        decompressed
+    } else {
+        raw.to_vec()
+    };
+
+    let blob = serde_json::from_slice::<LegacyBlob>(&json)?;
+    let body = STANDARD.decode(&blob.body)?;
+    Ok(entry_from_parts(Bytes::from(body), blob.headers, blob.meta))
+}
+
+/// Decodes a blob in either the envelope or the legacy JSON layout.
+pub fn decode_any(raw: &[u8], legacy_gzipped: bool) -> StorageResult<CachedResponse> {
+    if is_envelope(raw) {
+        decode(raw)
+    } else {
+        decode_legacy_json(raw, legacy_gzipped)
+    }
+}
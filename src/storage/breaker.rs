@@ -0,0 +1,540 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Per-backend circuit breaker.
+///
+/// - **Closed**: calls go through. Consecutive availability errors are counted;
+///   once more than `storage_backend_failures` are seen the breaker opens.
+/// - **Open**: calls are skipped (loads miss, writes fail with `BreakerOpen`) and a health
+///   probe calls `StorageBackend::health_check` every `backend_retry_interval_secs`.
+/// - **Half-open**: entered when a probe succeeds. A single trial call is let
+///   through; success closes the breaker, failure opens it again. A trial whose
+///   caller goes away without an outcome (see `BreakerPermit`) counts as failed.
+///
+/// Only errors classified as availability problems (see `ErrorClass`) count;
+/// misses and corrupt objects never trip the breaker.
+use chrono::{DateTime, Utc};
+use metrics::{counter, gauge};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::storage::StorageBackend;
+
+/// What went wrong in a backend call, as far as the breaker is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// Connection refused/reset, DNS or dispatch failures, 5xx responses.
+    Unavailable,
+    /// The backend did not answer in time.
+    Timeout,
+    /// The backend asked us to slow down (429, SlowDown).
+    Throttled,
+    /// Credentials rejected or access denied to the bucket/container.
+    Unauthorized,
+    /// The object does not exist.
+    NotFound,
+    /// The object exists but could not be decoded.
+    Corrupt,
+    /// Anything else.
+    Other,
+}
+
+impl ErrorClass {
+    /// Whether errors of this class mean the backend itself is unhealthy.
+    pub fn trips_breaker(self) -> bool {
+        matches!(
+            self,
+            ErrorClass::Unavailable | ErrorClass::Timeout | ErrorClass::Throttled | ErrorClass::Unauthorized
+        )
+    }
+
+    /// Label used in metrics.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorClass::Unavailable => "unavailable",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::Throttled => "throttled",
+            ErrorClass::Unauthorized => "unauthorized",
+            ErrorClass::NotFound => "not_found",
+            ErrorClass::Corrupt => "corrupt",
+            ErrorClass::Other => "other",
+        }
+    }
+
+    /// Class of an HTTP status returned by a cloud backend.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ErrorClass::Unauthorized,
+            404 => ErrorClass::NotFound,
+            408 => ErrorClass::Timeout,
+            429 => ErrorClass::Throttled,
+            500..=599 => ErrorClass::Unavailable,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+/// Error tagged with its class by the backend that produced it.
+#[derive(Debug)]
+pub struct BackendError {
+    pub class: ErrorClass,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl BackendError {
+    pub fn new(class: ErrorClass, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        BackendError {
+            class,
+            source: source.into(),
+        }
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl Error for BackendError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// A call skipped because the backend's circuit breaker is open.
+#[derive(Debug)]
+pub struct BreakerOpen {
+    pub backend: &'static str,
+}
+
+impl fmt::Display for BreakerOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} circuit breaker is open", self.backend)
+    }
+}
+
+impl Error for BreakerOpen {}
+
+/// Classifies a backend error by walking its source chain for known error
+/// types, falling back to the message for errors that only carry text.
+pub fn classify(e: &(dyn Error + 'static)) -> ErrorClass {
+    let mut current = Some(e);
+    while let Some(err) = current {
+        if let Some(class) = classify_known(err) {
+            return class;
+        }
+        current = err.source();
+    }
+    classify_message(&e.to_string())
+}
+
+fn classify_known(err: &(dyn Error + 'static)) -> Option<ErrorClass> {
+    use std::io::ErrorKind;
+
+    if let Some(e) = err.downcast_ref::<BackendError>() {
+        return Some(e.class);
+    }
+    if err.is::<BreakerOpen>() {
+        return Some(ErrorClass::Unavailable);
+    }
+    if let Some(e) = err.downcast_ref::<std::io::Error>() {
+        return match e.kind() {
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::AddrNotAvailable
+            | ErrorKind::BrokenPipe
+            | ErrorKind::HostUnreachable
+            | ErrorKind::NetworkUnreachable => Some(ErrorClass::Unavailable),
+            ErrorKind::TimedOut => Some(ErrorClass::Timeout),
+            ErrorKind::PermissionDenied => Some(ErrorClass::Unauthorized),
+            ErrorKind::NotFound => Some(ErrorClass::NotFound),
+            ErrorKind::InvalidData | ErrorKind::UnexpectedEof => Some(ErrorClass::Corrupt),
+            _ => None,
+        };
+    }
+    if let Some(e) = err.downcast_ref::<hyper::Error>() {
+        if e.is_timeout() {
+            return Some(ErrorClass::Timeout);
+        }
+        if e.is_connect() {
+            return Some(ErrorClass::Unavailable);
+        }
+        return None;
+    }
+    if let Some(e) = err.downcast_ref::<google_cloud_storage::http::error::ErrorResponse>() {
+        return Some(ErrorClass::from_status(e.code));
+    }
+    if let Some(e) = err.downcast_ref::<azure_storage::Error>() {
+        if let azure_storage::ErrorKind::HttpResponse { status, .. } = e.kind() {
+            return Some(ErrorClass::from_status(u16::from(*status)));
+        }
+        return None;
+    }
+    if let Some(e) = err.downcast_ref::<redis::RedisError>() {
+        if e.is_timeout() {
+            return Some(ErrorClass::Timeout);
+        }
+        if e.is_connection_refusal() || e.is_connection_dropped() || e.is_io_error() {
+            return Some(ErrorClass::Unavailable);
+        }
+        if e.kind() == redis::ErrorKind::AuthenticationFailed {
+            return Some(ErrorClass::Unauthorized);
+        }
+        return None;
+    }
+    if err.is::<tokio::time::error::Elapsed>() {
+        return Some(ErrorClass::Timeout);
+    }
+    if err.is::<serde_json::Error>()
+        || err.is::<base64::DecodeError>()
+        || err.is::<bincode::error::DecodeError>()
+    {
+        return Some(ErrorClass::Corrupt);
+    }
+    None
+}
+
+fn classify_message(msg: &str) -> ErrorClass {
+    let msg = msg.to_lowercase();
+    let any = |needles: &[&str]| needles.iter().any(|n| msg.contains(n));
+
+    if any(&["timeout", "timed out"]) {
+        ErrorClass::Timeout
+    } else if any(&["dispatch failure", "connection refused", "connect error", "error accessing bucket"]) {
+        ErrorClass::Unavailable
+    } else if any(&["slowdown", "slow down", "too many requests", "throttl"]) {
+        ErrorClass::Throttled
+    } else if any(&["access denied", "accessdenied", "forbidden", "unauthorized", "authenticat"]) {
+        ErrorClass::Unauthorized
+    } else {
+        ErrorClass::Other
+    }
+}
+
+/// Breaker state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// Value of the `cachebolt_backend_circuit_state` gauge.
+    fn gauge_value(self) -> f64 {
+        match self {
+            BreakerState::Closed => 0.0,
+            BreakerState::HalfOpen => 1.0,
+            BreakerState::Open => 2.0,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+}
+
+struct BreakerInner {
+    state: BreakerState,
+    consecutive_failures: usize,
+    opened_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    /// A half-open trial call is in flight.
+    trial_in_flight: bool,
+}
+
+/// Point-in-time view of a breaker, returned by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerSnapshot {
+    pub backend: &'static str,
+    pub state: BreakerState,
+    pub consecutive_failures: usize,
+    pub failure_threshold: usize,
+    pub retry_interval_secs: u64,
+    pub opened_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// Circuit breaker guarding a single backend.
+pub struct CircuitBreaker {
+    backend: Arc<dyn StorageBackend>,
+    /// Allowed consecutive failures before opening (0 = never open).
+    threshold: usize,
+    /// Interval between health probes while open (zero = no probe).
+    retry_interval: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(backend: Arc<dyn StorageBackend>, threshold: usize, retry_interval: Duration) -> Self {
+        gauge!("cachebolt_backend_circuit_state", "backend" => backend.name())
+            .set(BreakerState::Closed.gauge_value());
+        CircuitBreaker {
+            backend,
+            threshold,
+            retry_interval,
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                last_error: None,
+                trial_in_flight: false,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().unwrap().state
+    }
+
+    /// Returns a permit if a call may go to the backend. In the half-open state
+    /// only one caller at a time gets one: the trial.
+    pub fn allow(self: &Arc<Self>) -> Option<BreakerPermit> {
+        let mut inner = self.inner.lock().unwrap();
+        let trial = match inner.state {
+            BreakerState::Closed => false,
+            BreakerState::Open => return None,
+            BreakerState::HalfOpen if inner.trial_in_flight => return None,
+            BreakerState::HalfOpen => {
+                inner.trial_in_flight = true;
+                true
+            }
+        };
+        Some(BreakerPermit {
+            breaker: Arc::clone(self),
+            trial,
+            settled: false,
+        })
+    }
+
+    /// Records a successful call, closing a half-open breaker.
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = 0;
+        inner.trial_in_flight = false;
+        if inner.state == BreakerState::HalfOpen {
+            self.transition(&mut inner, BreakerState::Closed);
+            inner.opened_at = None;
+            info!("✅ {} circuit breaker closed", self.backend.name());
+        }
+    }
+
+    /// Records a failed call. Availability errors count towards opening the
+    /// breaker; a failed half-open trial reopens it.
+    pub fn record_failure(self: &Arc<Self>, e: &(dyn Error + 'static), operation: &str) {
+        let class = classify(e);
+        let name = self.backend.name();
+        counter!("cachebolt_backend_errors_total", "backend" => name, "class" => class.as_str())
+            .increment(1);
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.trial_in_flight = false;
+        if !class.trips_breaker() {
+            return;
+        }
+        inner.last_error = Some(e.to_string());
+
+        match inner.state {
+            BreakerState::Closed => {
+                inner.consecutive_failures += 1;
+                warn!(
+                    "{} {} error counted ({}) {}/{}",
+                    name,
+                    operation,
+                    class.as_str(),
+                    inner.consecutive_failures,
+                    self.threshold
+                );
+                if self.threshold > 0 && inner.consecutive_failures > self.threshold {
+                    error!(
+                        "{} errors exceeded threshold ({} > {}). Opening circuit breaker",
+                        name, inner.consecutive_failures, self.threshold
+                    );
+                    self.open(&mut inner);
+                }
+            }
+            BreakerState::HalfOpen => {
+                warn!("{} trial {} failed ({}). Reopening circuit breaker", name, operation, class.as_str());
+                self.open(&mut inner);
+            }
+            BreakerState::Open => {}
+        }
+    }
+
+    /// Reopens a half-open breaker whose trial permit was dropped without an outcome.
+    fn abandon_trial(self: &Arc<Self>) {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.trial_in_flight {
+            return;
+        }
+        inner.trial_in_flight = false;
+        if inner.state == BreakerState::HalfOpen {
+            warn!("{} trial call abandoned without a result. Reopening circuit breaker", self.backend.name());
+            self.open(&mut inner);
+        }
+    }
+
+    pub fn snapshot(&self) -> BreakerSnapshot {
+        let inner = self.inner.lock().unwrap();
+        BreakerSnapshot {
+            backend: self.backend.name(),
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            failure_threshold: self.threshold,
+            retry_interval_secs: self.retry_interval.as_secs(),
+            opened_at: inner.opened_at.map(|t| t.to_rfc3339()),
+            last_error: inner.last_error.clone(),
+        }
+    }
+
+    fn transition(&self, inner: &mut BreakerInner, to: BreakerState) {
+        inner.state = to;
+        gauge!("cachebolt_backend_circuit_state", "backend" => self.backend.name())
+            .set(to.gauge_value());
+        counter!(
+            "cachebolt_backend_circuit_transitions_total",
+            "backend" => self.backend.name(),
+            "to" => to.as_str()
+        )
+        .increment(1);
+    }
+
+    fn open(self: &Arc<Self>, inner: &mut BreakerInner) {
+        self.transition(inner, BreakerState::Open);
+        inner.consecutive_failures = 0;
+        inner.opened_at = Some(Utc::now());
+        self.start_probe();
+    }
+
+    /// Probes the backend every `retry_interval` while the breaker is open and
+    /// moves it to half-open on the first healthy probe.
+    fn start_probe(self: &Arc<Self>) {
+        let name = self.backend.name();
+        if self.retry_interval.is_zero() {
+            warn!("{} health probe disabled (interval = 0s); breaker stays open", name);
+            return;
+        }
+
+        info!("🩺 Starting {} health probe (interval {:?})", name, self.retry_interval);
+        let breaker = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(breaker.retry_interval).await;
+                if breaker.state() != BreakerState::Open {
+                    break;
+                }
+                match breaker.backend.health_check().await {
+                    Ok(()) => {
+                        let mut inner = breaker.inner.lock().unwrap();
+                        if inner.state == BreakerState::Open {
+                            breaker.transition(&mut inner, BreakerState::HalfOpen);
+                            info!("🩺 {} reachable again. Circuit breaker half-open", name);
+                        }
+                        break;
+                    }
+                    Err(e) => {
+                        warn!("⚠️ {} still unreachable (breaker open): {}", name, e);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Permission for one backend call, returned by `CircuitBreaker::allow`.
+/// Report the outcome with `success` or `failure`. A half-open trial permit
+/// dropped without an outcome, e.g. because its caller was cancelled, counts
+/// as a failed trial, so the trial slot is never held forever.
+#[must_use = "report the outcome of the call with `success` or `failure`"]
+pub struct BreakerPermit {
+    breaker: Arc<CircuitBreaker>,
+    /// This is the single half-open trial call.
+    trial: bool,
+    /// An outcome was reported.
+    settled: bool,
+}
+
+impl BreakerPermit {
+    /// Records a successful call (see `CircuitBreaker::record_success`).
+    pub fn success(mut self) {
+        self.settled = true;
+        self.breaker.record_success();
+    }
+
+    /// Records a failed call (see `CircuitBreaker::record_failure`).
+    pub fn failure(mut self, e: &(dyn Error + 'static), operation: &str) {
+        self.settled = true;
+        self.breaker.record_failure(e, operation);
+    }
+}
+
+impl Drop for BreakerPermit {
+    fn drop(&mut self) {
+        if self.trial && !self.settled {
+            self.breaker.abandon_trial();
+        }
+    }
+}
+
+/// Breakers by backend name, created on first use.
+static BREAKERS: Lazy<Mutex<HashMap<&'static str, Arc<CircuitBreaker>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Returns the breaker of `backend`, creating it from `storage_backend_failures`
+/// and `backend_retry_interval_secs` on first use.
+pub fn for_backend(backend: &Arc<dyn StorageBackend>) -> Arc<CircuitBreaker> {
+    let mut breakers = BREAKERS.lock().unwrap();
+    breakers
+        .entry(backend.name())
+        .or_insert_with(|| {
+            let (threshold, interval) = CONFIG
+                .get()
+                .map(|c| (c.storage_backend_failures, c.backend_retry_interval_secs))
+                .unwrap_or_default();
+            Arc::new(CircuitBreaker::new(
+                Arc::clone(backend),
+                threshold,
+                Duration::from_secs(interval),
+            ))
+        })
+        .clone()
+}
+
+/// Snapshots of every breaker created so far, sorted by backend name.
+pub fn snapshots() -> Vec<BreakerSnapshot> {
+    let mut all: Vec<_> = BREAKERS
+        .lock()
+        .unwrap()
+        .values()
+        .map(|b| b.snapshot())
+        .collect();
+    all.sort_by_key(|s| s.backend);
+    all
+}
@@ -15,97 +15,68 @@
 
 // GCS client and request types from the google-cloud-storage crate
 use google_cloud_storage::{
-    client::Client,
+    client::{Client, ClientConfig},
     http::objects::{
         delete::DeleteObjectRequest, download::Range, get::GetObjectRequest, upload::{Media, UploadObjectRequest, UploadType}
     },
 };
-use bytes::Bytes;
 use std::{borrow::Cow, error::Error};
 use std::sync::OnceLock;
-use flate2::write::GzEncoder;
-use flate2::read::GzDecoder;
-use flate2::Compression;
-use std::io::{Read, Write};
 use tracing::{info, error, warn};
 use crate::config::CONFIG;
-use serde::{Serialize, Deserialize};
-use base64::engine::general_purpose::STANDARD;
-use base64::Engine;
+use crate::memory::memory::CachedResponse;
+use crate::storage::{StorageBackend, StorageResult, StoredObject, blob, timestamp_to_datetime};
+use async_trait::async_trait;
+use bytes::Bytes;
 use google_cloud_storage::http::objects::list::ListObjectsRequest;
 
 /// Global singleton GCS client instance, initialized at runtime.
 pub static GCS_CLIENT: OnceLock<Client> = OnceLock::new();
 
-/// Serializable structure to store cached response data in GCS.
-/// - `body`: Base64-encoded response body.
-/// - `headers`: Associated response headers.
-#[derive(Serialize, Deserialize)]
-struct CachedBlob {
-    body: String,
-    headers: Vec<(String, String)>,
+/// Authenticates with Google Cloud (ADC or env vars) and initializes `GCS_CLIENT`.
+/// Calling it again once the client is set is a no-op.
+pub async fn init_gcs_client() -> Result<(), Box<dyn Error + Send + Sync>> {
+    if GCS_CLIENT.get().is_some() {
+        warn!("⚠️ GCS_CLIENT was already initialized");
+        return Ok(());
+    }
+
+    let gcs_config = ClientConfig::default()
+        .with_auth()
+        .await
+        .map_err(|e| format!("Failed to authenticate with GCS: {e}"))?;
+
+    let _ = GCS_CLIENT.set(Client::new(gcs_config));
+    Ok(())
 }
 
 /// Uploads a new cached object into GCS using the `cache/{app_id}/{key}` path.
-/// The body is base64-encoded, then compressed with Gzip before being stored.
+/// The entry is stored as a blob envelope (see `storage::blob`).
 ///
 /// # Arguments
 /// - `key`: Unique identifier for the object.
-/// - `data`: Raw body bytes to be cached.
-/// - `headers`: Response headers to store alongside the body.
-
-pub async fn store_in_cache(key: String, data: Bytes, headers: Vec<(String, String)>) {
+/// - `entry`: Cached response (body, headers and expiry metadata) to store.
+///
+/// # Returns
+/// - `Ok(())` once the object is uploaded.
+/// - `Err(...)` if the client is not ready, encoding fails, or the upload fails.
+pub async fn store_in_cache(key: String, entry: CachedResponse) -> Result<(), Box<dyn Error + Send + Sync>> {
     // Retrieve initialized GCS client
-    let client = match GCS_CLIENT.get() {
-        Some(c) => c,
-        None => {
-            error!("GCS client is not initialized");
-            return;
-        }
-    };
-
-    // Load bucket name from config
-    let bucket = match CONFIG.get() {
-        Some(cfg) => cfg.gcs_bucket.clone(),
-        None => {
-            error!("CONFIG is not initialized; cannot get GCS bucket");
-            return;
-        }
-    };
-
-    // Build a serializable blob (body + headers) using base64 encoding
-    let blob = CachedBlob {
-        body: STANDARD.encode(&data),
-        headers,
-    };
+    let client = GCS_CLIENT.get().ok_or("GCS client is not initialized")?;
 
-    // Serialize the struct into JSON
-    let json_bytes = match serde_json::to_vec(&blob) {
-        Ok(v) => v,
-        Err(e) => {
-            error!("Failed to serialize JSON for key '{key}': {e}");
-            return;
-        }
-    };
+    // Load bucket name and app id from config
+    let config = CONFIG
+        .get()
+        .ok_or("CONFIG is not initialized; cannot get GCS bucket")?;
+    let bucket = config.gcs_bucket.clone();
 
-    // Compress the JSON using Gzip
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-    if let Err(e) = encoder.write_all(&json_bytes) {
-        error!("Failed to compress data for key '{key}': {e}");
-        return;
-    }
-
-    let compressed = match encoder.finish() {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Failed to finalize compression for key '{key}': {e}");
-            return;
-        }
-    };
+    let encoded = blob::encode(&entry).map_err(|e| {
+        error!("Failed to encode blob for key '{key}': {e}");
+        e
+    })?;
 
     // Build storage path: cache/{app_id}/{key}
-    let app_id = &CONFIG.get().map(|c| c.app_id.clone()).unwrap_or_else(|| "default".into());
-    let path = format!("cache/{app_id}/{}", key);
+    let path = format!("cache/{}/{}", config.app_id, key);
 
     // Build GCS upload request
     let req = UploadObjectRequest {
@@ -115,29 +86,33 @@ pub async fn store_in_cache(key: String, data: Bytes, headers: Vec<(String, Stri
 
     let media = Media {
         name: Cow::Owned(path.clone()),
-        content_type: Cow::Borrowed("application/gzip"),
-        content_length: Some(compressed.len() as u64),
+        content_type: Cow::Borrowed("application/octet-stream"),
+        content_length: Some(encoded.len() as u64),
     };
 
     // Perform the upload using GCS simple upload API
-    if let Err(e) = client.upload_object(&req, compressed, &UploadType::Simple(media)).await {
-        error!("Failed to upload to GCS: bucket='{bucket}', object='{path}': {e}");
-    } else {
-        
-        info!("✅ Stored key '{key}' in GCS bucket '{bucket}'");
-    }
+    client
+        .upload_object(&req, encoded, &UploadType::Simple(media))
+        .await
+        .map_err(|e| {
+            error!("Failed to upload to GCS: bucket='{bucket}', object='{path}': {e}");
+            e
+        })?;
+
+    info!("✅ Stored key '{key}' in GCS bucket '{bucket}'");
+    Ok(())
 }
 
-/// Loads and decompresses a cached object from GCS using the key.
+/// Loads and decodes a cached object from GCS using the key.
+/// Both blob envelopes and legacy gzipped JSON objects are understood.
 ///
 /// # Arguments
 /// - `key`: The object key within the cache path.
 ///
 /// # Returns
-/// - `Some((body, headers))` on success
-/// - `None` if retrieval, decompression, or deserialization fails
-
-pub async fn load_from_cache(key: &str) -> Option<(Bytes, Vec<(String, String)>)> {
+/// - `Some(CachedResponse)` on success (expired entries included)
+/// - `None` if retrieval or decoding fails
+pub async fn load_from_cache(key: &str) -> Option<CachedResponse> {
     let client = GCS_CLIENT.get()?; // Get the global GCS client
     let bucket = CONFIG.get()?.gcs_bucket.clone(); // Load bucket from config
     let app_id = CONFIG.get().map(|c| c.app_id.clone()).unwrap_or_else(|| "default".into());
@@ -150,34 +125,14 @@ pub async fn load_from_cache(key: &str) -> Option<(Bytes, Vec<(String, String)>)
         ..Default::default()
     };
 
-    // Attempt to download the Gzipped object from GCS
     match client.download_object(&req, &Range::default()).await {
-        Ok(compressed) => {
-            let mut decoder = GzDecoder::new(&*compressed); // Create gzip reader
-            let mut decompressed = Vec::new();
-            if decoder.read_to_end(&mut decompressed).is_err() {
-                error!("Failed to decompress object '{path}' from bucket '{bucket}'");
-                return None;
+        Ok(raw) => match blob::decode_any(&raw, true) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                error!("Failed to decode object '{path}' from bucket '{bucket}': {e}");
+                None
             }
-
-            // Deserialize JSON into CachedBlob struct
-            match serde_json::from_slice::<CachedBlob>(&decompressed) {
-                Ok(blob) => {
-                    // Decode base64-encoded body
-                    match STANDARD.decode(&blob.body) {
-                        Ok(body) => Some((Bytes::from(body), blob.headers)),
-                        Err(e) => {
-                            error!("Failed to decode base64 for key '{key}': {e}");
-                            None
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to parse JSON for key '{key}': {e}");
-                    None
-                }
-            }
-        }
+        },
         Err(e) => {
             warn!("Failed to download object '{path}' from bucket '{bucket}': {e}");
             None
@@ -188,9 +143,9 @@ pub async fn load_from_cache(key: &str) -> Option<(Bytes, Vec<(String, String)>)
 /// Deletes all cached entries from GCS under `cache/{app_id}/`.
 ///
 /// # Returns
-/// - `Ok(count)` with number of objects deleted on success.
+/// - `Ok(keys)` with the keys of the objects deleted on success.
 /// - `Err(...)` if listing or deletion fails.
-pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Sync>> {
+pub async fn delete_all_from_cache() -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
     let client = GCS_CLIENT
         .get()
         .ok_or("GCS client is not initialized")?;
@@ -204,7 +159,7 @@ pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Syn
     let prefix = format!("cache/{app_id}/");
 
     let mut page_token: Option<String> = None;
-    let mut deleted = 0;
+    let mut deleted = Vec::new();
 
     loop {
         let list_req = ListObjectsRequest {
@@ -226,7 +181,7 @@ pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Syn
 
             match client.delete_object(&req).await {
                 Ok(_) => {
-                    deleted += 1;
+                    deleted.push(name[prefix.len()..].to_string());
                     info!("🗑️ Deleted '{name}' from bucket '{bucket}'");
                 }
                 Err(e) => {
@@ -245,6 +200,174 @@ pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Syn
         }
     }
 
-    info!("✅ Completed deletion of {deleted} objects under prefix '{prefix}'");
+    info!("✅ Completed deletion of {} objects under prefix '{prefix}'", deleted.len());
     Ok(deleted)
-}
\ No newline at end of file
+}
+
+/// Lists the cached entries stored in GCS under `cache/{app_id}/`.
+///
+/// # Returns
+/// - `Ok(objects)` with one `StoredObject` per cached entry.
+/// - `Err(...)` if listing fails.
+pub async fn list_cache_entries() -> Result<Vec<StoredObject>, Box<dyn Error + Send + Sync>> {
+    let client = GCS_CLIENT
+        .get()
+        .ok_or("GCS client is not initialized")?;
+
+    let config = CONFIG
+        .get()
+        .ok_or("CONFIG is not initialized")?;
+
+    let prefix = format!("cache/{}/", config.app_id);
+    let mut page_token: Option<String> = None;
+    let mut listed = Vec::new();
+
+    loop {
+        let list_req = ListObjectsRequest {
+            bucket: config.gcs_bucket.clone(),
+            prefix: Some(prefix.clone()),
+            page_token: page_token.clone(),
+            ..Default::default()
+        };
+
+        let objects = client.list_objects(&list_req).await?;
+
+        for obj in objects.items.unwrap_or_default() {
+            let Some(key) = obj.name.strip_prefix(&prefix) else { continue };
+            listed.push(StoredObject {
+                key: key.to_string(),
+                last_modified: timestamp_to_datetime(obj.updated.map(|t| t.unix_timestamp())),
+                size: obj.size.max(0) as u64,
+            });
+        }
+
+        match objects.next_page_token {
+            Some(token) if !token.is_empty() => page_token = Some(token),
+            _ => break,
+        }
+    }
+
+    Ok(listed)
+}
+
+/// Deletes the cached object of a single key from GCS.
+///
+/// # Returns
+/// - `Ok(true)` if the object was deleted.
+/// - `Ok(false)` if there was no object for the key.
+/// - `Err(...)` if the deletion fails.
+pub async fn delete_from_cache(key: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let client = GCS_CLIENT.get().ok_or("GCS client is not initialized")?;
+    let config = CONFIG.get().ok_or("CONFIG is not initialized")?;
+    let path = format!("cache/{}/{}", config.app_id, key);
+
+    let req = DeleteObjectRequest {
+        bucket: config.gcs_bucket.clone(),
+        object: path.clone(),
+        ..Default::default()
+    };
+    match client.delete_object(&req).await {
+        Ok(()) => {}
+        Err(google_cloud_storage::http::Error::Response(e)) if e.code == 404 => return Ok(false),
+        Err(e) => return Err(e.into()),
+    }
+
+    info!("🗑️ Deleted '{path}' from bucket '{}'", config.gcs_bucket);
+    Ok(true)
+}
+
+/// Single connectivity check to the configured GCS bucket.
+pub async fn check_bucket_connection() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let client = GCS_CLIENT.get().ok_or("GCS client is not initialized")?;
+    let config = CONFIG.get().ok_or("CONFIG is not initialized")?;
+
+    let req = ListObjectsRequest {
+        bucket: config.gcs_bucket.clone(),
+        max_results: Some(1),
+        ..Default::default()
+    };
+    client.list_objects(&req).await?;
+    Ok(())
+}
+
+/// Loads the metadata object `meta/{app_id}/{name}`, outside the listed
+/// `cache/{app_id}/` prefix. `Ok(None)` if it does not exist.
+pub async fn load_meta(name: &str) -> Result<Option<Vec<u8>>, Box<dyn Error + Send + Sync>> {
+    let client = GCS_CLIENT.get().ok_or("GCS client is not initialized")?;
+    let config = CONFIG.get().ok_or("CONFIG is not initialized")?;
+
+    let req = GetObjectRequest {
+        bucket: config.gcs_bucket.clone(),
+        object: format!("meta/{}/{}", config.app_id, name),
+        ..Default::default()
+    };
+    match client.download_object(&req, &Range::default()).await {
+        Ok(raw) => Ok(Some(raw)),
+        Err(google_cloud_storage::http::Error::Response(e)) if e.code == 404 => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Stores the metadata object `meta/{app_id}/{name}`.
+pub async fn store_meta(name: &str, data: Vec<u8>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let client = GCS_CLIENT.get().ok_or("GCS client is not initialized")?;
+    let config = CONFIG.get().ok_or("CONFIG is not initialized")?;
+
+    let req = UploadObjectRequest {
+        bucket: config.gcs_bucket.clone(),
+        ..Default::default()
+    };
+    let media = Media {
+        name: Cow::Owned(format!("meta/{}/{}", config.app_id, name)),
+        content_type: Cow::Borrowed("application/json"),
+        content_length: Some(data.len() as u64),
+    };
+    client.upload_object(&req, data, &UploadType::Simple(media)).await?;
+    Ok(())
+}
+
+/// Google Cloud Storage backend: one blob envelope per key under `cache/{app_id}/`.
+pub struct GcsBackend;
+
+#[async_trait]
+impl StorageBackend for GcsBackend {
+    fn name(&self) -> &'static str {
+        "gcs"
+    }
+
+    async fn init(&self) -> StorageResult<()> {
+        init_gcs_client().await
+    }
+
+    async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+        Ok(load_from_cache(key).await)
+    }
+
+    async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+        store_in_cache(key.to_string(), entry).await
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        delete_from_cache(key).await
+    }
+
+    async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+        list_cache_entries().await
+    }
+
+    async fn delete_all(&self) -> StorageResult<Vec<String>> {
+        delete_all_from_cache().await
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        check_bucket_connection().await
+    }
+
+    async fn get_meta(&self, name: &str) -> StorageResult<Option<Bytes>> {
+        Ok(load_meta(name).await?.map(Bytes::from))
+    }
+
+    async fn put_meta(&self, name: &str, data: Bytes) -> StorageResult<()> {
+        store_meta(name, data.to_vec()).await
+    }
+}
@@ -12,207 +12,595 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::config::CONFIG;
-use base64::Engine;
-use base64::engine::general_purpose::STANDARD;
+/// Local filesystem backend: one blob envelope file per key under `local_storage.root`.
+///
+/// - Files are sharded by a hash of the key into two directory levels,
+///   `{root}/{app_id}/{h[0..2]}/{h[2..4]}/{key}.gz`. Files of the former flat
+///   layout (`{root}/{app_id}/{key}.gz`) are still read, listed and deleted.
+/// - Entries are written to a temporary file that is renamed over the old
+///   one, so a crash never leaves a truncated entry behind.
+/// - File I/O goes through `tokio::fs`; directory walks run on the blocking pool.
+/// - With `local_storage.max_bytes` set, the least recently read entries are
+///   removed once the app's files grow past the quota.
+use async_trait::async_trait;
 use bytes::Bytes;
-use flate2::{Compression, read::GzDecoder, write::GzEncoder};
-use serde::{Deserialize, Serialize};
-use std::{
-    error::Error, fs::{self, File}, io::{Read, Write}, path::PathBuf
-};
+use metrics::{counter, gauge};
+use sha2::{Digest, Sha256};
+use std::fs::FileTimes;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncWriteExt;
+use tokio::{fs, task};
 use tracing::{error, info, warn};
-use std::fs::read_dir;
 
-/// Struct representing a cached response.
-/// - `body`: Base64-encoded body bytes.
-/// - `headers`: Response headers as key-value pairs.
-#[derive(Serialize, Deserialize)]
-pub struct CachedBlob {
-    pub body: String,
-    pub headers: Vec<(String, String)>,
+use crate::config::{CONFIG, Config};
+use crate::memory::memory::CachedResponse;
+use crate::storage::breaker::{BackendError, ErrorClass};
+use crate::storage::{StorageBackend, StorageResult, StorageUsage, StoredObject, blob};
+
+/// Approximate size of this app's cache files, resynced by every full scan.
+static USED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Set while a quota cleanup is running, so puts do not start another one.
+static CLEANING: AtomicBool = AtomicBool::new(false);
+
+/// Suffix of the temporary files written before the rename.
+static TMP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Temporary files older than this were left by a crashed write and are removed.
+const STALE_TMP_AGE: Duration = Duration::from_secs(3600);
+
+/// Directory holding every cache file of the current `app_id`.
+fn app_dir(config: &Config) -> PathBuf {
+    Path::new(&config.local_storage.root).join(&config.app_id)
+}
+
+/// Rejects keys that cannot be used as a plain file name. Names starting with
+/// a dot are reserved for temporary files.
+fn validate_key(key: &str) -> StorageResult<()> {
+    if key.is_empty() || key.starts_with('.') || key.contains(['/', '\\', '\0']) {
+        return Err(format!("Invalid cache key for local storage: {key:?}").into());
+    }
+    Ok(())
 }
 
 /// Constructs the full filesystem path for a given cache key.
-/// Format: `storage/cache/{app_id}/{key}.gz`
+/// Format: `{root}/{app_id}/{h[0..2]}/{h[2..4]}/{key}.gz`, where `h` is the
+/// hex SHA-256 of the key.
+///
+/// Returns `None` if `CONFIG` is not set or the key is not a valid file name.
 pub fn build_local_cache_path(key: &str) -> Option<PathBuf> {
     let config = CONFIG.get()?;
-    let app_id = &config.app_id;
+    validate_key(key).ok()?;
 
-    let mut path = PathBuf::from("storage/cache");
-    path.push(app_id);
+    let hash = hex::encode(Sha256::digest(key.as_bytes()));
+    let mut path = app_dir(config);
+    path.push(&hash[0..2]);
+    path.push(&hash[2..4]);
     path.push(format!("{key}.gz"));
 
     Some(path)
 }
 
-/// Stores a base64+Gzip-encoded blob (body + headers) to local disk.
-/// Creates intermediate directories if needed.
+/// Path of a key in the former flat layout, `{root}/{app_id}/{key}.gz`.
+fn legacy_cache_path(key: &str) -> Option<PathBuf> {
+    let config = CONFIG.get()?;
+    validate_key(key).ok()?;
+    Some(app_dir(config).join(format!("{key}.gz")))
+}
+
+/// Adds to or removes from the tracked disk usage.
+fn track(added: u64, removed: u64) {
+    let update = |used: u64| Some(used.saturating_add(added).saturating_sub(removed));
+    let _ = USED_BYTES.fetch_update(Ordering::Relaxed, Ordering::Relaxed, update);
+    gauge!("cachebolt_local_storage_bytes").set(USED_BYTES.load(Ordering::Relaxed) as f64);
+}
+
+/// Removes a file and returns its size, or `None` if it did not exist.
+async fn remove_sized(path: &Path) -> io::Result<Option<u64>> {
+    let size = match fs::metadata(path).await {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match fs::remove_file(path).await {
+        Ok(()) => Ok(Some(size)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes `data` to a temporary file next to `path`, syncs it and renames it
+/// over `path`. The temporary file is removed if any step fails.
+async fn write_atomically(path: &Path, key: &str, data: &[u8]) -> io::Result<()> {
+    let seq = TMP_SEQ.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_file_name(format!(".{key}.{}-{seq}.tmp", std::process::id()));
+
+    let written = async {
+        let mut file = fs::File::create(&tmp).await?;
+        file.write_all(data).await?;
+        file.sync_data().await?;
+        fs::rename(&tmp, path).await
+    }
+    .await;
+
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp).await;
+    }
+    written
+}
+
+/// Stores an entry as a blob envelope (see `storage::blob`) on local disk.
+/// Creates intermediate directories if needed, removes a copy left in the
+/// flat layout and starts a quota cleanup if the disk quota is exceeded.
 ///
 /// # Arguments
 /// - `key`: Cache key used as filename.
-/// - `data`: Raw body bytes.
-/// - `headers`: HTTP headers to store.
-pub async fn store_in_cache(key: String, data: Bytes, headers: Vec<(String, String)>) {
-    let path = match build_local_cache_path(&key) {
-        Some(p) => p,
-        None => {
-            
-            error!("CONFIG is not initialized; cannot build cache path");
-            return;
-        }
-    };
+/// - `entry`: Cached response (body, headers and expiry metadata) to store.
+///
+/// # Returns
+/// - `Ok(())` once the file is in place.
+/// - `Err(...)` if the key is not a valid file name, the entry cannot be encoded, or the write fails.
+pub async fn store_in_cache(key: String, entry: CachedResponse) -> StorageResult<()> {
+    validate_key(&key)?;
+    let path = build_local_cache_path(&key)
+        .ok_or("CONFIG is not initialized; cannot build cache path")?;
 
-    // Ensure parent directory exists
+    // Ensure the shard directory exists
     if let Some(parent) = path.parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            
-            error!(
-                "Failed to create local storage directory {:?}: {}",
-                parent, e
-            );
-            return;
-        }
+        fs::create_dir_all(parent).await.map_err(|e| {
+            error!("Failed to create local storage directory {:?}: {}", parent, e);
+            e
+        })?;
     }
 
-    // Construct the CachedBlob struct to serialize
-    let blob = CachedBlob {
-        body: STANDARD.encode(&data),
-        headers,
+    let encoded = blob::encode(&entry).map_err(|e| {
+        error!("Failed to encode blob for key '{}': {}", key, e);
+        e
+    })?;
+
+    let previous = fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0);
+    write_atomically(&path, &key, &encoded).await.map_err(|e| {
+        error!("Failed to write cache file for key '{}': {}", key, e);
+        e
+    })?;
+
+    // The sharded file now supersedes any copy in the flat layout
+    let legacy = match legacy_cache_path(&key) {
+        Some(legacy) => remove_sized(&legacy).await.ok().flatten().unwrap_or(0),
+        None => 0,
     };
+    track(encoded.len() as u64, previous + legacy);
 
-    // Serialize to JSON
-    let json = match serde_json::to_vec(&blob) {
-        Ok(j) => j,
-        
+    info!("✅ Stored key '{}' in local cache at {:?}", key, path);
+    start_cleanup_if_over_quota();
+    Ok(())
+}
+
+/// Reads a whole file and marks it as accessed now, which drives the LRU
+/// order of the quota cleanup regardless of the filesystem's atime settings.
+fn read_and_touch(path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    // Best effort: a file we cannot touch is only evicted a bit early
+    let _ = file.set_times(FileTimes::new().set_accessed(SystemTime::now()));
+    Ok(data)
+}
+
+/// Loads a previously cached file from local filesystem and decodes it.
+/// Both blob envelopes and legacy gzipped JSON files are understood, in the
+/// sharded layout or else the flat one.
+///
+/// # Arguments
+/// - `key`: Cache key corresponding to filename.
+///
+/// # Returns
+/// - `Ok(Some(CachedResponse))` on success (expired entries included).
+/// - `Ok(None)` if there is no file for the key.
+/// - `Err(_)` if the file cannot be read or decoded; IO errors keep their
+///   kind, so the circuit breaker can tell a failing disk from a bad file.
+pub async fn load_from_cache(key: &str) -> StorageResult<Option<CachedResponse>> {
+    validate_key(key)?;
+    let (Some(sharded), Some(legacy)) = (build_local_cache_path(key), legacy_cache_path(key)) else {
+        return Err("CONFIG not initialized".into());
+    };
+
+    let read = task::spawn_blocking(move || {
+        match read_and_touch(&sharded) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                read_and_touch(&legacy).map(|raw| (legacy, raw))
+            }
+            read => read.map(|raw| (sharded, raw)),
+        }
+    })
+    .await;
+
+    let (path, raw) = match read {
+        Ok(Ok(found)) => found,
+        Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Ok(Err(e)) => {
+            warn!("Failed to read cached file for key '{}': {}", key, e);
+            return Err(e.into());
+        }
         Err(e) => {
-            
-            error!("Failed to serialize blob for '{}': {}", key, e);
-            return;
+            error!("Local cache read task failed for key '{}': {}", key, e);
+            return Err(e.into());
         }
     };
 
-    // Compress the JSON using gzip
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-    if let Err(e) = encoder.write_all(&json) {
-        
-        error!("Failed to compress data for key '{}': {}", key, e);
-        return;
+    blob::decode_any(&raw, true).map(Some).map_err(|e| {
+        error!("Failed to decode local cache file {:?}: {}", path, e);
+        BackendError::new(ErrorClass::Corrupt, e).into()
+    })
+}
+
+/// Loads the metadata file `name`, kept at the top of the app directory (the
+/// scan only picks up `.gz` entries and temporary files, so it is never listed).
+pub async fn load_meta(name: &str) -> StorageResult<Option<Vec<u8>>> {
+    validate_key(name)?;
+    let config = CONFIG.get().ok_or("CONFIG not initialized")?;
+    match fs::read(app_dir(config).join(name)).await {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
     }
+}
 
-    
-    let compressed = match encoder.finish() {
-        Ok(c) => c,
-        
-        Err(e) => {
-            
-            error!("Failed to finalize compression for key '{}': {}", key, e);
-            return;
-        }
+/// Stores the metadata file `name` atomically.
+pub async fn store_meta(name: &str, data: &[u8]) -> StorageResult<()> {
+    validate_key(name)?;
+    let config = CONFIG.get().ok_or("CONFIG not initialized")?;
+    let dir = app_dir(config);
+    fs::create_dir_all(&dir).await?;
+    write_atomically(&dir.join(name), name, data).await?;
+    Ok(())
+}
+
+/// A file found under the app directory.
+struct CacheFile {
+    path: PathBuf,
+    /// Cache key, or `None` for a temporary file.
+    key: Option<String>,
+    size: u64,
+    modified: SystemTime,
+    accessed: SystemTime,
+}
+
+/// Walks the app directory: entry files in the flat layout at the top, and
+/// in the two shard levels below it. A missing directory has no files.
+fn scan(dir: &Path) -> io::Result<Vec<CacheFile>> {
+    let mut files = Vec::new();
+    scan_level(dir, 0, &mut files)?;
+    Ok(files)
+}
+
+fn scan_level(dir: &Path, depth: usize, files: &mut Vec<CacheFile>) -> io::Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
     };
 
-    // Write compressed data to file
-    match File::create(&path) {
-        Ok(mut file) => {
-            if let Err(e) = file.write_all(&compressed) {
-                
-                error!("Failed to write compressed file for key '{}': {}", key, e);
-            } else {
-                
-                info!("✅ Stored key '{}' in local cache at {:?}", key, path);
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if depth < 2 {
+                scan_level(&path, depth + 1, files)?;
             }
+            continue;
         }
-        Err(e) => {
-            
-            error!("Failed to create file for key '{}': {}", key, e);
+        if depth == 1 {
+            continue;
         }
+
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let key = if name.starts_with('.') && name.ends_with(".tmp") {
+            None
+        } else if let Some(key) = name.strip_suffix(".gz") {
+            Some(key.to_string())
+        } else {
+            continue;
+        };
+
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        files.push(CacheFile {
+            key,
+            size: metadata.len(),
+            modified,
+            accessed: metadata.accessed().unwrap_or(modified),
+            path,
+        });
     }
+    Ok(())
 }
 
-/// Loads a previously cached blob from local filesystem, decompresses and decodes it.
-///
-/// # Arguments
-/// - `key`: Cache key corresponding to filename.
+/// Runs `f` on the files of the current `app_id`, on the blocking thread pool.
+async fn with_files<T, F>(f: F) -> StorageResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&Path, Vec<CacheFile>) -> T + Send + 'static,
+{
+    let config = CONFIG.get().ok_or("CONFIG is not initialized; cannot scan local cache")?;
+    let dir = app_dir(config);
+
+    let result = task::spawn_blocking(move || scan(&dir).map(|files| f(&dir, files))).await?;
+    result.map_err(|e| format!("Failed to read local cache directory: {e}").into())
+}
+
+/// Deletes all cached files for the current `app_id` from local storage.
 ///
 /// # Returns
-/// - Some((body_bytes, headers)) on success.
-/// - None on error or file not found.
-pub async fn load_from_cache(key: &str) -> Option<(Bytes, Vec<(String, String)>)> {
-    let path = build_local_cache_path(key)?;
-
-    // Read compressed file from disk
-    let compressed = match fs::read(&path) {
-        Ok(data) => data,
-        Err(e) => {
-            warn!("Failed to read cached file {:?}: {}", path, e);
-            return None;
+/// - `Ok(keys)` with the keys of the entries deleted.
+/// - `Err(...)` if the directory cannot be read.
+pub async fn delete_all_from_cache() -> StorageResult<Vec<String>> {
+    let (mut deleted, freed) = with_files(|dir, files| {
+        let (mut deleted, mut freed) = (Vec::new(), 0);
+        for file in files {
+            match std::fs::remove_file(&file.path) {
+                Ok(()) => {
+                    freed += file.size;
+                    deleted.extend(file.key);
+                }
+                Err(e) => warn!("⚠️ Failed to delete file {:?}: {}", file.path, e),
+            }
         }
-    };
+        remove_empty_shards(dir);
+        (deleted, freed)
+    })
+    .await?;
 
-    // Decompress using gzip
-    let mut decoder = GzDecoder::new(&compressed[..]);
-    let mut decompressed = Vec::new();
-    if let Err(e) = decoder.read_to_end(&mut decompressed) {
-        error!("Failed to decompress local cache file {:?}: {}", path, e);
-        return None;
-    }
+    // A key may have had a copy in both layouts
+    deleted.sort_unstable();
+    deleted.dedup();
+    track(0, freed);
+    info!("✅ Deleted {} local cache files", deleted.len());
+    Ok(deleted)
+}
 
-    // Parse JSON blob and decode body
-    match serde_json::from_slice::<CachedBlob>(&decompressed) {
-        Ok(blob) => match STANDARD.decode(&blob.body) {
-            Ok(decoded) => Some((Bytes::from(decoded), blob.headers)),
-            Err(e) => {
-                error!("Failed to decode base64 body for key '{}': {}", key, e);
-                None
+/// Removes the shard directories left empty (non-empty ones are kept).
+fn remove_empty_shards(dir: &Path) {
+    let Ok(outer) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for shard in outer.flatten().map(|e| e.path()).filter(|p| p.is_dir()) {
+        if let Ok(inner) = std::fs::read_dir(&shard) {
+            for sub in inner.flatten() {
+                let _ = std::fs::remove_dir(sub.path());
             }
-        },
-        Err(e) => {
-            error!("Failed to parse cached JSON for key '{}': {}", key, e);
-            None
         }
+        let _ = std::fs::remove_dir(&shard);
     }
 }
 
-/// Deletes all cached files for the current `app_id` from local storage.
+/// Lists the cached files for the current `app_id` in local storage.
 ///
 /// # Returns
-/// - `true` if all matching files were deleted or folder was empty.
-/// - `false` if some deletions failed or CONFIG was not initialized.
-/// Deletes all cached files for the current `app_id` from local storage.
+/// - `Ok(objects)` with one `StoredObject` per entry file (empty if the directory is missing).
+/// - `Err(...)` if the directory cannot be read.
+pub async fn list_cache_entries() -> StorageResult<Vec<StoredObject>> {
+    with_files(|_, files| {
+        files
+            .into_iter()
+            .filter_map(|file| {
+                Some(StoredObject {
+                    key: file.key?,
+                    last_modified: file.modified.into(),
+                    size: file.size,
+                })
+            })
+            .collect()
+    })
+    .await
+}
+
+/// Deletes the cached file for a single key, in both layouts.
 ///
 /// # Returns
-/// - `Ok(count)` with number of files deleted.
-/// - `Err(...)` if reading or deleting fails.
-pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Sync>> {
-    let config = CONFIG
-        .get()
-        .ok_or("CONFIG is not initialized; cannot delete local cache")?;
-
-    let dir_path = PathBuf::from(format!("storage/cache/{}", config.app_id));
-    let mut deleted = 0;
-
-    match read_dir(&dir_path) {
-        Ok(entries) => {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().and_then(|e| e.to_str()) == Some("gz") {
-                    match fs::remove_file(&path) {
-                        Ok(_) => {
-                            deleted += 1;
-                            info!("🗑️ Deleted local cache file {:?}", path);
-                        }
-                        Err(e) => {
-                            warn!("⚠️ Failed to delete file {:?}: {}", path, e);
-                        }
-                    }
-                }
+/// - `Ok(true)` if a file was deleted, `Ok(false)` if none existed.
+/// - `Err(...)` if the deletion fails.
+pub async fn delete_from_cache(key: &str) -> StorageResult<bool> {
+    CONFIG.get().ok_or("CONFIG is not initialized; cannot build cache path")?;
+    let paths = [build_local_cache_path(key), legacy_cache_path(key)];
+    let [Some(sharded), Some(legacy)] = paths else {
+        return Ok(false);
+    };
+
+    let mut freed = None;
+    for path in [sharded, legacy] {
+        match remove_sized(&path).await {
+            Ok(Some(size)) => {
+                info!("🗑️ Deleted local cache file {:?}", path);
+                *freed.get_or_insert(0) += size;
             }
+            Ok(None) => {}
+            Err(e) => return Err(format!("Failed to delete local cache file {:?}: {e}", path).into()),
+        }
+    }
+
+    track(0, freed.unwrap_or(0));
+    Ok(freed.is_some())
+}
+
+/// Number of entries and bytes on disk for the current `app_id`, from a
+/// full scan that also resyncs the tracked usage.
+pub async fn storage_usage() -> StorageResult<StorageUsage> {
+    let usage = with_files(|_, files| {
+        let entries = files.iter().filter(|f| f.key.is_some()).count() as u64;
+        let bytes = files.iter().map(|f| f.size).sum();
+        StorageUsage { entries, bytes }
+    })
+    .await?;
+
+    USED_BYTES.store(usage.bytes, Ordering::Relaxed);
+    gauge!("cachebolt_local_storage_bytes").set(usage.bytes as f64);
+    Ok(usage)
+}
 
-            info!("✅ Deleted {deleted} local cache files under {:?}", dir_path);
-            Ok(deleted)
+/// Removes temporary files left by crashed writes and, when the app's files
+/// exceed `local_storage.max_bytes`, the least recently read entries until
+/// usage is back under `cleanup_target_percent` of the quota.
+///
+/// # Returns
+/// - `Ok(count)` with the number of entries evicted.
+/// - `Err(...)` if the directory cannot be read.
+pub async fn enforce_quota() -> StorageResult<usize> {
+    let settings = &CONFIG.get().ok_or("CONFIG is not initialized")?.local_storage;
+    let max_bytes = settings.max_bytes;
+    let target = (u128::from(max_bytes) * u128::from(settings.cleanup_target_percent) / 100) as u64;
+
+    let (evicted, used) = with_files(move |_, files| evict(files, max_bytes, target)).await?;
+
+    USED_BYTES.store(used, Ordering::Relaxed);
+    gauge!("cachebolt_local_storage_bytes").set(used as f64);
+    if evicted > 0 {
+        counter!("cachebolt_local_evictions_total").increment(evicted as u64);
+        info!(
+            "🧹 Local storage over its {} byte quota: evicted {} entries, {} bytes left",
+            max_bytes, evicted, used
+        );
+    }
+    Ok(evicted)
+}
+
+/// Eviction pass over a scan; returns the entries evicted and the bytes left.
+fn evict(files: Vec<CacheFile>, max_bytes: u64, target: u64) -> (usize, u64) {
+    let stale_before = SystemTime::now() - STALE_TMP_AGE;
+    let (mut entries, temporary): (Vec<_>, Vec<_>) =
+        files.into_iter().partition(|f| f.key.is_some());
+
+    let mut used = 0;
+    for file in temporary {
+        if file.modified < stale_before && std::fs::remove_file(&file.path).is_ok() {
+            continue;
         }
-        Err(e) => {
-            Err(format!("Failed to read local cache directory: {e}").into())
+        used += file.size;
+    }
+    used += entries.iter().map(|f| f.size).sum::<u64>();
+
+    if max_bytes == 0 || used <= max_bytes {
+        return (0, used);
+    }
+
+    entries.sort_by_key(|f| f.accessed);
+    let mut evicted = 0;
+    for file in entries {
+        if used <= target {
+            break;
         }
+        match std::fs::remove_file(&file.path) {
+            Ok(()) => evicted += 1,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                warn!("⚠️ Failed to evict local cache file {:?}: {}", file.path, e);
+                continue;
+            }
+        }
+        used -= file.size;
     }
-}
\ No newline at end of file
+    (evicted, used)
+}
+
+/// Starts a background quota cleanup if the tracked usage exceeds the quota
+/// and none is running yet.
+fn start_cleanup_if_over_quota() {
+    let Some(config) = CONFIG.get() else {
+        return;
+    };
+    let max_bytes = config.local_storage.max_bytes;
+    if max_bytes == 0 || USED_BYTES.load(Ordering::Relaxed) <= max_bytes {
+        return;
+    }
+    if CLEANING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    tokio::spawn(async {
+        if let Err(e) = enforce_quota().await {
+            warn!("⚠️ Local storage quota cleanup failed: {}", e);
+        }
+        CLEANING.store(false, Ordering::Release);
+    });
+}
+
+/// Creates the app directory, removes leftovers of crashed writes and applies
+/// the disk quota.
+pub async fn init_local() -> StorageResult<()> {
+    check_cache_dir().await?;
+    let evicted = enforce_quota().await?;
+    let usage = storage_usage().await?;
+    info!(
+        "📦 Local cache opened: {} entries, {} bytes ({} evicted)",
+        usage.entries, usage.bytes, evicted
+    );
+    Ok(())
+}
+
+/// Checks that the local cache directory exists (creating it if needed).
+pub async fn check_cache_dir() -> StorageResult<()> {
+    let config = CONFIG
+        .get()
+        .ok_or("CONFIG is not initialized; cannot check local cache")?;
+
+    let dir_path = app_dir(config);
+    fs::create_dir_all(&dir_path)
+        .await
+        .map_err(|e| format!("Local cache directory {:?} is not usable: {e}", dir_path))?;
+    Ok(())
+}
+
+/// Local filesystem backend (`{root}/{app_id}/{h[0..2]}/{h[2..4]}/{key}.gz`).
+pub struct LocalBackend;
+
+#[async_trait]
+impl StorageBackend for LocalBackend {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn init(&self) -> StorageResult<()> {
+        init_local().await
+    }
+
+    async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+        load_from_cache(key).await
+    }
+
+    async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+        store_in_cache(key.to_string(), entry).await
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        delete_from_cache(key).await
+    }
+
+    async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+        list_cache_entries().await
+    }
+
+    async fn delete_all(&self) -> StorageResult<Vec<String>> {
+        delete_all_from_cache().await
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        check_cache_dir().await
+    }
+
+    async fn get_meta(&self, name: &str) -> StorageResult<Option<Bytes>> {
+        Ok(load_meta(name).await?.map(Bytes::from))
+    }
+
+    async fn put_meta(&self, name: &str, data: Bytes) -> StorageResult<()> {
+        store_meta(name, &data).await
+    }
+
+    async fn usage(&self) -> StorageResult<StorageUsage> {
+        storage_usage().await
+    }
+}
@@ -16,3 +16,262 @@ pub mod gcs;
 pub mod s3;
 pub mod azure;
 pub mod local;
+pub mod redis;
+pub mod sqlite;
+pub mod blob;
+pub mod breaker;
+pub mod tiered;
+pub mod queue;
+pub mod purge;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use once_cell::sync::OnceCell;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::Arc;
+
+use crate::cache_key::source_uri;
+use crate::cache_tags;
+use crate::config::{self, CONFIG};
+use crate::memory::memory::CachedResponse;
+
+/// Result type shared by every persistent backend.
+pub type StorageResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// A persistent cache backend (local disk, S3, GCS, Azure, or a fake in tests).
+///
+/// Entries are addressed by their cache key; each implementation decides how
+/// keys map to files, objects or blobs under its `cache/{app_id}/` namespace.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Short label used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Sets up clients for the backend. Called once at startup.
+    async fn init(&self) -> StorageResult<()> {
+        Ok(())
+    }
+
+    /// Loads an entry, expired ones included. `Ok(None)` on a miss.
+    async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>>;
+
+    /// Stores an entry, replacing any previous one under the same key.
+    async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()>;
+
+    /// Removes one entry. `Ok(false)` if there was nothing to delete.
+    async fn delete(&self, key: &str) -> StorageResult<bool>;
+
+    /// Lists every entry stored for the current `app_id`.
+    async fn list(&self) -> StorageResult<Vec<StoredObject>>;
+
+    /// Removes every entry stored for the current `app_id` and returns the
+    /// keys deleted.
+    async fn delete_all(&self) -> StorageResult<Vec<String>>;
+
+    /// Single connectivity check. `Ok(())` if the backend is reachable.
+    async fn health_check(&self) -> StorageResult<()>;
+
+    /// Removes every entry whose key source (the request URI its key was
+    /// derived from) starts with `prefix`, and returns the keys deleted.
+    ///
+    /// The default implementation loads every listed entry; backends that
+    /// index key sources override it.
+    async fn delete_source_prefix(&self, prefix: &str) -> StorageResult<Vec<String>> {
+        let mut deleted = Vec::new();
+        for object in self.list().await? {
+            let Some(entry) = self.get(&object.key).await? else {
+                continue;
+            };
+            if entry.key_source.starts_with(prefix) && self.delete(&object.key).await? {
+                deleted.push(object.key);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Removes every entry whose request URI (see `cache_key::source_uri`)
+    /// matches `pattern`, and returns the keys deleted. Loads every listed
+    /// entry.
+    async fn delete_source_matching(&self, pattern: &Regex) -> StorageResult<Vec<String>> {
+        let mut deleted = Vec::new();
+        for object in self.list().await? {
+            let Some(entry) = self.get(&object.key).await? else {
+                continue;
+            };
+            if pattern.is_match(source_uri(&entry.key_source)) && self.delete(&object.key).await? {
+                deleted.push(object.key);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Removes every entry tagged with at least one of `tags` (see
+    /// `CachedResponse::tags`), and returns the keys deleted.
+    ///
+    /// The default implementation loads every listed entry; backends that
+    /// index tags override it.
+    async fn delete_tagged(&self, tags: &[String]) -> StorageResult<Vec<String>> {
+        let mut deleted = Vec::new();
+        for object in self.list().await? {
+            let Some(entry) = self.get(&object.key).await? else {
+                continue;
+            };
+            let tagged = entry.tags.iter().any(|tag| tags.contains(tag));
+            if tagged && self.delete(&object.key).await? {
+                deleted.push(object.key);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Loads the metadata object `name` (see `purge`), stored for the current
+    /// `app_id` apart from the entries. `Ok(None)` if it was never written.
+    async fn get_meta(&self, _name: &str) -> StorageResult<Option<Bytes>> {
+        Ok(None)
+    }
+
+    /// Stores the metadata object `name`, replacing any previous one.
+    async fn put_meta(&self, _name: &str, _data: Bytes) -> StorageResult<()> {
+        Err(format!("{} backend does not store metadata", self.name()).into())
+    }
+
+    /// Number of entries stored for the current `app_id` and their total size.
+    async fn usage(&self) -> StorageResult<StorageUsage> {
+        let objects = self.list().await?;
+        Ok(StorageUsage {
+            entries: objects.len() as u64,
+            bytes: objects.iter().map(|o| o.size).sum(),
+        })
+    }
+}
+
+/// Backend used by the proxy, chosen on first use.
+pub(crate) static BACKEND: OnceCell<Arc<dyn StorageBackend>> = OnceCell::new();
+
+/// Builds a single backend of the given kind.
+pub fn backend_for(kind: &config::StorageBackend) -> Arc<dyn StorageBackend> {
+    match kind {
+        config::StorageBackend::Azure => Arc::new(azure::AzureBackend),
+        config::StorageBackend::Gcs => Arc::new(gcs::GcsBackend),
+        config::StorageBackend::Local => Arc::new(local::LocalBackend),
+        config::StorageBackend::S3 => Arc::new(s3::S3Backend),
+        config::StorageBackend::Redis => Arc::new(redis::RedisBackend),
+        config::StorageBackend::Sqlite => Arc::new(sqlite::SqliteBackend),
+    }
+}
+
+/// Returns the backend in use: the installed one, otherwise the tiers
+/// selected by `storage.tiers` (or `storage_backend`). `None` until `CONFIG`
+/// is initialized, or if it selects no backend.
+pub fn backend() -> Option<Arc<dyn StorageBackend>> {
+    if let Some(backend) = BACKEND.get() {
+        return Some(backend.clone());
+    }
+    let config = CONFIG.get()?;
+    if config.storage_tiers().is_empty() {
+        return None;
+    }
+    Some(
+        BACKEND
+            .get_or_init(|| Arc::new(tiered::TieredStorage::from_config(config)))
+            .clone(),
+    )
+}
+
+/// Converts a persisted Unix timestamp (seconds) back into a `DateTime<Utc>`.
+/// Missing or out-of-range values map to the Unix epoch.
+pub fn timestamp_to_datetime(secs: Option<i64>) -> DateTime<Utc> {
+    secs.and_then(|s| DateTime::from_timestamp(s, 0))
+        .unwrap_or(DateTime::UNIX_EPOCH)
+}
+
+/// Expiry and key metadata persisted with every entry.
+/// Every field is optional on read so blobs written by older versions still load.
+#[derive(Serialize, Deserialize, Default)]
+pub struct EntryMeta {
+    /// Unix timestamps (seconds); absent in legacy blobs.
+    #[serde(default)]
+    pub inserted_at: Option<i64>,
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Whether the entry must not be served once stale.
+    #[serde(default)]
+    pub must_revalidate: bool,
+    /// Stale serving windows in seconds past expiry.
+    #[serde(default)]
+    pub stale_while_revalidate: u64,
+    #[serde(default)]
+    pub stale_if_error: u64,
+    /// Human-readable source the cache key was hashed from.
+    #[serde(default)]
+    pub key_source: String,
+}
+
+/// An entry found when listing a persistent backend, used to pick the most
+/// recent entries for the startup warm-up.
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    /// Cache key, without the `cache/{app_id}/` prefix or file extension.
+    pub key: String,
+    /// When the backend last wrote the entry.
+    pub last_modified: DateTime<Utc>,
+    /// Size of the stored (possibly compressed) object in bytes.
+    pub size: u64,
+}
+
+/// Entry count and stored size of a persistent backend.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StorageUsage {
+    pub entries: u64,
+    /// Size of the stored (possibly compressed) objects in bytes.
+    pub bytes: u64,
+}
+
+/// Seconds from `now` until the entry can no longer be served: the end of its
+/// freshness lifetime and stale windows, extended by `cache.max_stale_seconds`
+/// when stale entries may be served on error. `None` if it may be served
+/// stale indefinitely. Never less than one second.
+///
+/// Used by backends that expire entries on their own (Redis, SQLite).
+pub fn retention_secs(entry: &CachedResponse, now: DateTime<Utc>) -> Option<u64> {
+    let window = entry.stale_while_revalidate.max(entry.stale_if_error);
+    let mut until = entry.expires_at + chrono::Duration::seconds(window as i64);
+
+    let (policy, max_stale_secs) = CONFIG
+        .get()
+        .map(|c| (c.cache.expired_policy, c.cache.max_stale_seconds))
+        .unwrap_or_default();
+    if policy == config::ExpiredPolicy::ServeStaleOnError && !entry.must_revalidate {
+        if max_stale_secs == 0 {
+            return None;
+        }
+        until = until.max(entry.expires_at + chrono::Duration::seconds(max_stale_secs as i64));
+    }
+
+    Some((until - now).num_seconds().max(1) as u64)
+}
+
+/// Rebuilds a `CachedResponse` from the fields stored by a persistent backend.
+///
+/// Blobs written before expiry metadata was persisted carry no timestamps;
+/// they are treated as already expired so `cache.expired_policy` applies to them.
+/// Their tags are read back from the stored tag headers. These formats carry
+/// no status, so entries are served as 200 as they always were.
+pub fn entry_from_parts(body: Bytes, headers: Vec<(String, String)>, meta: EntryMeta) -> CachedResponse {
+    CachedResponse {
+        tags: cache_tags::from_headers(&headers),
+        status: 200,
+        body,
+        headers,
+        inserted_at: timestamp_to_datetime(meta.inserted_at),
+        expires_at: timestamp_to_datetime(meta.expires_at),
+        must_revalidate: meta.must_revalidate,
+        stale_while_revalidate: meta.stale_while_revalidate,
+        stale_if_error: meta.stale_if_error,
+        key_source: meta.key_source,
+        purged: false,
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Soft purges of the persistent backend, recorded as rules instead of
+/// rewriting the stored entries.
+///
+/// - A soft purge adds a rule (what it matches and when it was issued) to a
+///   small log stored next to the entries (see `StorageBackend::put_meta`), so
+///   it costs one read and one write whatever the size of the backend.
+/// - Entries read from the backend are checked against the rules: one stored
+///   before a rule that matches it comes back with `purged` set. Stored
+///   entries carry their insertion time to the second, so an entry stored in
+///   the same second as a purge counts as purged.
+/// - The log is loaded at startup and merged with the stored copy on every
+///   soft purge, which is when purges issued by other replicas are picked up.
+/// - A newer purge of the same scope replaces the older rule, and a purge of
+///   everything replaces every earlier rule. Deleting every entry clears the log.
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::{PoisonError, RwLock};
+use tokio::sync::Mutex;
+
+use crate::cache_key::source_uri;
+use crate::memory::memory::CachedResponse;
+use crate::storage::{StorageBackend, StorageResult};
+
+/// Name of the metadata object holding the log.
+const LOG_NAME: &str = "purge-rules.json";
+
+/// What a soft purge matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PurgeScope {
+    /// Every entry.
+    All,
+    /// Entries stored under these keys.
+    Keys(Vec<String>),
+    /// Entries whose request URI starts with the prefix.
+    UriPrefix(String),
+    /// Entries whose request URI matches the regular expression.
+    UriRegex(String),
+    /// Entries carrying at least one of the tags.
+    Tags(Vec<String>),
+}
+
+/// A soft purge: entries stored up to `at` that `scope` matches are purged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PurgeRule {
+    /// Unix timestamp (seconds) the purge was issued at.
+    pub at: i64,
+    pub scope: PurgeScope,
+}
+
+/// A rule ready to be checked, with its regex compiled.
+struct ActiveRule {
+    rule: PurgeRule,
+    regex: Option<Regex>,
+}
+
+impl ActiveRule {
+    fn new(rule: PurgeRule) -> Self {
+        let regex = match &rule.scope {
+            PurgeScope::UriRegex(pattern) => Regex::new(pattern).ok(),
+            _ => None,
+        };
+        ActiveRule { rule, regex }
+    }
+
+    fn covers(&self, key: &str, entry: &CachedResponse) -> bool {
+        if entry.inserted_at.timestamp() > self.rule.at {
+            return false;
+        }
+        match &self.rule.scope {
+            PurgeScope::All => true,
+            PurgeScope::Keys(keys) => keys.iter().any(|k| k == key),
+            PurgeScope::UriPrefix(prefix) => source_uri(&entry.key_source).starts_with(prefix),
+            PurgeScope::UriRegex(_) => self
+                .regex
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(source_uri(&entry.key_source))),
+            PurgeScope::Tags(tags) => entry.tags.iter().any(|tag| tags.contains(tag)),
+        }
+    }
+}
+
+/// Rules known to this process.
+static RULES: Lazy<RwLock<Vec<ActiveRule>>> = Lazy::new(Default::default);
+
+/// Serializes the read-merge-write of the stored log.
+static LOG_WRITE: Mutex<()> = Mutex::const_new(());
+
+/// Sets `entry.purged` if a recorded soft purge covers the entry stored under `key`.
+pub fn apply(key: &str, entry: &mut CachedResponse) {
+    if entry.purged {
+        return;
+    }
+    let rules = RULES.read().unwrap_or_else(PoisonError::into_inner);
+    entry.purged = rules.iter().any(|rule| rule.covers(key, entry));
+}
+
+/// Replaces the rules known to this process with the log stored in `backend`.
+///
+/// # Returns
+/// The number of rules loaded.
+pub async fn load(backend: &dyn StorageBackend) -> StorageResult<usize> {
+    let rules = read_log(backend).await?;
+    let count = rules.len();
+    activate(rules);
+    Ok(count)
+}
+
+/// Records a soft purge of every scope in `scopes`, issued now.
+pub async fn record(backend: &dyn StorageBackend, scopes: Vec<PurgeScope>) -> StorageResult<()> {
+    let _guard = LOG_WRITE.lock().await;
+    let at = Utc::now().timestamp();
+    let mut rules = read_log(backend).await?;
+    for scope in scopes {
+        add_rule(&mut rules, PurgeRule { at, scope });
+    }
+    write_log(backend, rules).await
+}
+
+/// Clears the log, once every stored entry has been deleted.
+pub async fn clear(backend: &dyn StorageBackend) -> StorageResult<()> {
+    let _guard = LOG_WRITE.lock().await;
+    write_log(backend, Vec::new()).await
+}
+
+/// Adds `rule` to the log, dropping the rules it supersedes.
+fn add_rule(rules: &mut Vec<PurgeRule>, rule: PurgeRule) {
+    if rule.scope == PurgeScope::All {
+        rules.clear();
+    } else {
+        rules.retain(|existing| existing.scope != rule.scope);
+    }
+    rules.push(rule);
+}
+
+async fn read_log(backend: &dyn StorageBackend) -> StorageResult<Vec<PurgeRule>> {
+    match backend.get_meta(LOG_NAME).await? {
+        Some(raw) => Ok(serde_json::from_slice(&raw)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+async fn write_log(backend: &dyn StorageBackend, rules: Vec<PurgeRule>) -> StorageResult<()> {
+    backend.put_meta(LOG_NAME, serde_json::to_vec(&rules)?.into()).await?;
+    activate(rules);
+    Ok(())
+}
+
+fn activate(rules: Vec<PurgeRule>) {
+    let active = rules.into_iter().map(ActiveRule::new).collect();
+    *RULES.write().unwrap_or_else(PoisonError::into_inner) = active;
+}
@@ -0,0 +1,538 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Background queue that writes new cache entries to the persistent storage.
+///
+/// - Failed writes are retried with exponential backoff, up to
+///   `persistence.max_retries` times.
+/// - When the queue is full, new entries are dropped or the request waits,
+///   depending on `persistence.full_policy`.
+/// - Entries that are dropped, or still queued when shutdown runs out of
+///   time, are spilled to `persistence.journal_path` if set. The journal is
+///   replayed into the queue on the next start.
+/// - With a journal, every accepted entry is first appended to a write-ahead
+///   log (`<journal_path>.wal`), cleared whenever the queue drains. A log left
+///   behind by a crash is moved into the journal on the next start, so entries
+///   still queued when the process died are replayed too.
+use metrics::{counter, gauge};
+use once_cell::sync::OnceCell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, mpsc, watch};
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::config::{CONFIG, PersistenceSettings, QueueFullPolicy};
+use crate::memory::memory::CachedResponse;
+use crate::storage::{self, StorageBackend, StorageResult, blob};
+
+/// A single persistence job.
+struct PersistJob {
+    key: String,
+    entry: CachedResponse,
+    /// Backend written instead of the queue's own, e.g. the lower tiers of a
+    /// write-back. Spilled jobs are replayed into the queue's own backend.
+    target: Option<Arc<dyn StorageBackend>>,
+}
+
+/// How long shutdown waits for the remaining jobs to be spilled once the
+/// flush timeout has passed.
+const SPILL_GRACE: Duration = Duration::from_secs(1);
+
+/// Global queue feeding the configured persistent backend.
+static QUEUE: OnceCell<PersistQueue> = OnceCell::new();
+
+/// Queue of entries waiting to be written to a persistent backend.
+pub struct PersistQueue {
+    tx: mpsc::Sender<PersistJob>,
+    full_policy: QueueFullPolicy,
+    shared: Arc<Shared>,
+}
+
+/// State shared between the queue handle and its worker.
+struct Shared {
+    backend: Arc<dyn StorageBackend>,
+    settings: PersistenceSettings,
+    journal: Option<Journal>,
+    /// Write-ahead log of the accepted jobs; set along with `journal`.
+    wal: Option<Journal>,
+    /// Jobs accepted by the queue and not yet written, spilled or dropped.
+    pending: AtomicUsize,
+    /// Set when shutdown runs out of time: remaining jobs go to the journal.
+    abandon: watch::Sender<bool>,
+}
+
+impl PersistQueue {
+    /// Creates the queue and spawns its worker. Must be called within a Tokio runtime.
+    pub fn start(backend: Arc<dyn StorageBackend>, settings: PersistenceSettings) -> Self {
+        let (tx, rx) = mpsc::channel(settings.queue_capacity.max(1));
+        let journal = (!settings.journal_path.trim().is_empty())
+            .then(|| Journal::new(PathBuf::from(settings.journal_path.trim())));
+        let wal = journal.as_ref().map(|journal| {
+            let wal = Journal::new(journal.sibling("wal"));
+            recover_wal(&wal, journal);
+            wal
+        });
+        let shared = Arc::new(Shared {
+            backend,
+            journal,
+            wal,
+            pending: AtomicUsize::new(0),
+            abandon: watch::channel(false).0,
+            settings,
+        });
+        tokio::spawn(run_worker(Arc::clone(&shared), rx));
+
+        PersistQueue {
+            tx,
+            full_policy: shared.settings.full_policy,
+            shared,
+        }
+    }
+
+    /// Queues an entry for the backend.
+    ///
+    /// With the `drop` policy this never waits on the backend: if the queue is
+    /// full the entry is spilled to the journal, or dropped without one. With
+    /// `block` it waits until the queue has room.
+    pub async fn enqueue(&self, key: String, entry: CachedResponse) {
+        self.enqueue_job(PersistJob { key, entry, target: None }).await;
+    }
+
+    /// Queues an entry for `backend` instead of the queue's own backend,
+    /// with the same retries, overflow policy and journal.
+    pub async fn enqueue_to(
+        &self,
+        backend: Arc<dyn StorageBackend>,
+        key: String,
+        entry: CachedResponse,
+    ) {
+        self.enqueue_job(PersistJob { key, entry, target: Some(backend) }).await;
+    }
+
+    async fn enqueue_job(&self, job: PersistJob) {
+        // A slot is reserved before the job is counted, so a request dropped
+        // while waiting for room leaves no trace in the queue depth
+        let permit = match self.full_policy {
+            QueueFullPolicy::Block => self.tx.reserve().await.ok(),
+            QueueFullPolicy::Drop => self.tx.try_reserve().ok(),
+        };
+        match permit {
+            Some(permit) => {
+                self.shared.accept(&job).await;
+                permit.send(job);
+            }
+            None => self.shared.spill_or_drop(&job.key, &job.entry, "queue_full").await,
+        }
+    }
+
+    /// Number of entries queued or being written.
+    pub fn depth(&self) -> usize {
+        self.shared.pending.load(Ordering::SeqCst)
+    }
+
+    /// Queues the entries spilled to the journal by a previous run.
+    ///
+    /// The journal is renamed to `<journal_path>.replay` first, so entries
+    /// spilled while replaying start a new journal; the renamed file is
+    /// removed once every entry is queued, as replayed entries are written
+    /// ahead again. A replay file left behind by a crash is picked up again on
+    /// the next start.
+    ///
+    /// # Returns
+    /// The number of entries queued.
+    pub async fn replay_journal(&self) -> usize {
+        let Some(journal) = &self.shared.journal else {
+            return 0;
+        };
+
+        let replay_path = journal.replay_path();
+        if !replay_path.exists() {
+            let _guard = journal.lock.lock().await;
+            match tokio::fs::rename(&journal.path, &replay_path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return 0,
+                Err(e) => {
+                    error!("❌ Failed to rotate persistence journal {:?}: {}", journal.path, e);
+                    return 0;
+                }
+            }
+        }
+
+        let jobs = match read_journal(&replay_path).await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                error!("❌ Failed to read persistence journal {:?}: {}", replay_path, e);
+                return 0;
+            }
+        };
+
+        // Replayed entries always wait for room instead of being spilled again
+        let mut replayed = 0;
+        for (key, entry) in jobs {
+            let Ok(permit) = self.tx.reserve().await else {
+                break;
+            };
+            let job = PersistJob { key, entry, target: None };
+            self.shared.accept(&job).await;
+            permit.send(job);
+            replayed += 1;
+        }
+        counter!("cachebolt_persist_replayed_total").increment(replayed as u64);
+
+        if let Err(e) = tokio::fs::remove_file(&replay_path).await {
+            warn!("⚠️ Failed to remove replayed journal {:?}: {}", replay_path, e);
+        }
+        info!("📼 Replayed {} entries from the persistence journal", replayed);
+        replayed
+    }
+
+    /// Waits until every queued entry is written, spilled or dropped.
+    ///
+    /// If `timeout` passes first, retries stop and the remaining entries are
+    /// spilled to the journal (or dropped without one).
+    ///
+    /// # Returns
+    /// `true` if the queue drained within `timeout`.
+    pub async fn flush(&self, timeout: Duration) -> bool {
+        if self.wait_until_empty(Instant::now() + timeout).await {
+            return true;
+        }
+
+        warn!(
+            "⚠️ Persistence queue still has {} entries after {:?}; spilling them",
+            self.depth(),
+            timeout
+        );
+        self.shared.abandon.send_replace(true);
+        if !self.wait_until_empty(Instant::now() + SPILL_GRACE).await {
+            error!("❌ {} queued entries were lost on shutdown", self.depth());
+        }
+        false
+    }
+
+    async fn wait_until_empty(&self, deadline: Instant) -> bool {
+        while self.depth() > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        true
+    }
+}
+
+impl Shared {
+    /// Counts a job taken by the queue, after appending it to the write-ahead
+    /// log so a crash cannot lose it.
+    async fn accept(&self, job: &PersistJob) {
+        let Some(wal) = &self.wal else {
+            return self.job_accepted();
+        };
+        // Counted under the log's lock, so the log is never cleared between
+        // the append and the count
+        let record = encode_record(&job.key, &job.entry);
+        let _guard = wal.lock.lock().await;
+        if let Err(e) = async { wal.write(&record?).await }.await {
+            error!("❌ Failed to write key '{}' ahead to {:?}: {}", job.key, wal.path, e);
+        }
+        self.job_accepted();
+    }
+
+    /// Clears the write-ahead log once every accepted job is written,
+    /// spilled or dropped.
+    async fn clear_wal_if_idle(&self) {
+        let Some(wal) = &self.wal else {
+            return;
+        };
+        if self.pending.load(Ordering::SeqCst) > 0 {
+            return;
+        }
+        let _guard = wal.lock.lock().await;
+        if self.pending.load(Ordering::SeqCst) > 0 {
+            return;
+        }
+        match tokio::fs::remove_file(&wal.path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("⚠️ Failed to clear write-ahead log {:?}: {}", wal.path, e),
+        }
+    }
+
+    fn job_accepted(&self) {
+        let depth = self.pending.fetch_add(1, Ordering::SeqCst) + 1;
+        gauge!("cachebolt_persist_queue_depth").set(depth as f64);
+    }
+
+    fn job_done(&self) {
+        let depth = self.pending.fetch_sub(1, Ordering::SeqCst) - 1;
+        gauge!("cachebolt_persist_queue_depth").set(depth as f64);
+    }
+
+    fn abandoned(&self) -> bool {
+        *self.abandon.borrow()
+    }
+
+    /// Writes an entry, retrying failures with exponential backoff. Gives up
+    /// early if shutdown abandons the queue.
+    async fn persist(&self, job: PersistJob) {
+        let PersistJob { key, entry, target } = job;
+        let backend = target.as_ref().unwrap_or(&self.backend);
+        let mut abandon = self.abandon.subscribe();
+        let mut backoff = Duration::from_millis(self.settings.initial_backoff_ms);
+        let max_backoff = Duration::from_millis(self.settings.max_backoff_ms);
+
+        for attempt in 0..=self.settings.max_retries {
+            if attempt > 0 {
+                counter!("cachebolt_persist_retries_total").increment(1);
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = abandon.wait_for(|abandoned| *abandoned) => break,
+                }
+                backoff = (backoff * 2).min(max_backoff);
+            }
+
+            // Per-tier metrics and circuit breakers are handled by the storage tiers
+            let result = tokio::select! {
+                result = backend.put(&key, entry.clone()) => result,
+                _ = abandon.wait_for(|abandoned| *abandoned) => break,
+            };
+            match result {
+                Ok(()) => return,
+                Err(e) => warn!(
+                    "⚠️ Persisting key '{}' in {} failed (attempt {}/{}): {}",
+                    key,
+                    backend.name(),
+                    attempt + 1,
+                    self.settings.max_retries + 1,
+                    e
+                ),
+            }
+        }
+
+        let reason = if self.abandoned() { "shutdown" } else { "retries_exhausted" };
+        self.spill_or_drop(&key, &entry, reason).await;
+    }
+
+    /// Appends an entry to the journal, or drops it if there is none.
+    async fn spill_or_drop(&self, key: &str, entry: &CachedResponse, reason: &'static str) {
+        if let Some(journal) = &self.journal {
+            match journal.append(key, entry).await {
+                Ok(()) => {
+                    counter!("cachebolt_persist_spilled_total", "reason" => reason).increment(1);
+                    return;
+                }
+                Err(e) => error!("❌ Failed to spill key '{}' to the journal: {}", key, e),
+            }
+        }
+        warn!("🗑️ Dropping persistence of key '{}' ({})", key, reason);
+        counter!("cachebolt_persist_dropped_total", "reason" => reason).increment(1);
+    }
+}
+
+/// Writes queued entries one at a time until every sender is gone.
+async fn run_worker(shared: Arc<Shared>, mut rx: mpsc::Receiver<PersistJob>) {
+    while let Some(job) = rx.recv().await {
+        if shared.abandoned() {
+            shared.spill_or_drop(&job.key, &job.entry, "shutdown").await;
+        } else {
+            shared.persist(job).await;
+        }
+        shared.job_done();
+        shared.clear_wal_if_idle().await;
+    }
+}
+
+/// Append-only file of entries: the journal of entries that could not be
+/// persisted, or the write-ahead log of the queued ones.
+///
+/// Each record is the key and an encoded entry (see `blob::encode`), both
+/// prefixed with their length as a little-endian `u32`.
+struct Journal {
+    path: PathBuf,
+    /// Keeps concurrent appends from interleaving.
+    lock: Mutex<()>,
+}
+
+impl Journal {
+    fn new(path: PathBuf) -> Self {
+        Journal {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Path of this file with `.<extension>` appended.
+    fn sibling(&self, extension: &str) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(".");
+        name.push(extension);
+        PathBuf::from(name)
+    }
+
+    fn replay_path(&self) -> PathBuf {
+        self.sibling("replay")
+    }
+
+    async fn append(&self, key: &str, entry: &CachedResponse) -> StorageResult<()> {
+        let record = encode_record(key, entry)?;
+        let _guard = self.lock.lock().await;
+        self.write(&record).await
+    }
+
+    /// Appends an encoded record. Callers hold `lock`.
+    async fn write(&self, record: &[u8]) -> StorageResult<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(record).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Encodes a journal record: the key and the encoded entry, each prefixed
+/// with its length.
+fn encode_record(key: &str, entry: &CachedResponse) -> StorageResult<Vec<u8>> {
+    let blob = blob::encode(entry)?;
+    let mut record = Vec::with_capacity(8 + key.len() + blob.len());
+    record.extend_from_slice(&u32::try_from(key.len())?.to_le_bytes());
+    record.extend_from_slice(key.as_bytes());
+    record.extend_from_slice(&u32::try_from(blob.len())?.to_le_bytes());
+    record.extend_from_slice(&blob);
+    Ok(record)
+}
+
+/// Moves the complete records of a write-ahead log left behind by a crash
+/// into the journal, so they are replayed with it. Runs once at startup,
+/// before anything is queued.
+fn recover_wal(wal: &Journal, journal: &Journal) {
+    let raw = match std::fs::read(&wal.path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => {
+            error!("❌ Failed to read write-ahead log {:?}: {}", wal.path, e);
+            return;
+        }
+    };
+
+    // A truncated last record would corrupt the records appended after it
+    let mut complete = 0;
+    let mut records = 0;
+    while let Some((_, rest)) = take_field(&raw[complete..]).and_then(|(_, rest)| take_field(rest)) {
+        complete = raw.len() - rest.len();
+        records += 1;
+    }
+
+    let moved = (|| -> std::io::Result<()> {
+        if let Some(parent) = journal.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal.path)?
+            .write_all(&raw[..complete])?;
+        std::fs::remove_file(&wal.path)
+    })();
+    match moved {
+        Ok(()) if records > 0 => {
+            warn!("⚠️ Recovered {} entries queued before a crash into the journal", records)
+        }
+        Ok(()) => {}
+        Err(e) => error!("❌ Failed to move write-ahead log {:?} into the journal: {}", wal.path, e),
+    }
+}
+
+/// Reads every entry of a journal file, oldest first.
+///
+/// Records that fail to decode are skipped; a truncated last record (e.g.
+/// after a crash mid-write) ends the journal.
+pub async fn read_journal(path: &Path) -> StorageResult<Vec<(String, CachedResponse)>> {
+    let raw = tokio::fs::read(path).await?;
+    let mut jobs = Vec::new();
+    let mut rest = raw.as_slice();
+
+    while !rest.is_empty() {
+        let Some((key, after_key)) = take_field(rest) else {
+            warn!("⚠️ Persistence journal {:?} ends with a truncated record", path);
+            break;
+        };
+        let Some((blob, after_blob)) = take_field(after_key) else {
+            warn!("⚠️ Persistence journal {:?} ends with a truncated record", path);
+            break;
+        };
+        rest = after_blob;
+
+        match (std::str::from_utf8(key), blob::decode(blob)) {
+            (Ok(key), Ok(entry)) => jobs.push((key.to_string(), entry)),
+            (_, Err(e)) => warn!("⚠️ Skipping unreadable journal record: {}", e),
+            (Err(e), _) => warn!("⚠️ Skipping journal record with invalid key: {}", e),
+        }
+    }
+    Ok(jobs)
+}
+
+/// Splits a `u32` length-prefixed field off the front of `raw`.
+fn take_field(raw: &[u8]) -> Option<(&[u8], &[u8])> {
+    let len = u32::from_le_bytes(raw.get(..4)?.try_into().ok()?) as usize;
+    let field = raw.get(4..4 + len)?;
+    Some((field, &raw[4 + len..]))
+}
+
+/// Returns the queue feeding the configured backend, creating it on first
+/// use. `None` until `CONFIG` is initialized.
+pub fn queue() -> Option<&'static PersistQueue> {
+    if let Some(queue) = QUEUE.get() {
+        return Some(queue);
+    }
+    let config = CONFIG.get()?;
+    let backend = storage::backend()?;
+    Some(QUEUE.get_or_init(|| PersistQueue::start(backend, config.persistence.clone())))
+}
+
+/// Replays the persistence journal in the background, if one is configured.
+pub fn start_journal_replay() {
+    let Some(queue) = queue() else {
+        return;
+    };
+    tokio::spawn(async move {
+        queue.replay_journal().await;
+    });
+}
+
+/// Flushes the queue before exit, waiting up to `persistence.shutdown_timeout_secs`.
+pub async fn shutdown() {
+    let Some(queue) = QUEUE.get() else {
+        return;
+    };
+    let timeout = CONFIG
+        .get()
+        .map(|c| c.persistence.shutdown_timeout_secs)
+        .unwrap_or_default();
+
+    info!("💾 Flushing {} queued cache entries before exit", queue.depth());
+    if queue.flush(Duration::from_secs(timeout)).await {
+        info!("✅ Persistence queue flushed");
+    }
+}
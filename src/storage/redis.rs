@@ -0,0 +1,299 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Redis (or any Redis-compatible server) as a cache tier shared by replicas.
+///
+/// Each entry is a hash under `cachebolt:{app_id}:{key}` holding the blob
+/// envelope and the time it was stored. The key expires natively once the
+/// entry can no longer be served (see `retention_secs`).
+/// Metadata (see `purge`) is kept under `cachebolt-meta:{app_id}:{name}`,
+/// outside the namespace that is listed and cleared.
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use redis::AsyncCommands;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::memory::memory::CachedResponse;
+use crate::storage::{
+    StorageBackend, StorageResult, StoredObject, blob, retention_secs, timestamp_to_datetime,
+};
+
+/// Global connection to the configured Redis server. Reconnects on its own
+/// after the server goes away.
+static REDIS_CONN: OnceCell<ConnectionManager> = OnceCell::new();
+
+/// Hash field holding the blob envelope.
+const BLOB_FIELD: &str = "blob";
+/// Hash field holding the Unix timestamp the entry was stored at.
+const STORED_AT_FIELD: &str = "stored_at";
+/// Keys fetched per `SCAN` call when listing or clearing the namespace.
+const SCAN_BATCH: usize = 500;
+
+/// Connects to `redis_url`. Fails if the server cannot be reached.
+pub async fn init_redis_client() -> StorageResult<()> {
+    if REDIS_CONN.get().is_some() {
+        return Ok(());
+    }
+    let url = &CONFIG.get().ok_or("CONFIG not initialized")?.redis_url;
+
+    let client = redis::Client::open(url.as_str())?;
+    let settings = ConnectionManagerConfig::new()
+        .set_connection_timeout(Duration::from_secs(5))
+        .set_response_timeout(Duration::from_secs(5))
+        .set_number_of_retries(2);
+    let conn = ConnectionManager::new_with_config(client, settings).await?;
+
+    let _ = REDIS_CONN.set(conn);
+    Ok(())
+}
+
+fn connection() -> StorageResult<ConnectionManager> {
+    // Clones share the underlying multiplexed connection
+    Ok(REDIS_CONN.get().ok_or("Redis client not initialized")?.clone())
+}
+
+/// Namespace of the configured app: `cachebolt:{app_id}:`.
+fn key_prefix() -> StorageResult<String> {
+    let app_id = &CONFIG.get().ok_or("CONFIG not initialized")?.app_id;
+    Ok(format!("cachebolt:{app_id}:"))
+}
+
+/// `SCAN` pattern matching every key of the namespace, with glob
+/// metacharacters in the prefix escaped.
+fn scan_pattern(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('*');
+    pattern
+}
+
+/// Stores an entry under `cachebolt:{app_id}:{key}` with its native TTL.
+pub async fn store_in_cache(key: &str, entry: CachedResponse) -> StorageResult<()> {
+    let redis_key = format!("{}{key}", key_prefix()?);
+    let now = Utc::now();
+    let ttl = retention_secs(&entry, now);
+    let encoded = blob::encode(&entry).map_err(|e| {
+        error!("❌ Failed to encode cache for key '{}': {}", key, e);
+        e
+    })?;
+
+    let mut pipe = redis::pipe();
+    pipe.atomic()
+        .del(&redis_key)
+        .ignore()
+        .cmd("HSET")
+        .arg(&redis_key)
+        .arg(BLOB_FIELD)
+        .arg(encoded)
+        .arg(STORED_AT_FIELD)
+        .arg(now.timestamp())
+        .ignore();
+    if let Some(ttl) = ttl {
+        pipe.expire(&redis_key, ttl as i64).ignore();
+    }
+    pipe.query_async::<()>(&mut connection()?).await?;
+
+    info!("✅ Key '{}' stored in Redis (ttl={:?}s)", key, ttl);
+    Ok(())
+}
+
+/// Loads an entry. `Ok(None)` if the key is missing or has expired.
+pub async fn load_from_cache(key: &str) -> StorageResult<Option<CachedResponse>> {
+    let redis_key = format!("{}{key}", key_prefix()?);
+    let raw: Option<Vec<u8>> = connection()?.hget(&redis_key, BLOB_FIELD).await?;
+    let Some(raw) = raw else {
+        return Ok(None);
+    };
+
+    match blob::decode(&raw) {
+        Ok(entry) => {
+            info!("📦 Key '{}' loaded from Redis", key);
+            Ok(Some(entry))
+        }
+        Err(e) => {
+            error!("❌ Failed to decode cache for key '{}': {}", key, e);
+            Err(e)
+        }
+    }
+}
+
+/// Deletes one entry. `Ok(false)` if it did not exist.
+pub async fn delete_from_cache(key: &str) -> StorageResult<bool> {
+    let redis_key = format!("{}{key}", key_prefix()?);
+    let removed: usize = connection()?.del(&redis_key).await?;
+    Ok(removed > 0)
+}
+
+/// Redis key of the metadata value `name`.
+fn meta_key(name: &str) -> StorageResult<String> {
+    let app_id = &CONFIG.get().ok_or("CONFIG not initialized")?.app_id;
+    Ok(format!("cachebolt-meta:{app_id}:{name}"))
+}
+
+/// Loads the metadata value `name`. `Ok(None)` if it was never stored.
+pub async fn load_meta(name: &str) -> StorageResult<Option<Vec<u8>>> {
+    Ok(connection()?.get(meta_key(name)?).await?)
+}
+
+/// Stores the metadata value `name`, without expiry.
+pub async fn store_meta(name: &str, data: &[u8]) -> StorageResult<()> {
+    connection()?.set::<_, _, ()>(meta_key(name)?, data).await?;
+    Ok(())
+}
+
+/// Collects every key of the namespace with `SCAN`, which unlike `KEYS` does
+/// not block the server.
+async fn scan_keys(conn: &mut ConnectionManager, prefix: &str) -> StorageResult<Vec<String>> {
+    let pattern = scan_pattern(prefix);
+    let mut cursor: u64 = 0;
+    let mut keys = Vec::new();
+    loop {
+        let (next, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(&pattern)
+            .arg("COUNT")
+            .arg(SCAN_BATCH)
+            .query_async(conn)
+            .await?;
+        keys.extend(batch);
+        if next == 0 {
+            return Ok(keys);
+        }
+        cursor = next;
+    }
+}
+
+/// Lists the entries of the namespace with their stored time and size.
+pub async fn list_cache_entries() -> StorageResult<Vec<StoredObject>> {
+    let prefix = key_prefix()?;
+    let mut conn = connection()?;
+    let keys = scan_keys(&mut conn, &prefix).await?;
+
+    let mut listed = Vec::with_capacity(keys.len());
+    for chunk in keys.chunks(SCAN_BATCH) {
+        let mut pipe = redis::pipe();
+        for key in chunk {
+            pipe.hget(key, STORED_AT_FIELD).cmd("HSTRLEN").arg(key).arg(BLOB_FIELD);
+        }
+        let replies: Vec<(Option<i64>, u64)> = pipe.query_async(&mut conn).await?;
+
+        for (key, (stored_at, size)) in chunk.iter().zip(replies) {
+            // Expired between SCAN and HGET
+            if stored_at.is_none() {
+                continue;
+            }
+            listed.push(StoredObject {
+                key: key[prefix.len()..].to_string(),
+                last_modified: timestamp_to_datetime(stored_at),
+                size,
+            });
+        }
+    }
+    Ok(listed)
+}
+
+/// Deletes every entry of the namespace (`cachebolt:{app_id}:*`).
+///
+/// # Returns
+/// The cache keys removed (without the namespace).
+pub async fn delete_all_from_cache() -> StorageResult<Vec<String>> {
+    let prefix = key_prefix()?;
+    let mut conn = connection()?;
+    let keys = scan_keys(&mut conn, &prefix).await?;
+
+    let mut deleted = Vec::new();
+    for chunk in keys.chunks(SCAN_BATCH) {
+        // UNLINK frees the memory in the background on the server; one per
+        // key, so keys that expired since the SCAN are not reported
+        let mut pipe = redis::pipe();
+        for key in chunk {
+            pipe.cmd("UNLINK").arg(key);
+        }
+        let removed: Vec<usize> = pipe.query_async(&mut conn).await?;
+        for (key, removed) in chunk.iter().zip(removed) {
+            if removed > 0 {
+                deleted.push(key[prefix.len()..].to_string());
+            }
+        }
+    }
+
+    info!("✅ Redis: Deleted {} keys under '{prefix}'", deleted.len());
+    Ok(deleted)
+}
+
+/// Single round trip to the server.
+pub async fn check_redis_connection() -> StorageResult<()> {
+    let reply: String = redis::cmd("PING").query_async(&mut connection()?).await?;
+    if reply != "PONG" {
+        warn!("⚠️ Unexpected PING reply from Redis: {}", reply);
+    }
+    Ok(())
+}
+
+/// Redis backend: one hash per key under `cachebolt:{app_id}:`, expiring natively.
+pub struct RedisBackend;
+
+#[async_trait]
+impl StorageBackend for RedisBackend {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn init(&self) -> StorageResult<()> {
+        init_redis_client().await
+    }
+
+    async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+        load_from_cache(key).await
+    }
+
+    async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+        store_in_cache(key, entry).await
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        delete_from_cache(key).await
+    }
+
+    async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+        list_cache_entries().await
+    }
+
+    async fn delete_all(&self) -> StorageResult<Vec<String>> {
+        delete_all_from_cache().await
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        check_redis_connection().await
+    }
+
+    async fn get_meta(&self, name: &str) -> StorageResult<Option<Bytes>> {
+        Ok(load_meta(name).await?.map(Bytes::from))
+    }
+
+    async fn put_meta(&self, name: &str, data: Bytes) -> StorageResult<()> {
+        store_meta(name, &data).await
+    }
+}
@@ -13,28 +13,57 @@
 // limitations under the License.
 
 use crate::config::CONFIG;
+use crate::memory::memory::CachedResponse;
+use crate::storage::breaker::{BackendError, ErrorClass};
+use crate::storage::{
+    EntryMeta, StorageBackend, StorageResult, StoredObject, blob, entry_from_parts,
+    timestamp_to_datetime,
+};
 use aws_config::meta::region::RegionProviderChain;
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::SdkError;
 use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::{Client, config::Builder};
 use bytes::Bytes;
-use flate2::Compression;
 use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
 use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
 use serde_json;
-use std::{error::Error, io::{Read, Write}};
+use std::{error::Error, io::Read};
 use tracing::{error, info, warn};
 use std::env; //MIA
-use tokio::time::{sleep, Duration}; //MIA
-use crate::proxy::CIRCUIT_BREAKER; // importar el breaker (pub(crate) en proxy.rs) MIA
-use std::sync::atomic::Ordering; //MIA
+use async_trait::async_trait;
+
+/// Metadata stored in `cache/{app_id}/{key}.meta.gz` next to legacy body objects
+/// (gzipped body only). Older meta objects contain only the JSON array of headers.
+#[derive(Serialize, Deserialize)]
+struct S3Meta {
+    headers: Vec<(String, String)>,
+    #[serde(flatten)]
+    meta: EntryMeta,
+}
+
+/// Tags an S3 SDK error with its class for the circuit breaker, using the
+/// SDK error variant and the HTTP status of service errors.
+fn classified<E>(e: SdkError<E, HttpResponse>) -> BackendError
+where
+    E: Error + Send + Sync + 'static,
+{
+    let class = match &e {
+        SdkError::TimeoutError(_) => ErrorClass::Timeout,
+        SdkError::DispatchFailure(f) if f.is_timeout() => ErrorClass::Timeout,
+        SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => ErrorClass::Unavailable,
+        SdkError::ServiceError(se) => ErrorClass::from_status(se.raw().status().as_u16()),
+        _ => ErrorClass::Other,
+    };
+    BackendError::new(class, e)
+}
 
 /// Global instance of the AWS S3 client, initialized once and reused.
 static S3_CLIENT: OnceCell<Client> = OnceCell::new();
 
 /// Initializes the AWS S3 client from environment variables or default provider chain.
 /// Region fallback is `us-east-1` if no environment setting is present.
-
 pub async fn init_s3_client() {
     if S3_CLIENT.get().is_none() {
         let region_provider = RegionProviderChain::default_provider().or_else("us-east-1");
@@ -86,15 +115,11 @@ pub async fn init_s3_client() {
 
 
 
-/// Stores both response body and headers in AWS S3 using gzip compression.
-///
-/// - Body is stored under: `cache/{app_id}/{key}.gz`
-/// - Headers are stored separately under: `cache/{app_id}/{key}.meta.gz`
-
+/// Stores an entry in AWS S3 as a single blob envelope (see `storage::blob`)
+/// under `cache/{app_id}/{key}.gz`.
 pub async fn store_in_cache(
     key: String,
-    data: Bytes,
-    headers: Vec<(String, String)>
+    entry: CachedResponse,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     let client = S3_CLIENT.get().ok_or("S3 client not initialized")?;
     let cfg = CONFIG.get().ok_or("CONFIG not initialized")?;
@@ -102,75 +127,23 @@ pub async fn store_in_cache(
     let app_id = &cfg.app_id;
 
     let data_path = format!("cache/{}/{}.gz", app_id, key);
-    let meta_path = format!("cache/{}/{}.meta.gz", app_id, key);
 
-    // Check if bucket is available
-    client
-        .head_bucket()
-        .bucket(bucket)
-        .send()
-        .await
-        .map_err(|e| {
-            error!("❌ Error accessing bucket '{}': {:?}", bucket, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(e)
-        })?;
-
-    // Compress response body
-    let compressed_data = {
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all(&data).map_err(|e| {
-            error!("⚠️ Failed to compress body for key '{}': {}", key, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(e)
-        })?;
-        encoder.finish().map_err(|e| {
-            error!("⚠️ Failed to finish compression for key '{}': {}", key, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(e)
-        })?
-    };
-
-    // Serialize and compress headers
-    let compressed_meta = {
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-        let headers_json = serde_json::to_vec(&headers).map_err(|e| {
-            error!("⚠️ Failed to serialize headers for key '{}': {}", key, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(e)
-        })?;
-        encoder.write_all(&headers_json).map_err(|e| {
-            error!("⚠️ Failed to compress headers for key '{}': {}", key, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(e)
-        })?;
-        encoder.finish().map_err(|e| {
-            error!("⚠️ Failed to finish compression for headers key '{}': {}", key, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(e)
-        })?
-    };
+    let encoded = blob::encode(&entry).map_err(|e| {
+        error!("⚠️ Failed to encode blob for key '{}': {}", key, e);
+        e
+    })?;
 
-    // Upload compressed body to S3
     client
         .put_object()
         .bucket(bucket)
         .key(&data_path)
-        .body(ByteStream::from(compressed_data))
-        .content_type("application/gzip")
-        .send()
-        .await
-        .map_err(|e| {
-            error!("❌ Error uploading body for key '{}': {}", key, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(e)
-        })?;
-
-    // Upload compressed headers to S3
-    client
-        .put_object()
-        .bucket(bucket)
-        .key(&meta_path)
-        .body(ByteStream::from(compressed_meta))
-        .content_type("application/gzip")
+        .body(ByteStream::from(encoded))
+        .content_type("application/octet-stream")
         .send()
         .await
         .map_err(|e| {
-            error!("❌ Error uploading headers for key '{}': {}", key, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(e)
+            error!("❌ Error uploading blob for key '{}': {}", key, e);
+            Box::<dyn std::error::Error + Send + Sync>::from(classified(e))
         })?;
 
     info!("✅ Key '{}' stored in S3 bucket '{}'", key, bucket);
@@ -178,44 +151,40 @@ pub async fn store_in_cache(
 }
 
 
-/// Loads both body and headers from S3 and decompresses them.
-/// If headers are missing or invalid, defaults to empty header list.
-
+/// Loads an entry from S3.
+///
+/// Blob envelopes are decoded directly. Legacy objects hold only the gzipped
+/// body; their headers and expiry come from the `.meta.gz` object. If that is
+/// missing or invalid, headers default to an empty list and the entry is
+/// treated as expired.
+///
+/// # Returns
+/// - `Ok(Some(CachedResponse))` on success (expired entries included).
+/// - `Ok(None)` if the body object does not exist.
+/// - `Err(_)` if the bucket is unreachable or the object cannot be read.
 pub async fn load_from_cache(
     key: &str,
-) -> Result<(Bytes, Vec<(String, String)>), Box<dyn Error + Send + Sync>> {
+) -> Result<Option<CachedResponse>, Box<dyn Error + Send + Sync>> {
     let client = S3_CLIENT.get().ok_or("S3 client not initialized")?;
     let cfg = CONFIG.get().ok_or("CONFIG not initialized")?;
     let app_id = &cfg.app_id;
     let bucket = &cfg.s3_bucket;
 
-    // Check if bucket is available
-    client
-        .head_bucket()
-        .bucket(bucket)
-        .send()
-        .await
-        .map_err(|e| {
-            error!("❌ Error accessing bucket '{}': {:?}", bucket, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(e)
-        })?;
-
     let data_path = format!("cache/{}/{}.gz", app_id, key);
     let meta_path = format!("cache/{}/{}.meta.gz", app_id, key);
 
     // Fetch and decompress body
-    let resp = client
-        .get_object()
-        .bucket(bucket)
-        .key(&data_path)
-        .send()
-        .await
-        .map_err(|e| {
-            warn!("❌ Object '{}' is not in the S3 cache: {}", key, e);
-            Box::<dyn std::error::Error + Send + Sync>::from(format!(
-                "Object '{}' is not in the S3 cache: {}", key, e
-            ))
-        })?;
+    let resp = match client.get_object().bucket(bucket).key(&data_path).send().await {
+        Ok(resp) => resp,
+        Err(e) if e.as_service_error().is_some_and(|se| se.is_no_such_key()) => {
+            info!("Object '{}' is not in the S3 cache", key);
+            return Ok(None);
+        }
+        Err(e) => {
+            warn!("❌ Failed to get object '{}' from the S3 cache: {}", key, e);
+            return Err(classified(e).into());
+        }
+    };
 
     let collected = resp.body.collect().await.map_err(|e| {
         error!("⚠️ Failed to read body for key '{}': {}", key, e);
@@ -223,6 +192,13 @@ pub async fn load_from_cache(
     })?;
 
     let compressed = collected.into_bytes();
+    if blob::is_envelope(&compressed) {
+        return blob::decode(&compressed).map(Some).map_err(|e| {
+            error!("⚠️ Failed to decode blob for key '{}': {}", key, e);
+            e
+        });
+    }
+
     let mut decoder = GzDecoder::new(&compressed[..]);
     let mut decompressed = Vec::new();
 
@@ -234,7 +210,7 @@ pub async fn load_from_cache(
     let data = Bytes::from(decompressed);
 
     // Fetch and decompress headers (optional fallback to empty)
-    let headers = match client
+    let meta = match client
         .get_object()
         .bucket(bucket)
         .key(&meta_path)
@@ -249,37 +225,54 @@ pub async fn load_from_cache(
 
                 if decoder.read_to_end(&mut decompressed).is_err() {
                     error!("⚠️ Failed to decompress headers for key '{}'", key);
-                    vec![]
+                    None
                 } else {
-                    match serde_json::from_slice::<Vec<(String, String)>>(&decompressed) {
-                        Ok(h) => h,
-                        Err(e) => {
-                            error!("⚠️ Failed to parse headers JSON for key '{}': {}", key, e);
-                            vec![]
-                        }
-                    }
+                    parse_meta(key, &decompressed)
                 }
             }
             Err(e) => {
                 warn!("⚠️ Failed to read headers for key '{}': {}", key, e);
-                vec![]
+                None
             }
         },
         Err(e) => {
             warn!("⚠️ Failed to get headers object '{}' from S3: {}", key, e);
-            vec![]
+            None
         }
     };
 
-    Ok((data, headers))
+    Ok(Some(match meta {
+        Some(m) => entry_from_parts(data, m.headers, m.meta),
+        None => entry_from_parts(data, vec![], EntryMeta::default()),
+    }))
+}
+
+/// Parses a meta object, accepting both the current `S3Meta` layout and the
+/// legacy layout (a bare JSON array of headers without expiry metadata).
+fn parse_meta(key: &str, raw: &[u8]) -> Option<S3Meta> {
+    if let Ok(meta) = serde_json::from_slice::<S3Meta>(raw) {
+        return Some(meta);
+    }
+    match serde_json::from_slice::<Vec<(String, String)>>(raw) {
+        Ok(headers) => Some(S3Meta {
+            headers,
+            meta: EntryMeta::default(),
+        }),
+        Err(e) => {
+            error!("⚠️ Failed to parse headers JSON for key '{}': {}", key, e);
+            None
+        }
+    }
 }
 
 /// Deletes all cached objects (both `.gz` and `.meta.gz`) under `cache/{app_id}/` in the S3 bucket.
 ///
 /// # Returns
-/// - `Ok(count)` if all deletions succeeded or no files were found.
-/// - `Err(_)` if any error occurred during listing or deletion.
-pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Sync>> {
+/// - `Ok(keys)` with the keys of the deleted bodies, if all deletions
+///   succeeded or no files were found.
+/// - `Err(_)` if listing fails, or if any deletion failed. The remaining
+///   objects are still deleted before the error is returned.
+pub async fn delete_all_from_cache() -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
     let client = S3_CLIENT
         .get()
         .ok_or_else(|| "S3 client not initialized".to_string())?;
@@ -292,6 +285,9 @@ pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Syn
     let bucket = &config.s3_bucket;
     let mut continuation_token = None;
     let mut deleted_count = 0;
+    let mut deleted_keys = Vec::new();
+    let mut failed = 0;
+    let mut first_error = None;
 
     loop {
         let resp = client
@@ -300,7 +296,8 @@ pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Syn
             .prefix(&prefix)
             .set_continuation_token(continuation_token.clone())
             .send()
-            .await?;
+            .await
+            .map_err(classified)?;
 
         for obj in resp.contents() {
             if let Some(key) = obj.key() {
@@ -308,9 +305,17 @@ pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Syn
                     Ok(_) => {
                         info!("🗑️ Deleted S3 object '{}'", key);
                         deleted_count += 1;
+                        let name = &key[prefix.len()..];
+                        if !name.ends_with(".meta.gz")
+                            && let Some(cache_key) = name.strip_suffix(".gz")
+                        {
+                            deleted_keys.push(cache_key.to_string());
+                        }
                     }
                     Err(e) => {
                         warn!("⚠️ Failed to delete S3 object '{}': {}", key, e);
+                        failed += 1;
+                        first_error.get_or_insert(classified(e));
                     }
                 }
             }
@@ -323,7 +328,66 @@ pub async fn delete_all_from_cache() -> Result<usize, Box<dyn Error + Send + Syn
         }
     }
 
-    Ok(deleted_count)
+    if let Some(e) = first_error {
+        return Err(BackendError::new(
+            e.class,
+            format!("{failed} S3 objects could not be deleted ({deleted_count} were): {e}"),
+        )
+        .into());
+    }
+    Ok(deleted_keys)
+}
+
+/// Lists the cached bodies (`.gz`, not `.meta.gz`) under `cache/{app_id}/` in the S3 bucket.
+///
+/// # Returns
+/// - `Ok(objects)` with one `StoredObject` per cached entry.
+/// - `Err(_)` if listing fails.
+pub async fn list_cache_entries() -> Result<Vec<StoredObject>, Box<dyn Error + Send + Sync>> {
+    let client = S3_CLIENT.get().ok_or("S3 client not initialized")?;
+    let config = CONFIG.get().ok_or("CONFIG not initialized")?;
+
+    let prefix = format!("cache/{}/", config.app_id);
+    let bucket = &config.s3_bucket;
+    let mut continuation_token = None;
+    let mut objects = Vec::new();
+
+    loop {
+        let resp = client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(&prefix)
+            .set_continuation_token(continuation_token.clone())
+            .send()
+            .await
+            .map_err(classified)?;
+
+        for obj in resp.contents() {
+            let Some(name) = obj.key() else { continue };
+            if name.ends_with(".meta.gz") {
+                continue;
+            }
+            let Some(key) = name
+                .strip_prefix(&prefix)
+                .and_then(|k| k.strip_suffix(".gz"))
+            else {
+                continue;
+            };
+            objects.push(StoredObject {
+                key: key.to_string(),
+                last_modified: timestamp_to_datetime(obj.last_modified().map(|t| t.secs())),
+                size: obj.size().unwrap_or_default().max(0) as u64,
+            });
+        }
+
+        if resp.is_truncated() == Some(true) {
+            continuation_token = resp.next_continuation_token().map(|s| s.to_string());
+        } else {
+            break;
+        }
+    }
+
+    Ok(objects)
 }
 
 /// Single connectivity check to the configured S3 bucket.
@@ -340,51 +404,123 @@ pub async fn check_bucket_connection() -> Result<(), Box<dyn std::error::Error +
         }
         Err(e) => {
             tracing::warn!("⚠️ S3 health check FAILED (bucket='{}'): {:?}", bucket, e);
-            Err(Box::<dyn std::error::Error + Send + Sync>::from(e))
+            Err(Box::<dyn std::error::Error + Send + Sync>::from(classified(e)))
         }
     }
 }
 
-/// Inicia un task que hace head_bucket() cada `interval_secs`.
-/// Flujo esperado:
-/// - Se llama cuando el circuito ya está en true (abierto).
-/// - En cada fallo: mantiene CIRCUIT_BREAKER en true y reintenta.
-/// - En el primer éxito: pone CIRCUIT_BREAKER = false y termina el task.
-/// - interval_secs == 0 -> no arranca.
-pub fn start_s3_health_checker(interval_secs: u64) {
-    if interval_secs == 0 {
-        tracing::info!("S3 health checker deshabilitado (intervalo = 0s)");
-        return;
+/// Deletes the objects of a single key (including a legacy meta object) from the S3 bucket.
+///
+/// # Returns
+/// - `Ok(true)` once both objects are deleted.
+/// - `Ok(false)` if there was no object for the key. S3 does not report
+///   missing keys on delete, so the object is looked up first.
+/// - `Err(_)` if the lookup or a deletion fails.
+pub async fn delete_from_cache(key: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let client = S3_CLIENT.get().ok_or("S3 client not initialized")?;
+    let cfg = CONFIG.get().ok_or("CONFIG not initialized")?;
+    let bucket = &cfg.s3_bucket;
+
+    let data_path = format!("cache/{}/{}.gz", cfg.app_id, key);
+    match client.head_object().bucket(bucket).key(&data_path).send().await {
+        Ok(_) => {}
+        Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => return Ok(false),
+        Err(e) => return Err(classified(e).into()),
     }
 
-    let dur = Duration::from_secs(interval_secs);
-    tracing::info!(
-        "🩺 Iniciando S3 health checker (intervalo {}s, breaker actual = {})",
-        interval_secs,
-        CIRCUIT_BREAKER.load(Ordering::SeqCst)
-    );
-
-    tokio::spawn(async move {
-        loop {
-            match check_bucket_connection().await {
-                Ok(_) => {
-                    tracing::info!(
-                        "✅ S3 restaurado. Cerrando circuit breaker (true -> false) y deteniendo checker."
-                    );
-                    CIRCUIT_BREAKER.store(false, Ordering::SeqCst);
-                    break;
-                }
-                Err(e) => {
-                    // Mantener breaker abierto
-                    CIRCUIT_BREAKER.store(true, Ordering::SeqCst);
-                    tracing::warn!(
-                        "⚠️ S3 aún inaccesible (breaker=true). Reintento en {}s. Error: {}",
-                        interval_secs,
-                        e
-                    );
-                }
-            }
-            sleep(dur).await;
-        }
-    });
-}
\ No newline at end of file
+    for path in [
+        data_path,
+        format!("cache/{}/{}.meta.gz", cfg.app_id, key),
+    ] {
+        client
+            .delete_object()
+            .bucket(bucket)
+            .key(&path)
+            .send()
+            .await
+            .map_err(classified)?;
+        info!("🗑️ Deleted S3 object '{}'", path);
+    }
+
+    Ok(true)
+}
+
+/// Loads the metadata object `meta/{app_id}/{name}`, outside the listed
+/// `cache/{app_id}/` prefix. `Ok(None)` if it does not exist.
+pub async fn load_meta(name: &str) -> StorageResult<Option<Bytes>> {
+    let client = S3_CLIENT.get().ok_or("S3 client not initialized")?;
+    let cfg = CONFIG.get().ok_or("CONFIG not initialized")?;
+    let path = format!("meta/{}/{}", cfg.app_id, name);
+
+    let resp = match client.get_object().bucket(&cfg.s3_bucket).key(&path).send().await {
+        Ok(resp) => resp,
+        Err(e) if e.as_service_error().is_some_and(|se| se.is_no_such_key()) => return Ok(None),
+        Err(e) => return Err(classified(e).into()),
+    };
+    let collected = resp.body.collect().await.map_err(|e| format!("Failed to read {path}: {e}"))?;
+    Ok(Some(collected.into_bytes()))
+}
+
+/// Stores the metadata object `meta/{app_id}/{name}`.
+pub async fn store_meta(name: &str, data: Bytes) -> StorageResult<()> {
+    let client = S3_CLIENT.get().ok_or("S3 client not initialized")?;
+    let cfg = CONFIG.get().ok_or("CONFIG not initialized")?;
+
+    client
+        .put_object()
+        .bucket(&cfg.s3_bucket)
+        .key(format!("meta/{}/{}", cfg.app_id, name))
+        .body(ByteStream::from(data))
+        .content_type("application/json")
+        .send()
+        .await
+        .map_err(classified)?;
+    Ok(())
+}
+
+/// AWS S3 (or S3-compatible) backend: one blob envelope per key under `cache/{app_id}/`.
+pub struct S3Backend;
+
+#[async_trait]
+impl StorageBackend for S3Backend {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+
+    async fn init(&self) -> StorageResult<()> {
+        init_s3_client().await;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+        load_from_cache(key).await
+    }
+
+    async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+        store_in_cache(key.to_string(), entry).await
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        delete_from_cache(key).await
+    }
+
+    async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+        list_cache_entries().await
+    }
+
+    async fn delete_all(&self) -> StorageResult<Vec<String>> {
+        delete_all_from_cache().await
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        check_bucket_connection().await
+    }
+
+    async fn get_meta(&self, name: &str) -> StorageResult<Option<Bytes>> {
+        load_meta(name).await
+    }
+
+    async fn put_meta(&self, name: &str, data: Bytes) -> StorageResult<()> {
+        store_meta(name, data).await
+    }
+}
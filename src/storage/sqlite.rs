@@ -0,0 +1,440 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Embedded SQLite backend: every entry of every app in a single database file.
+///
+/// - One row per key holds the blob envelope, its size, key source, store time
+///   and the time it can be evicted (see `retention_secs`); the entry's cache
+///   tags go to a side table.
+/// - Listing, prefix and tag deletion and size queries use indexes.
+/// - Every statement runs on the blocking thread pool, never on a Tokio worker.
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::task;
+use tracing::{error, info, warn};
+
+use crate::config::CONFIG;
+use crate::memory::memory::CachedResponse;
+use crate::storage::{
+    StorageBackend, StorageResult, StorageUsage, StoredObject, blob, retention_secs,
+    timestamp_to_datetime,
+};
+
+/// Global connection to the database file.
+static SQLITE_CONN: OnceCell<Arc<Mutex<Connection>>> = OnceCell::new();
+
+/// How often rows past their eviction time are deleted.
+const PURGE_INTERVAL: Duration = Duration::from_secs(600);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        app_id     TEXT    NOT NULL,
+        key        TEXT    NOT NULL,
+        source     TEXT    NOT NULL,
+        blob       BLOB    NOT NULL,
+        size       INTEGER NOT NULL,
+        stored_at  INTEGER NOT NULL,
+        expires_at INTEGER NOT NULL,
+        evict_at   INTEGER,
+        PRIMARY KEY (app_id, key)
+    );
+    CREATE INDEX IF NOT EXISTS entries_by_stored_at ON entries (app_id, stored_at);
+    CREATE INDEX IF NOT EXISTS entries_by_source ON entries (app_id, source);
+    CREATE INDEX IF NOT EXISTS entries_by_evict_at ON entries (evict_at) WHERE evict_at IS NOT NULL;
+
+    CREATE TABLE IF NOT EXISTS entry_tags (
+        app_id TEXT NOT NULL,
+        tag    TEXT NOT NULL,
+        key    TEXT NOT NULL,
+        PRIMARY KEY (app_id, tag, key),
+        FOREIGN KEY (app_id, key) REFERENCES entries (app_id, key) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS entry_tags_by_key ON entry_tags (app_id, key);
+
+    CREATE TABLE IF NOT EXISTS meta (
+        app_id TEXT NOT NULL,
+        name   TEXT NOT NULL,
+        data   BLOB NOT NULL,
+        PRIMARY KEY (app_id, name)
+    );
+";
+
+/// Filters rows that can no longer be served; `?2` is the current Unix time.
+const LIVE: &str = "(evict_at IS NULL OR evict_at > ?2)";
+
+/// Opens (or creates) the database at `sqlite_path` and starts the periodic
+/// purge of rows past their eviction time.
+pub async fn init_sqlite() -> StorageResult<()> {
+    if SQLITE_CONN.get().is_some() {
+        return Ok(());
+    }
+    let path = CONFIG.get().ok_or("CONFIG not initialized")?.sqlite_path.clone();
+
+    let conn = task::spawn_blocking(move || open(Path::new(&path))).await??;
+    if SQLITE_CONN.set(Arc::new(Mutex::new(conn))).is_err() {
+        return Ok(());
+    }
+
+    let usage = storage_usage().await?;
+    info!(
+        "📦 SQLite cache opened: {} entries, {} bytes",
+        usage.entries, usage.bytes
+    );
+
+    tokio::spawn(async {
+        loop {
+            match purge_evicted().await {
+                Ok(0) => {}
+                Ok(purged) => info!("🧹 SQLite: purged {} entries past their lifetime", purged),
+                Err(e) => warn!("⚠️ SQLite purge failed: {}", e),
+            }
+            tokio::time::sleep(PURGE_INTERVAL).await;
+        }
+    });
+    Ok(())
+}
+
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        // Surfaced by `Connection::open` below if this fails
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    // WAL lets readers of other processes proceed while a write is in progress
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+/// Runs `f` with the connection and the current `app_id` on the blocking
+/// thread pool.
+async fn with_conn<T, F>(f: F) -> StorageResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Connection, &str) -> StorageResult<T> + Send + 'static,
+{
+    let conn = Arc::clone(SQLITE_CONN.get().ok_or("SQLite backend not initialized")?);
+    let app_id = CONFIG.get().ok_or("CONFIG not initialized")?.app_id.clone();
+
+    task::spawn_blocking(move || {
+        let mut conn = conn.lock().unwrap_or_else(PoisonError::into_inner);
+        f(&mut conn, &app_id)
+    })
+    .await?
+}
+
+/// `GLOB` pattern matching every string starting with `prefix`. Unlike
+/// `LIKE`, `GLOB` is case-sensitive and can use the `source` index.
+fn glob_prefix(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        match c {
+            '*' | '?' | '[' => {
+                pattern.push('[');
+                pattern.push(c);
+                pattern.push(']');
+            }
+            _ => pattern.push(c),
+        }
+    }
+    pattern.push('*');
+    pattern
+}
+
+/// Stores an entry and its tags in one transaction.
+pub async fn store_in_cache(key: &str, entry: CachedResponse) -> StorageResult<()> {
+    let key = key.to_string();
+    with_conn(move |conn, app_id| {
+        let now = Utc::now();
+        let evict_at = retention_secs(&entry, now).map(|secs| now.timestamp() + secs as i64);
+        let encoded = blob::encode(&entry)?;
+
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO entries (app_id, key, source, blob, size, stored_at, expires_at, evict_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT (app_id, key) DO UPDATE SET
+                 source = excluded.source, blob = excluded.blob, size = excluded.size,
+                 stored_at = excluded.stored_at, expires_at = excluded.expires_at,
+                 evict_at = excluded.evict_at",
+            params![
+                app_id,
+                key,
+                entry.key_source,
+                encoded,
+                encoded.len() as i64,
+                now.timestamp(),
+                entry.expires_at.timestamp(),
+                evict_at,
+            ],
+        )?;
+        tx.execute(
+            "DELETE FROM entry_tags WHERE app_id = ?1 AND key = ?2",
+            params![app_id, key],
+        )?;
+        for tag in &entry.tags {
+            tx.execute(
+                "INSERT INTO entry_tags (app_id, tag, key) VALUES (?1, ?2, ?3)",
+                params![app_id, tag, key],
+            )?;
+        }
+        tx.commit()?;
+
+        info!("✅ Key '{}' stored in SQLite", key);
+        Ok(())
+    })
+    .await
+}
+
+/// Loads an entry. `Ok(None)` if it is missing or past its eviction time.
+pub async fn load_from_cache(key: &str) -> StorageResult<Option<CachedResponse>> {
+    let key = key.to_string();
+    with_conn(move |conn, app_id| {
+        let raw: Option<Vec<u8>> = conn
+            .query_row(
+                &format!("SELECT blob FROM entries WHERE app_id = ?1 AND key = ?3 AND {LIVE}"),
+                params![app_id, Utc::now().timestamp(), key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        match blob::decode(&raw) {
+            Ok(entry) => {
+                info!("📦 Key '{}' loaded from SQLite", key);
+                Ok(Some(entry))
+            }
+            Err(e) => {
+                error!("❌ Failed to decode cache for key '{}': {}", key, e);
+                Err(e)
+            }
+        }
+    })
+    .await
+}
+
+/// Loads the metadata row `name` of the current app.
+pub async fn load_meta(name: &str) -> StorageResult<Option<Vec<u8>>> {
+    let name = name.to_string();
+    with_conn(move |conn, app_id| {
+        Ok(conn
+            .query_row(
+                "SELECT data FROM meta WHERE app_id = ?1 AND name = ?2",
+                params![app_id, name],
+                |row| row.get(0),
+            )
+            .optional()?)
+    })
+    .await
+}
+
+/// Stores the metadata row `name` of the current app.
+pub async fn store_meta(name: &str, data: Vec<u8>) -> StorageResult<()> {
+    let name = name.to_string();
+    with_conn(move |conn, app_id| {
+        conn.execute(
+            "INSERT OR REPLACE INTO meta (app_id, name, data) VALUES (?1, ?2, ?3)",
+            params![app_id, name, data],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes one entry (tags follow through the foreign key).
+pub async fn delete_from_cache(key: &str) -> StorageResult<bool> {
+    let key = key.to_string();
+    with_conn(move |conn, app_id| {
+        let deleted = conn.execute(
+            "DELETE FROM entries WHERE app_id = ?1 AND key = ?2",
+            params![app_id, key],
+        )?;
+        Ok(deleted > 0)
+    })
+    .await
+}
+
+/// Lists the live entries of the current app, most recent first.
+pub async fn list_cache_entries() -> StorageResult<Vec<StoredObject>> {
+    with_conn(|conn, app_id| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT key, stored_at, size FROM entries
+             WHERE app_id = ?1 AND {LIVE} ORDER BY stored_at DESC"
+        ))?;
+        let rows = stmt.query_map(params![app_id, Utc::now().timestamp()], |row| {
+            Ok(StoredObject {
+                key: row.get(0)?,
+                last_modified: timestamp_to_datetime(row.get(1)?),
+                size: row.get::<_, i64>(2)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    })
+    .await
+}
+
+/// Runs a `DELETE` on `entries` and returns the keys of the deleted rows.
+fn delete_returning(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("{sql} RETURNING key"))?;
+    let keys = stmt.query_map(params, |row| row.get(0))?;
+    keys.collect()
+}
+
+/// Deletes every entry of the current app and returns their keys.
+pub async fn delete_all_from_cache() -> StorageResult<Vec<String>> {
+    with_conn(|conn, app_id| {
+        let deleted =
+            delete_returning(conn, "DELETE FROM entries WHERE app_id = ?1", params![app_id])?;
+        info!("✅ SQLite: Deleted {} entries of app '{app_id}'", deleted.len());
+        Ok(deleted)
+    })
+    .await
+}
+
+/// Deletes the entries of the current app whose key source starts with
+/// `prefix` and returns their keys.
+pub async fn delete_by_source_prefix(prefix: &str) -> StorageResult<Vec<String>> {
+    let pattern = glob_prefix(prefix);
+    with_conn(move |conn, app_id| {
+        Ok(delete_returning(
+            conn,
+            "DELETE FROM entries WHERE app_id = ?1 AND source GLOB ?2",
+            params![app_id, pattern],
+        )?)
+    })
+    .await
+}
+
+/// Deletes the entries of the current app carrying any of `tags` and returns
+/// their keys.
+pub async fn delete_by_tags(tags: &[String]) -> StorageResult<Vec<String>> {
+    let tags = tags.to_vec();
+    with_conn(move |conn, app_id| {
+        let tx = conn.transaction()?;
+        let mut deleted = Vec::new();
+        for tag in &tags {
+            deleted.extend(delete_returning(
+                &tx,
+                "DELETE FROM entries WHERE app_id = ?1 AND key IN
+                     (SELECT key FROM entry_tags WHERE app_id = ?1 AND tag = ?2)",
+                params![app_id, tag],
+            )?);
+        }
+        tx.commit()?;
+        Ok(deleted)
+    })
+    .await
+}
+
+/// Counts the live entries of the current app and their stored size.
+pub async fn storage_usage() -> StorageResult<StorageUsage> {
+    with_conn(|conn, app_id| {
+        let (entries, bytes): (i64, i64) = conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM entries WHERE app_id = ?1 AND {LIVE}"
+            ),
+            params![app_id, Utc::now().timestamp()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(StorageUsage {
+            entries: entries as u64,
+            bytes: bytes as u64,
+        })
+    })
+    .await
+}
+
+/// Deletes the rows of every app that are past their eviction time.
+pub async fn purge_evicted() -> StorageResult<usize> {
+    with_conn(|conn, _| {
+        Ok(conn.execute(
+            "DELETE FROM entries WHERE evict_at IS NOT NULL AND evict_at <= ?1",
+            params![Utc::now().timestamp()],
+        )?)
+    })
+    .await
+}
+
+/// SQLite backend: one row per key in the `sqlite_path` database.
+pub struct SqliteBackend;
+
+#[async_trait]
+impl StorageBackend for SqliteBackend {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn init(&self) -> StorageResult<()> {
+        init_sqlite().await
+    }
+
+    async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+        load_from_cache(key).await
+    }
+
+    async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+        store_in_cache(key, entry).await
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        delete_from_cache(key).await
+    }
+
+    async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+        list_cache_entries().await
+    }
+
+    async fn delete_all(&self) -> StorageResult<Vec<String>> {
+        delete_all_from_cache().await
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        with_conn(|conn, _| Ok(conn.query_row("SELECT 1", [], |_| Ok(()))?)).await
+    }
+
+    async fn get_meta(&self, name: &str) -> StorageResult<Option<Bytes>> {
+        Ok(load_meta(name).await?.map(Bytes::from))
+    }
+
+    async fn put_meta(&self, name: &str, data: Bytes) -> StorageResult<()> {
+        store_meta(name, data.to_vec()).await
+    }
+
+    async fn delete_source_prefix(&self, prefix: &str) -> StorageResult<Vec<String>> {
+        delete_by_source_prefix(prefix).await
+    }
+
+    async fn delete_tagged(&self, tags: &[String]) -> StorageResult<Vec<String>> {
+        delete_by_tags(tags).await
+    }
+
+    async fn usage(&self) -> StorageResult<StorageUsage> {
+        storage_usage().await
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Ordered persistent storage tiers, e.g. local disk (L2) in front of S3 (L3).
+///
+/// - Lookups go through the tiers in order; a hit in a lower tier is promoted
+///   to the tiers above it in the background.
+/// - Writes go to every tier (write-through), or to the first tier right away
+///   and to the others after a delay (write-back). Write-backs go through the
+///   persistence queue, so failed ones are retried and journaled like any write.
+/// - Each tier has its own circuit breaker; an open tier is skipped.
+/// - Bulk deletions report the union of the keys each tier deleted, so a key
+///   kept in several tiers counts once.
+use async_trait::async_trait;
+use bytes::Bytes;
+use dashmap::DashMap;
+use metrics::{counter, gauge};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::cache_key::source_uri;
+use crate::config::{Config, WritePolicy};
+use crate::memory::memory::CachedResponse;
+use crate::storage::{
+    StorageBackend, StorageResult, StorageUsage, StoredObject, backend_for, queue,
+    breaker::{self, BreakerOpen},
+};
+
+/// Persistent storage made of one or more tiers, fastest first.
+pub struct TieredStorage {
+    tiers: Vec<Arc<dyn StorageBackend>>,
+    write_policy: WritePolicy,
+    write_back_delay: Duration,
+    /// The tiers below the first, written through, for write-backs.
+    lower: Option<Arc<dyn StorageBackend>>,
+    /// Entries waiting to be written back to the lower tiers.
+    pending: Arc<DashMap<String, CachedResponse>>,
+}
+
+impl TieredStorage {
+    /// Builds the storage from tiers ordered fastest first. Panics if `tiers` is empty.
+    pub fn new(
+        tiers: Vec<Arc<dyn StorageBackend>>,
+        write_policy: WritePolicy,
+        write_back_delay: Duration,
+    ) -> Self {
+        assert!(!tiers.is_empty(), "TieredStorage needs at least one tier");
+        // Register every breaker up front so the admin API lists them
+        for tier in &tiers {
+            breaker::for_backend(tier);
+        }
+        let lower = (write_policy == WritePolicy::WriteBack && tiers.len() > 1).then(|| {
+            let lower = TieredStorage::new(tiers[1..].to_vec(), WritePolicy::WriteThrough, Duration::ZERO);
+            Arc::new(lower) as Arc<dyn StorageBackend>
+        });
+        TieredStorage {
+            tiers,
+            write_policy,
+            write_back_delay,
+            lower,
+            pending: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Builds the tiers selected by `storage.tiers` (or `storage_backend`).
+    pub fn from_config(config: &Config) -> Self {
+        let tiers = config.storage_tiers().iter().map(backend_for).collect();
+        TieredStorage::new(
+            tiers,
+            config.storage.write_policy,
+            Duration::from_millis(config.storage.write_back_delay_ms),
+        )
+    }
+
+    /// Runs a bulk deletion on every tier and returns the union of the keys
+    /// they deleted. Fails with the first error, once every tier was tried.
+    async fn delete_from_tiers<'a, F, Fut>(&'a self, delete: F) -> StorageResult<Vec<String>>
+    where
+        F: Fn(&'a Arc<dyn StorageBackend>) -> Fut,
+        Fut: Future<Output = StorageResult<Vec<String>>> + Send,
+    {
+        let mut deleted = HashSet::new();
+        let mut first_error = None;
+        for tier in &self.tiers {
+            match delete(tier).await {
+                Ok(keys) => deleted.extend(keys),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or_else(|| Ok(deleted.into_iter().collect()), Err)
+    }
+
+    /// Copies an entry found in tier `level` into the tiers above it.
+    fn promote(&self, key: &str, entry: &CachedResponse, level: usize) {
+        let upper = self.tiers[..level].to_vec();
+        let key = key.to_string();
+        let entry = entry.clone();
+        tokio::spawn(async move {
+            for tier in upper {
+                if put_tier(&tier, &key, entry.clone()).await.is_ok() {
+                    counter!("cachebolt_tier_promotions_total", "backend" => tier.name())
+                        .increment(1);
+                }
+            }
+        });
+    }
+
+    /// Queues an entry for the lower tiers. Rewrites of a key already queued
+    /// replace the pending entry instead of scheduling another write.
+    fn schedule_write_back(&self, key: &str, entry: CachedResponse) {
+        let Some(lower) = self.lower.clone() else {
+            return;
+        };
+        if self.pending.insert(key.to_string(), entry).is_some() {
+            return;
+        }
+        gauge!("cachebolt_write_back_pending").set(self.pending.len() as f64);
+
+        let pending = Arc::clone(&self.pending);
+        let delay = self.write_back_delay;
+        let key = key.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            // Deleted or cleared in the meantime
+            let Some((key, entry)) = pending.remove(&key) else {
+                return;
+            };
+            gauge!("cachebolt_write_back_pending").set(pending.len() as f64);
+            match queue::queue() {
+                Some(queue) => queue.enqueue_to(lower, key, entry).await,
+                // Storage built without a configuration has no queue
+                None => {
+                    if let Err(e) = lower.put(&key, entry).await {
+                        warn!("⚠️ Write-back of key '{}' failed: {}", key, e);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Writes an entry to one tier, honoring its circuit breaker. A write skipped
+/// because the breaker is open fails with `BreakerOpen`, so the persistence
+/// queue retries it and spills it to the journal.
+async fn put_tier(tier: &Arc<dyn StorageBackend>, key: &str, entry: CachedResponse) -> StorageResult<()> {
+    let name = tier.name();
+    counter!("cachebolt_persist_attempts_total", "backend" => name).increment(1);
+
+    let breaker = breaker::for_backend(tier);
+    let Some(permit) = breaker.allow() else {
+        warn!(
+            "Skipping {} write because circuit breaker is {:?} (key={})",
+            name,
+            breaker.state(),
+            key
+        );
+        counter!("cachebolt_persist_skipped_total", "backend" => name).increment(1);
+        return Err(Box::new(BreakerOpen { backend: name }));
+    };
+
+    match tier.put(key, entry).await {
+        Ok(()) => {
+            permit.success();
+            Ok(())
+        }
+        Err(e) => {
+            warn!("❌ Error storing key '{}' in {}: {}", key, name, e);
+            counter!("cachebolt_persist_errors_total", "backend" => name).increment(1);
+            permit.failure(&*e, "store");
+            Err(e)
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for TieredStorage {
+    /// The tier's name with a single tier, otherwise `"tiered"`.
+    fn name(&self) -> &'static str {
+        match self.tiers.as_slice() {
+            [only] => only.name(),
+            _ => "tiered",
+        }
+    }
+
+    async fn init(&self) -> StorageResult<()> {
+        for tier in &self.tiers {
+            tier.init().await?;
+            info!("✅ {} storage tier initialized", tier.name());
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+        let mut first_error = None;
+
+        for (level, tier) in self.tiers.iter().enumerate() {
+            let breaker = breaker::for_backend(tier);
+            let Some(permit) = breaker.allow() else {
+                warn!(
+                    "Skipping {} load because circuit breaker is {:?} (key={})",
+                    tier.name(),
+                    breaker.state(),
+                    key
+                );
+                continue;
+            };
+
+            match tier.get(key).await {
+                Ok(Some(entry)) => {
+                    permit.success();
+                    counter!("cachebolt_tier_hits_total", "backend" => tier.name()).increment(1);
+                    if level > 0 {
+                        self.promote(key, &entry, level);
+                    }
+                    return Ok(Some(entry));
+                }
+                Ok(None) => permit.success(),
+                Err(e) => {
+                    warn!("⚠️ Failed to load key '{}' from {}: {}", key, tier.name(), e);
+                    permit.failure(&*e, "load");
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        first_error.map_or(Ok(None), Err)
+    }
+
+    async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+        match self.write_policy {
+            WritePolicy::WriteThrough => {
+                let mut first_error = None;
+                for tier in &self.tiers {
+                    if let Err(e) = put_tier(tier, key, entry.clone()).await {
+                        first_error.get_or_insert(e);
+                    }
+                }
+                first_error.map_or(Ok(()), Err)
+            }
+            WritePolicy::WriteBack => {
+                self.schedule_write_back(key, entry.clone());
+                put_tier(&self.tiers[0], key, entry).await
+            }
+        }
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<bool> {
+        self.pending.remove(key);
+
+        let mut deleted = false;
+        let mut first_error = None;
+        for tier in &self.tiers {
+            match tier.delete(key).await {
+                Ok(found) => deleted |= found,
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(deleted), Err)
+    }
+
+    /// Entries of every tier, deduplicated by key (newest copy wins).
+    async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+        let mut by_key: HashMap<String, StoredObject> = HashMap::new();
+        let mut first_error = None;
+        let mut listed_any = false;
+
+        for tier in &self.tiers {
+            match tier.list().await {
+                Ok(objects) => {
+                    listed_any = true;
+                    for object in objects {
+                        match by_key.get(&object.key) {
+                            Some(known) if known.last_modified >= object.last_modified => {}
+                            _ => {
+                                by_key.insert(object.key.clone(), object);
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("⚠️ Failed to list {}: {}", tier.name(), e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) if !listed_any => Err(e),
+            _ => Ok(by_key.into_values().collect()),
+        }
+    }
+
+    async fn delete_all(&self) -> StorageResult<Vec<String>> {
+        self.pending.clear();
+        gauge!("cachebolt_write_back_pending").set(0.0);
+
+        self.delete_from_tiers(|tier| tier.delete_all()).await
+    }
+
+    async fn health_check(&self) -> StorageResult<()> {
+        for tier in &self.tiers {
+            tier.health_check().await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_source_prefix(&self, prefix: &str) -> StorageResult<Vec<String>> {
+        self.pending.retain(|_, entry| !entry.key_source.starts_with(prefix));
+
+        self.delete_from_tiers(|tier| tier.delete_source_prefix(prefix)).await
+    }
+
+    async fn delete_source_matching(&self, pattern: &Regex) -> StorageResult<Vec<String>> {
+        self.pending
+            .retain(|_, entry| !pattern.is_match(source_uri(&entry.key_source)));
+
+        self.delete_from_tiers(|tier| tier.delete_source_matching(pattern)).await
+    }
+
+    async fn delete_tagged(&self, tags: &[String]) -> StorageResult<Vec<String>> {
+        self.pending
+            .retain(|_, entry| !entry.tags.iter().any(|tag| tags.contains(tag)));
+
+        self.delete_from_tiers(|tier| tier.delete_tagged(tags)).await
+    }
+
+    /// The copy of the first tier that has one.
+    async fn get_meta(&self, name: &str) -> StorageResult<Option<Bytes>> {
+        let mut first_error = None;
+        for tier in &self.tiers {
+            match tier.get_meta(name).await {
+                Ok(Some(data)) => return Ok(Some(data)),
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("⚠️ Failed to load {} from {}: {}", name, tier.name(), e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(None), Err)
+    }
+
+    async fn put_meta(&self, name: &str, data: Bytes) -> StorageResult<()> {
+        let mut first_error = None;
+        for tier in &self.tiers {
+            if let Err(e) = tier.put_meta(name, data.clone()).await {
+                warn!("❌ Error storing {} in {}: {}", name, tier.name(), e);
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Entries and bytes summed over every tier.
+    async fn usage(&self) -> StorageResult<StorageUsage> {
+        let mut total = StorageUsage::default();
+        for tier in &self.tiers {
+            let usage = tier.usage().await?;
+            total.entries += usage.entries;
+            total.bytes += usage.bytes;
+        }
+        Ok(total)
+    }
+}
@@ -0,0 +1,26 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::storage::{BACKEND, StorageBackend};
+
+/// Installs the backend used by the proxy instead of the configured tiers,
+/// e.g. an in-memory fake in tests.
+///
+/// # Returns
+/// `false` if a backend was already installed or used.
+pub fn install_backend(backend: Arc<dyn StorageBackend>) -> bool {
+    BACKEND.set(backend).is_ok()
+}
@@ -0,0 +1,154 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Startup warm-up of the in-memory cache from the persistent backend.
+///
+/// After a restart `MEMORY_CACHE` is empty. When `warmup.enabled` is set, the
+/// entries under `cache/{app_id}/` are listed, the most recent ones are loaded
+/// (up to `warmup.max_entries` and `warmup.max_bytes`) and inserted into memory
+/// in the background, so the listener binds without waiting for the backend.
+use futures::{StreamExt, stream};
+use metrics::{counter, gauge};
+use std::time::Instant;
+use tokio::task;
+use tracing::{info, warn};
+
+use crate::config::CONFIG;
+use crate::memory::memory;
+use crate::proxy::load_from_backend;
+use crate::storage::{self, StoredObject};
+
+/// Summary of a completed warm-up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WarmupReport {
+    /// Entries listed in the backend.
+    pub listed: usize,
+    /// Entries inserted into memory.
+    pub loaded: usize,
+    /// Entries that could not be loaded or can no longer be served.
+    pub skipped: usize,
+    /// Bytes inserted into memory (keys plus `CachedResponse::size_bytes`).
+    pub bytes: usize,
+}
+
+/// Orders listed entries from most to least recently written and keeps at
+/// most `max_entries` of them (0 = no limit).
+pub fn select_for_warmup(mut objects: Vec<StoredObject>, max_entries: usize) -> Vec<StoredObject> {
+    objects.sort_by_key(|o| std::cmp::Reverse(o.last_modified));
+    if max_entries > 0 {
+        objects.truncate(max_entries);
+    }
+    objects
+}
+
+/// Loads the most recent persisted entries into `MEMORY_CACHE`.
+///
+/// Entries are fetched `warmup.concurrency` at a time, newest first. Entries that
+/// fail to load or can no longer be served are skipped; loading stops once
+/// `warmup.max_bytes` would be exceeded.
+pub async fn warm_up_memory_cache() -> WarmupReport {
+    let settings = CONFIG.get().map(|c| c.warmup.clone()).unwrap_or_default();
+    let mut report = WarmupReport::default();
+
+    let Some(backend) = storage::backend() else {
+        return report;
+    };
+
+    let objects = match backend.list().await {
+        Ok(objects) => objects,
+        Err(e) => {
+            warn!("⚠️ Cache warm-up could not list the persistent backend: {}", e);
+            counter!("cachebolt_warmup_failures_total").increment(1);
+            return report;
+        }
+    };
+    report.listed = objects.len();
+
+    let selected = select_for_warmup(objects, settings.max_entries);
+    let total = selected.len();
+    let stored_bytes = selected.iter().map(|o| o.size).sum::<u64>();
+    info!(
+        "🔥 Warming MEMORY_CACHE with up to {} of {} persisted entries ({} bytes stored)",
+        total, report.listed, stored_bytes
+    );
+
+    let max_bytes = match settings.max_bytes {
+        0 => usize::MAX,
+        max => max,
+    };
+
+    let mut loads = stream::iter(selected)
+        .map(|object| async move {
+            let entry = load_from_backend(&object.key).await.ok().flatten();
+            (object.key, entry)
+        })
+        .buffered(settings.concurrency.max(1));
+
+    while let Some((key, entry)) = loads.next().await {
+        let Some(entry) = entry.filter(|e| e.is_servable()) else {
+            report.skipped += 1;
+            counter!("cachebolt_warmup_skipped_entries_total").increment(1);
+            continue;
+        };
+
+        let weight = key.len() + entry.size_bytes();
+        if report.bytes + weight > max_bytes {
+            info!("🔥 Cache warm-up reached warmup.max_bytes ({} bytes)", report.bytes);
+            break;
+        }
+
+        memory::load_into_memory(vec![(key, entry)]).await;
+        report.loaded += 1;
+        report.bytes += weight;
+        counter!("cachebolt_warmup_loaded_entries_total").increment(1);
+        counter!("cachebolt_warmup_loaded_bytes_total").increment(weight as u64);
+
+        if report.loaded % 100 == 0 {
+            info!("🔥 Cache warm-up progress: {}/{} entries loaded", report.loaded, total);
+        }
+    }
+
+    report
+}
+
+/// Launches the warm-up in the background if `warmup.enabled` is set.
+/// Requests are served while it runs; early misses simply go downstream.
+pub fn start_warmup_task() {
+    let settings = CONFIG.get().map(|c| c.warmup.clone()).unwrap_or_default();
+
+    if !settings.enabled {
+        info!("🔥 Cache warm-up disabled");
+        return;
+    }
+
+    task::spawn(async move {
+        let started = Instant::now();
+        gauge!("cachebolt_warmup_in_progress").set(1.0);
+
+        let report = warm_up_memory_cache().await;
+
+        gauge!("cachebolt_warmup_in_progress").set(0.0);
+        gauge!("cachebolt_warmup_duration_seconds").set(started.elapsed().as_secs_f64());
+        info!(
+            "🔥 Cache warm-up finished in {:.1}s: {} loaded ({} bytes), {} skipped, {} listed",
+            started.elapsed().as_secs_f64(),
+            report.loaded,
+            report.bytes,
+            report.skipped,
+            report.listed
+        );
+    });
+
+    info!("🔥 Cache warm-up started in the background");
+}
@@ -0,0 +1,173 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use cachebolt::memory::memory::CachedResponse;
+    use cachebolt::storage::blob::{self, MAGIC, VERSION};
+    use flate2::{Compression, write::GzEncoder};
+    use std::io::Write;
+
+    fn entry(body: &'static [u8]) -> CachedResponse {
+        let now = chrono::Utc::now();
+        CachedResponse {
+            status: 200,
+            body: Bytes::from_static(body),
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(60),
+            must_revalidate: true,
+            stale_while_revalidate: 30,
+            stale_if_error: 600,
+            key_source: "GET /api/items".to_string(),
+            tags: vec!["items".to_string(), "list".to_string()],
+            purged: false,
+        }
+    }
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let stored = entry(br#"{"items":[1,2,3]}"#);
+        let raw = blob::encode(&stored).unwrap();
+
+        assert!(raw.starts_with(MAGIC));
+        assert_eq!(raw[MAGIC.len()], VERSION);
+        assert!(blob::is_envelope(&raw));
+
+        let loaded = blob::decode(&raw).unwrap();
+        assert_eq!(loaded.status, 200);
+        assert_eq!(loaded.body, stored.body);
+        assert_eq!(loaded.headers, stored.headers);
+        assert_eq!(loaded.inserted_at.timestamp(), stored.inserted_at.timestamp());
+        assert_eq!(loaded.expires_at.timestamp(), stored.expires_at.timestamp());
+        assert!(loaded.must_revalidate);
+        assert_eq!(loaded.stale_while_revalidate, 30);
+        assert_eq!(loaded.stale_if_error, 600);
+        assert_eq!(loaded.key_source, "GET /api/items");
+        assert_eq!(loaded.tags, vec!["items", "list"]);
+    }
+
+    /// Rewrites an envelope as `version`, dropping the header fields it lacks
+    /// (trailing and empty or `false` in `raw`: one byte each).
+    fn downgrade(mut raw: Vec<u8>, version: u8) -> Vec<u8> {
+        let dropped = usize::from(VERSION - version);
+        let header_len = u32::from_le_bytes(raw[5..9].try_into().unwrap()) as usize;
+        raw.drain(9 + header_len - dropped..9 + header_len);
+        raw[5..9].copy_from_slice(&((header_len - dropped) as u32).to_le_bytes());
+        raw[MAGIC.len()] = version;
+        raw
+    }
+
+    #[test]
+    fn test_envelope_roundtrip_keeps_status() {
+        let stored = CachedResponse {
+            status: 203,
+            ..entry(b"body")
+        };
+        assert_eq!(blob::decode(&blob::encode(&stored).unwrap()).unwrap().status, 203);
+    }
+
+    #[test]
+    fn test_envelope_roundtrip_soft_purge_flag() {
+        let stored = CachedResponse {
+            purged: true,
+            ..entry(b"body")
+        };
+        assert!(blob::decode(&blob::encode(&stored).unwrap()).unwrap().purged);
+    }
+
+    #[test]
+    fn test_decode_reads_older_versions() {
+        let mut stored = entry(b"body");
+        stored.headers.push(("Surrogate-Key".to_string(), "item-1 items".to_string()));
+        stored.tags.clear();
+        let raw = blob::encode(&stored).unwrap();
+
+        // Version 2 carries tags but no soft-purge flag
+        let loaded = blob::decode(&downgrade(raw.clone(), 2)).unwrap();
+        assert!(loaded.tags.is_empty() && !loaded.purged);
+
+        // Version 1 tags are read back from the tag headers
+        let loaded = blob::decode(&downgrade(raw, 1)).unwrap();
+        assert_eq!(loaded.body, Bytes::from_static(b"body"));
+        assert_eq!(loaded.tags, vec!["item-1", "items"]);
+        assert!(!loaded.purged);
+    }
+
+    #[test]
+    fn test_envelope_roundtrip_empty_body() {
+        let raw = blob::encode(&entry(b"")).unwrap();
+        assert!(blob::decode(&raw).unwrap().body.is_empty());
+    }
+
+    #[test]
+    fn test_decode_rejects_tampered_body() {
+        let stored = entry(b"original body");
+        let mut raw = blob::encode(&stored).unwrap();
+
+        // Replace the compressed body with a valid gzip stream of other bytes
+        let header_len = u32::from_le_bytes(raw[5..9].try_into().unwrap()) as usize;
+        raw.truncate(9 + header_len);
+        let mut encoder = GzEncoder::new(raw, Compression::default());
+        encoder.write_all(b"tampered body").unwrap();
+        let raw = encoder.finish().unwrap();
+
+        let Err(err) = blob::decode(&raw) else {
+            panic!("expected decode to fail");
+        };
+        assert!(err.to_string().contains("hash mismatch"), "{err}");
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_version() {
+        let mut raw = blob::encode(&entry(b"body")).unwrap();
+        raw[MAGIC.len()] = VERSION + 1;
+
+        let Err(err) = blob::decode(&raw) else {
+            panic!("expected decode to fail");
+        };
+        assert!(err.to_string().contains("version"), "{err}");
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_header() {
+        let raw = blob::encode(&entry(b"body")).unwrap();
+        assert!(blob::decode(&raw[..12]).is_err());
+        assert!(!blob::is_envelope(&raw[..6]));
+    }
+
+    #[test]
+    fn test_decode_any_reads_legacy_layouts() {
+        let legacy = br#"{"body":"SGVsbG8=","headers":[["X-Test","true"]],"expires_at":4102444800}"#;
+
+        // Azure stored plain JSON
+        let plain = blob::decode_any(legacy, false).unwrap();
+        assert_eq!(plain.body, Bytes::from("Hello"));
+        assert_eq!(plain.headers, vec![("X-Test".to_string(), "true".to_string())]);
+        assert!(plain.is_fresh());
+
+        // Local and GCS stored gzipped JSON
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(legacy).unwrap();
+        let gzipped = blob::decode_any(&encoder.finish().unwrap(), true).unwrap();
+        assert_eq!(gzipped.body, Bytes::from("Hello"));
+    }
+
+    #[test]
+    fn test_decode_any_prefers_envelope() {
+        let raw = blob::encode(&entry(b"body")).unwrap();
+        assert_eq!(blob::decode_any(&raw, false).unwrap().body, Bytes::from("body"));
+    }
+}
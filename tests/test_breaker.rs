@@ -0,0 +1,240 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use cachebolt::memory::memory::CachedResponse;
+    use cachebolt::storage::breaker::{
+        BackendError, BreakerState, CircuitBreaker, ErrorClass, classify,
+    };
+    use cachebolt::storage::{StorageBackend, StorageResult, StoredObject};
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    /// Backend whose health probe result can be switched on and off.
+    #[derive(Default)]
+    struct FlakyBackend {
+        healthy: AtomicBool,
+    }
+
+    #[async_trait]
+    impl StorageBackend for FlakyBackend {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        async fn get(&self, _key: &str) -> StorageResult<Option<CachedResponse>> {
+            Ok(None)
+        }
+
+        async fn put(&self, _key: &str, _entry: CachedResponse) -> StorageResult<()> {
+            Ok(())
+        }
+
+        async fn delete(&self, _key: &str) -> StorageResult<bool> {
+            Ok(false)
+        }
+
+        async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+            Ok(vec![])
+        }
+
+        async fn delete_all(&self) -> StorageResult<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn health_check(&self) -> StorageResult<()> {
+            if self.healthy.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err("connection refused".into())
+            }
+        }
+    }
+
+    const PROBE_INTERVAL: Duration = Duration::from_millis(20);
+
+    fn breaker(threshold: usize) -> (Arc<FlakyBackend>, Arc<CircuitBreaker>) {
+        let backend = Arc::new(FlakyBackend::default());
+        let breaker = Arc::new(CircuitBreaker::new(backend.clone(), threshold, PROBE_INTERVAL));
+        (backend, breaker)
+    }
+
+    fn unavailable() -> io::Error {
+        io::Error::from(io::ErrorKind::ConnectionRefused)
+    }
+
+    #[test]
+    fn test_classify_error_types() {
+        assert_eq!(classify(&unavailable()), ErrorClass::Unavailable);
+        assert_eq!(classify(&io::Error::from(io::ErrorKind::TimedOut)), ErrorClass::Timeout);
+        assert_eq!(classify(&io::Error::from(io::ErrorKind::PermissionDenied)), ErrorClass::Unauthorized);
+
+        let json = serde_json::from_str::<u32>("not json").unwrap_err();
+        assert_eq!(classify(&json), ErrorClass::Corrupt);
+
+        let tagged = BackendError::new(ErrorClass::Throttled, "SlowDown");
+        assert_eq!(classify(&tagged), ErrorClass::Throttled);
+    }
+
+    #[test]
+    fn test_classify_walks_source_chain() {
+        let wrapped = BackendError::new(ErrorClass::Other, unavailable());
+        // The outermost known type wins
+        assert_eq!(classify(&wrapped), ErrorClass::Other);
+
+        #[derive(Debug)]
+        struct Wrapper(io::Error);
+        impl std::fmt::Display for Wrapper {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "request failed")
+            }
+        }
+        impl std::error::Error for Wrapper {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+        assert_eq!(classify(&Wrapper(unavailable())), ErrorClass::Unavailable);
+    }
+
+    #[test]
+    fn test_classify_falls_back_to_message() {
+        let classify_msg = |msg: &str| {
+            let e: Box<dyn std::error::Error + Send + Sync> = msg.into();
+            classify(&*e)
+        };
+        assert_eq!(classify_msg("dispatch failure"), ErrorClass::Unavailable);
+        assert_eq!(classify_msg("operation timed out"), ErrorClass::Timeout);
+        assert_eq!(classify_msg("Access Denied"), ErrorClass::Unauthorized);
+        assert_eq!(classify_msg("object missing header"), ErrorClass::Other);
+    }
+
+    #[test]
+    fn test_status_classes() {
+        assert_eq!(ErrorClass::from_status(403), ErrorClass::Unauthorized);
+        assert_eq!(ErrorClass::from_status(404), ErrorClass::NotFound);
+        assert_eq!(ErrorClass::from_status(429), ErrorClass::Throttled);
+        assert_eq!(ErrorClass::from_status(503), ErrorClass::Unavailable);
+        assert!(!ErrorClass::NotFound.trips_breaker());
+        assert!(!ErrorClass::Corrupt.trips_breaker());
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_threshold() {
+        let (_backend, breaker) = breaker(2);
+
+        for _ in 0..2 {
+            breaker.record_failure(&unavailable(), "load");
+            assert_eq!(breaker.state(), BreakerState::Closed);
+        }
+        breaker.record_failure(&unavailable(), "load");
+
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(breaker.allow().is_none());
+        assert!(breaker.snapshot().opened_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_ignores_non_availability_errors_and_resets_on_success() {
+        let (_backend, breaker) = breaker(1);
+
+        let json = serde_json::from_str::<u32>("not json").unwrap_err();
+        for _ in 0..5 {
+            breaker.record_failure(&json, "load");
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        breaker.record_failure(&unavailable(), "load");
+        breaker.record_success();
+        breaker.record_failure(&unavailable(), "load");
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert_eq!(breaker.snapshot().consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_zero_threshold_never_opens() {
+        let (_backend, breaker) = breaker(0);
+        for _ in 0..10 {
+            breaker.record_failure(&unavailable(), "store");
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.allow().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_probe_half_opens_and_trial_success_closes() {
+        let (backend, breaker) = breaker(1);
+        breaker.record_failure(&unavailable(), "load");
+        breaker.record_failure(&unavailable(), "load");
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // Probes keep failing while the backend is down
+        tokio::time::sleep(PROBE_INTERVAL * 3).await;
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        backend.healthy.store(true, Ordering::SeqCst);
+        tokio::time::sleep(PROBE_INTERVAL * 5).await;
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        // A single trial call is let through
+        let trial = breaker.allow().expect("trial permit");
+        assert!(breaker.allow().is_none());
+
+        trial.success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.allow().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_failed_trial_reopens() {
+        let (backend, breaker) = breaker(1);
+        breaker.record_failure(&unavailable(), "load");
+        breaker.record_failure(&unavailable(), "load");
+
+        backend.healthy.store(true, Ordering::SeqCst);
+        tokio::time::sleep(PROBE_INTERVAL * 5).await;
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        backend.healthy.store(false, Ordering::SeqCst);
+        let trial = breaker.allow().expect("trial permit");
+        trial.failure(&unavailable(), "load");
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(breaker.allow().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_trial_reopens() {
+        let (backend, breaker) = breaker(1);
+        breaker.record_failure(&unavailable(), "load");
+        breaker.record_failure(&unavailable(), "load");
+
+        backend.healthy.store(true, Ordering::SeqCst);
+        tokio::time::sleep(PROBE_INTERVAL * 5).await;
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        // The trial caller goes away without reporting an outcome
+        drop(breaker.allow().expect("trial permit"));
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // The probe half-opens it again and a new trial is let through
+        tokio::time::sleep(PROBE_INTERVAL * 5).await;
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        breaker.allow().expect("new trial permit").success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
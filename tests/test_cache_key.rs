@@ -0,0 +1,184 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, http::HeaderMap, response::IntoResponse, routing::get};
+    use cachebolt::{
+        cache_key::{key_source, normalize_uri, source_uri},
+        config::{
+            CONFIG, CacheKeyRule, CacheKeySettings, CacheMode, CacheSettings, Config,
+            LatencyFailover, StorageBackend,
+        },
+        memory::memory::peek_from_memory,
+        proxy::{hash_uri, proxy_handler},
+    };
+    use hyper::{Body, Request};
+    use std::net::TcpListener;
+    use std::sync::Once;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static UPSTREAM_HITS: AtomicUsize = AtomicUsize::new(0);
+    static INIT: Once = Once::new();
+
+    fn search_rule() -> CacheKeyRule {
+        CacheKeyRule {
+            pattern: "(?i)^/search".into(),
+            sort_query: true,
+            drop_params: vec!["utm_*".into(), "fbclid".into()],
+            lowercase_path: true,
+            include_cookies: vec!["currency".into()],
+            ..Default::default()
+        }
+    }
+
+    /// Starts a mock downstream on its own runtime and points CONFIG at it.
+    fn setup() {
+        INIT.call_once(|| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(async move {
+                    let app = Router::new().route(
+                        "/*path",
+                        get(|| async {
+                            UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                            ([("connection", "close")], "results").into_response()
+                        }),
+                    );
+                    axum::Server::from_tcp(listener)
+                        .unwrap()
+                        .serve(app.into_make_service())
+                        .await
+                        .unwrap();
+                });
+            });
+
+            let _ = CONFIG.set(Config {
+                app_id: "cache-key".into(),
+                max_concurrent_requests: 10,
+                downstream_base_url: format!("http://127.0.0.1:{port}"),
+                cache: CacheSettings {
+                    memory_threshold: 100,
+                    ttl_seconds: 60,
+                    mode: CacheMode::ReadThrough,
+                    ..Default::default()
+                },
+                latency_failover: LatencyFailover {
+                    default_max_latency_ms: 5000,
+                    path_rules: vec![],
+                },
+                cache_key: CacheKeySettings {
+                    path_rules: vec![
+                        search_rule(),
+                        CacheKeyRule {
+                            pattern: "^/products".into(),
+                            param_allowlist: Some(vec!["page".into()]),
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                storage_backend: Some(StorageBackend::Local),
+                ..Default::default()
+            });
+        });
+    }
+
+    async fn get_body(uri: &str, headers: &[(&str, &str)]) -> String {
+        let mut builder = Request::builder().uri(uri);
+        for (k, v) in headers {
+            builder = builder.header(*k, *v);
+        }
+        let resp = proxy_handler(builder.body(Body::empty()).unwrap())
+            .await
+            .into_response();
+        let bytes = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+
+    fn hits() -> usize {
+        UPSTREAM_HITS.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_normalize_uri() {
+        let rule = search_rule();
+        assert_eq!(
+            normalize_uri("/Search?q=shoes&b=2&utm_source=mail&fbclid=x&a=1", &rule),
+            "/search?a=1&b=2&q=shoes"
+        );
+        assert_eq!(normalize_uri("/search?utm_medium=cpc", &rule), "/search");
+
+        let allowlist = CacheKeyRule {
+            param_allowlist: Some(vec!["page".into()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            normalize_uri("/Products?session=1&page=2&sort=asc", &allowlist),
+            "/Products?page=2"
+        );
+        assert_eq!(
+            normalize_uri("/products?b=1&a=2", &CacheKeyRule::default()),
+            "/products?b=1&a=2"
+        );
+    }
+
+    #[test]
+    fn test_source_uri() {
+        assert_eq!(source_uri("/search?q=hat|accept:*/*"), "/search?q=hat");
+        assert_eq!(source_uri("POST /graphql|content-type:json|ab12"), "/graphql");
+        assert_eq!(source_uri("/a|vary|accept:text/html"), "/a");
+        assert_eq!(source_uri("/no-headers"), "/no-headers");
+    }
+
+    #[test]
+    fn test_key_source_applies_first_matching_rule() {
+        setup();
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", "session=abc; currency=EUR".parse().unwrap());
+
+        assert_eq!(
+            key_source("/search?b=2&a=1&utm_campaign=x", &headers, None),
+            "/search?a=1&b=2|cookie:session=abc; currency=EUR|cookies:currency=EUR"
+        );
+        assert_eq!(
+            key_source("/products?page=3&ref=home", &HeaderMap::new(), None),
+            "/products?page=3|"
+        );
+        assert_eq!(
+            key_source("/other?b=2&a=1", &HeaderMap::new(), None),
+            "/other?b=2&a=1|"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_equivalent_urls_share_one_entry() {
+        setup();
+        let before = hits();
+        assert_eq!(get_body("/search?q=hat&page=1", &[]).await, "results");
+        assert_eq!(
+            get_body("/SEARCH?page=1&q=hat&utm_source=news&fbclid=abc", &[]).await,
+            "results"
+        );
+        assert_eq!(hits(), before + 1);
+
+        let entry = peek_from_memory(&hash_uri("/search?page=1&q=hat|"))
+            .await
+            .expect("entry stored under the normalized key");
+        assert_eq!(entry.key_source, "/search?page=1&q=hat|");
+    }
+}
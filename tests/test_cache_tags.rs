@@ -0,0 +1,61 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use cachebolt::cache_tags;
+    use cachebolt::config::{CONFIG, CacheTagSettings, Config};
+    use cachebolt::proxy::build_response;
+    use ctor::ctor;
+
+    #[ctor]
+    fn init() {
+        let _ = CONFIG.set(Config {
+            app_id: "cache-tags-test".into(),
+            cache_tags: CacheTagSettings {
+                headers: vec!["Surrogate-Key".into(), "cache-tag".into()],
+                strip_from_response: true,
+            },
+            ..Default::default()
+        });
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_tags_from_every_configured_header() {
+        let headers = pairs(&[
+            ("surrogate-key", "product-42  category-7"),
+            ("Cache-Tag", "category-7,home"),
+            ("x-other", "ignored"),
+        ]);
+        assert_eq!(cache_tags::from_headers(&headers), vec!["category-7", "home", "product-42"]);
+        assert!(cache_tags::from_headers(&pairs(&[("surrogate-key", " , ")])).is_empty());
+    }
+
+    #[test]
+    fn test_tag_headers_are_stripped_from_responses() {
+        let mut headers = pairs(&[("content-type", "text/plain"), ("Surrogate-Key", "p1")]);
+        cache_tags::strip_pairs(&mut headers);
+        assert_eq!(headers, pairs(&[("content-type", "text/plain")]));
+
+        let resp = build_response(Bytes::new(), pairs(&[("cache-tag", "p1"), ("etag", "\"v1\"")]));
+        assert!(resp.headers().get("cache-tag").is_none());
+        assert!(resp.headers().contains_key("etag"));
+    }
+}
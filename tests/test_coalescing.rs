@@ -0,0 +1,83 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use cachebolt::coalescing::{Flight, SharedResponse, join, wait};
+    use hyper::{HeaderMap, StatusCode};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn shared(body: &'static str) -> Arc<SharedResponse> {
+        Arc::new(SharedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_followers_receive_leader_response() {
+        let Flight::Leader(guard) = join("coalesce-ok") else {
+            panic!("first request should lead");
+        };
+        let Flight::Follower(rx1) = join("coalesce-ok") else {
+            panic!("second request should follow");
+        };
+        let Flight::Follower(rx2) = join("coalesce-ok") else {
+            panic!("third request should follow");
+        };
+
+        guard.complete(Some(shared("payload")));
+
+        for rx in [rx1, rx2] {
+            let result = wait(rx, Duration::from_secs(1)).await.unwrap().unwrap();
+            let resp = result.to_response();
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+            assert_eq!(body, "payload");
+        }
+
+        // Once completed, the next request leads a new fetch
+        assert!(matches!(join("coalesce-ok"), Flight::Leader(_)));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_leader_releases_followers() {
+        let Flight::Leader(guard) = join("coalesce-drop") else {
+            panic!("first request should lead");
+        };
+        let Flight::Follower(rx) = join("coalesce-drop") else {
+            panic!("second request should follow");
+        };
+
+        drop(guard);
+
+        assert!(wait(rx, Duration::from_secs(1)).await.unwrap().is_none());
+        assert!(matches!(join("coalesce-drop"), Flight::Leader(_)));
+    }
+
+    #[tokio::test]
+    async fn test_follower_times_out() {
+        let Flight::Leader(_guard) = join("coalesce-slow") else {
+            panic!("first request should lead");
+        };
+        let Flight::Follower(rx) = join("coalesce-slow") else {
+            panic!("second request should follow");
+        };
+
+        assert!(wait(rx, Duration::from_millis(20)).await.is_err());
+    }
+}
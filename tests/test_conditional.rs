@@ -0,0 +1,222 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+    };
+    use cachebolt::{
+        config::{CONFIG, CacheMode, CacheSettings, Config, LatencyFailover, StorageBackend},
+        memory::memory::{CachedResponse, load_into_memory, peek_from_memory},
+        proxy::{hash_uri, proxy_handler},
+    };
+    use hyper::{Body, Method, Request};
+    use std::net::TcpListener;
+    use std::sync::Once;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FULL_RESPONSES: AtomicUsize = AtomicUsize::new(0);
+    static NOT_MODIFIED: AtomicUsize = AtomicUsize::new(0);
+    static INIT: Once = Once::new();
+
+    const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+    /// Starts a mock downstream honoring `If-None-Match` and points CONFIG at it.
+    fn setup() {
+        INIT.call_once(|| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(async move {
+                    let app = Router::new().route(
+                        "/*path",
+                        get(|headers: HeaderMap| async move {
+                            if headers.get("if-none-match").is_some_and(|v| v == "\"v1\"") {
+                                NOT_MODIFIED.fetch_add(1, Ordering::SeqCst);
+                                return (
+                                    StatusCode::NOT_MODIFIED,
+                                    [
+                                        ("connection", "close"),
+                                        ("etag", "\"v1\""),
+                                        ("cache-control", "max-age=120"),
+                                    ],
+                                )
+                                    .into_response();
+                            }
+                            FULL_RESPONSES.fetch_add(1, Ordering::SeqCst);
+                            (
+                                [
+                                    ("connection", "close"),
+                                    ("etag", "\"v1\""),
+                                    ("last-modified", LAST_MODIFIED),
+                                ],
+                                "from-upstream",
+                            )
+                                .into_response()
+                        }),
+                    );
+                    axum::Server::from_tcp(listener)
+                        .unwrap()
+                        .serve(app.into_make_service())
+                        .await
+                        .unwrap();
+                });
+            });
+
+            let _ = CONFIG.set(Config {
+                app_id: "conditional".into(),
+                max_concurrent_requests: 10,
+                downstream_base_url: format!("http://127.0.0.1:{port}"),
+                cache: CacheSettings {
+                    memory_threshold: 100,
+                    ttl_seconds: 60,
+                    mode: CacheMode::ReadThrough,
+                    ..Default::default()
+                },
+                latency_failover: LatencyFailover {
+                    default_max_latency_ms: 5000,
+                    path_rules: vec![],
+                },
+                storage_backend: Some(StorageBackend::Local),
+                ..Default::default()
+            });
+        });
+    }
+
+    fn entry(expires_in_secs: i64) -> CachedResponse {
+        CachedResponse {
+            status: 200,
+            body: "from-cache".into(),
+            headers: vec![
+                ("etag".into(), "\"v1\"".into()),
+                ("last-modified".into(), LAST_MODIFIED.into()),
+                ("content-type".into(), "text/plain".into()),
+            ],
+            inserted_at: chrono::Utc::now() - chrono::Duration::seconds(120),
+            expires_at: chrono::Utc::now() + chrono::Duration::seconds(expires_in_secs),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
+    async fn send(method: Method, uri: &str, headers: &[(&str, &str)]) -> (StatusCode, HeaderMap, String) {
+        let mut builder = Request::builder().method(method).uri(uri);
+        for (k, v) in headers {
+            builder = builder.header(*k, *v);
+        }
+        let resp = proxy_handler(builder.body(Body::empty()).unwrap())
+            .await
+            .into_response();
+        let (parts, body) = resp.into_parts();
+        let bytes = hyper::body::to_bytes(body).await.unwrap();
+        (parts.status, parts.headers, String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_if_none_match_answered_from_cache() {
+        setup();
+        load_into_memory(vec![(hash_uri("/inm|"), entry(60))]).await;
+
+        let (status, headers, body) =
+            send(Method::GET, "/inm", &[("if-none-match", "W/\"v1\"")]).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(headers.get("etag").unwrap(), "\"v1\"");
+        assert!(headers.get("content-type").is_none());
+        assert!(body.is_empty());
+
+        let (status, _, body) = send(Method::GET, "/inm", &[("if-none-match", "\"v2\"")]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "from-cache");
+    }
+
+    #[tokio::test]
+    async fn test_if_modified_since_answered_from_cache() {
+        setup();
+        load_into_memory(vec![(hash_uri("/ims|"), entry(60))]).await;
+
+        let (status, _, _) = send(
+            Method::GET,
+            "/ims",
+            &[("if-modified-since", "Thu, 22 Oct 2015 07:28:00 GMT")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+        let (status, _, _) = send(
+            Method::GET,
+            "/ims",
+            &[("if-modified-since", "Tue, 20 Oct 2015 07:28:00 GMT")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_client_validators_are_not_forwarded() {
+        setup();
+        let before = FULL_RESPONSES.load(Ordering::SeqCst);
+
+        // The full response is fetched and cached; the client gets a 304
+        let (status, _, _) = send(Method::GET, "/miss", &[("if-none-match", "\"v1\"")]).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(FULL_RESPONSES.load(Ordering::SeqCst), before + 1);
+
+        let (status, _, body) = send(Method::GET, "/miss", &[]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "from-upstream");
+        assert_eq!(FULL_RESPONSES.load(Ordering::SeqCst), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entry_revalidated_with_conditional_get() {
+        setup();
+        let key = hash_uri("/revalidate|");
+        load_into_memory(vec![(key.clone(), entry(-10))]).await;
+        let full_before = FULL_RESPONSES.load(Ordering::SeqCst);
+        let not_modified_before = NOT_MODIFIED.load(Ordering::SeqCst);
+
+        let (status, _, body) = send(Method::GET, "/revalidate", &[]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "from-cache");
+        assert_eq!(NOT_MODIFIED.load(Ordering::SeqCst), not_modified_before + 1);
+        assert_eq!(FULL_RESPONSES.load(Ordering::SeqCst), full_before);
+
+        let refreshed = peek_from_memory(&key).await.unwrap();
+        assert!(refreshed.is_fresh(), "304 restarts the freshness lifetime");
+        assert_eq!(refreshed.header("cache-control"), Some("max-age=120"));
+        assert_eq!(refreshed.header("content-type"), Some("text/plain"));
+    }
+
+    #[tokio::test]
+    async fn test_head_is_served_from_cache() {
+        setup();
+        load_into_memory(vec![(hash_uri("/head|"), entry(60))]).await;
+        let before = FULL_RESPONSES.load(Ordering::SeqCst);
+
+        let (status, headers, _) = send(Method::HEAD, "/head", &[]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers.get("etag").unwrap(), "\"v1\"");
+        assert_eq!(FULL_RESPONSES.load(Ordering::SeqCst), before);
+    }
+}
@@ -14,7 +14,7 @@
 
 #[cfg(test)]
 pub mod tests {
-    use cachebolt::config::{Config, LatencyFailover, CacheSettings, StorageBackend, CONFIG};
+    use cachebolt::config::{Config, LatencyFailover, CacheSettings, QueueFullPolicy, StorageBackend, WritePolicy, CONFIG};
     use std::env;
     use std::fs::write;
 
@@ -54,7 +54,7 @@ backend_retry_interval_secs: 60
         assert_eq!(config.cache.memory_threshold, 75);
         assert_eq!(config.latency_failover.default_max_latency_ms, 300);
         assert_eq!(config.latency_failover.path_rules.len(), 1);
-        assert_eq!(config.storage_backend, StorageBackend::S3);
+        assert_eq!(config.storage_backend, Some(StorageBackend::S3));
         assert_eq!(config.storage_backend_failures, 3);
         assert_eq!(config.backend_retry_interval_secs, 60);
     }
@@ -85,6 +85,189 @@ backend_retry_interval_secs: 30
         assert!(result.is_err(), "Expected error due to empty gcs_bucket");
     }
 
+    #[test]
+    fn test_redis_backend_requires_redis_url() {
+        let yaml = r#"
+app_id: testapp
+gcs_bucket: ""
+s3_bucket: ""
+azure_container: ""
+max_concurrent_requests: 5
+downstream_base_url: http://localhost
+cache:
+  memory_threshold: 75
+latency_failover:
+  default_max_latency_ms: 200
+  path_rules: []
+storage_backend: redis
+storage_backend_failures: 2
+backend_retry_interval_secs: 30
+"#;
+
+        let path = temp_config_path("invalid_redis_config.yaml");
+        write(&path, yaml).unwrap();
+        assert!(Config::from_file(&path).is_err(), "Expected error due to empty redis_url");
+
+        let path = temp_config_path("redis_config.yaml");
+        write(&path, format!("{yaml}redis_url: redis://127.0.0.1:6379/0\n")).unwrap();
+        let config = Config::from_file(&path).expect("should parse the redis backend");
+        assert_eq!(config.storage_tiers(), vec![StorageBackend::Redis]);
+    }
+
+    #[test]
+    fn test_local_storage_settings_from_file() {
+        let yaml = r#"
+app_id: testapp
+gcs_bucket: ""
+s3_bucket: ""
+azure_container: ""
+max_concurrent_requests: 5
+downstream_base_url: http://localhost
+cache:
+  memory_threshold: 75
+latency_failover:
+  default_max_latency_ms: 200
+  path_rules: []
+storage_backend: local
+storage_backend_failures: 2
+backend_retry_interval_secs: 30
+"#;
+
+        let path = temp_config_path("local_defaults_config.yaml");
+        write(&path, yaml).unwrap();
+        let config = Config::from_file(&path).expect("should parse without local_storage");
+        assert_eq!(config.local_storage.root, "storage/cache");
+        assert_eq!(config.local_storage.max_bytes, 0);
+        assert_eq!(config.local_storage.cleanup_target_percent, 90);
+
+        let path = temp_config_path("local_config.yaml");
+        let local = "local_storage:\n  root: /var/cache/cachebolt\n  max_bytes: 1048576\n";
+        write(&path, format!("{yaml}{local}")).unwrap();
+        let config = Config::from_file(&path).expect("should parse local_storage");
+        assert_eq!(config.local_storage.root, "/var/cache/cachebolt");
+        assert_eq!(config.local_storage.max_bytes, 1048576);
+
+        let path = temp_config_path("invalid_local_config.yaml");
+        write(&path, format!("{yaml}local_storage:\n  cleanup_target_percent: 0\n")).unwrap();
+        assert!(Config::from_file(&path).is_err(), "Expected error due to a 0% cleanup target");
+    }
+
+    #[test]
+    fn test_cache_tag_settings_from_file() {
+        let yaml = r#"
+app_id: testapp
+gcs_bucket: ""
+s3_bucket: ""
+azure_container: ""
+max_concurrent_requests: 5
+downstream_base_url: http://localhost
+cache:
+  memory_threshold: 75
+latency_failover:
+  default_max_latency_ms: 200
+  path_rules: []
+storage_backend: local
+storage_backend_failures: 2
+backend_retry_interval_secs: 30
+"#;
+
+        let path = temp_config_path("cache_tags_defaults_config.yaml");
+        write(&path, yaml).unwrap();
+        let config = Config::from_file(&path).expect("should parse without cache_tags");
+        assert_eq!(config.cache_tags.headers, vec!["surrogate-key"]);
+        assert!(!config.cache_tags.strip_from_response);
+
+        let path = temp_config_path("cache_tags_config.yaml");
+        let tags = "cache_tags:\n  headers: [Surrogate-Key, Cache-Tag]\n  strip_from_response: true\n";
+        write(&path, format!("{yaml}{tags}")).unwrap();
+        let config = Config::from_file(&path).expect("should parse cache_tags");
+        assert_eq!(config.cache_tags.headers, vec!["Surrogate-Key", "Cache-Tag"]);
+        assert!(config.cache_tags.strip_from_response);
+
+        let path = temp_config_path("invalid_cache_tags_config.yaml");
+        write(&path, format!("{yaml}cache_tags:\n  headers: [\"bad header\"]\n")).unwrap();
+        assert!(Config::from_file(&path).is_err(), "Expected error due to an invalid header name");
+    }
+
+    #[test]
+    fn test_storage_tiers_from_file() {
+        let yaml = r#"
+app_id: testapp
+gcs_bucket: ""
+s3_bucket: test-s3
+azure_container: test-azure
+max_concurrent_requests: 5
+downstream_base_url: http://localhost
+cache:
+  memory_threshold: 75
+latency_failover:
+  default_max_latency_ms: 200
+  path_rules: []
+storage:
+  tiers: [local, s3]
+  write_policy: write_back
+  write_back_delay_ms: 250
+storage_backend_failures: 0
+backend_retry_interval_secs: 0
+"#;
+
+        let path = temp_config_path("storage_tiers.yaml");
+        write(&path, yaml).unwrap();
+        let config = Config::from_file(&path).expect("should parse storage tiers");
+        assert_eq!(config.storage_tiers(), vec![StorageBackend::Local, StorageBackend::S3]);
+        assert_eq!(config.storage.write_policy, WritePolicy::WriteBack);
+        assert_eq!(config.storage.write_back_delay_ms, 250);
+
+        // Every tier needs its bucket, and a tier may only appear once
+        for tiers in ["[local, gcs]", "[s3, local, s3]"] {
+            let path = temp_config_path("storage_tiers_invalid.yaml");
+            write(&path, yaml.replace("[local, s3]", tiers)).unwrap();
+            assert!(Config::from_file(&path).is_err(), "Expected {tiers} to be rejected");
+        }
+
+        // Without tiers, storage_backend is required
+        let path = temp_config_path("storage_tiers_missing.yaml");
+        write(&path, yaml.replace("[local, s3]", "[]")).unwrap();
+        assert!(Config::from_file(&path).is_err(), "Expected a config without backend to be rejected");
+    }
+
+    #[test]
+    fn test_persistence_settings_from_file() {
+        let yaml = r#"
+app_id: testapp
+gcs_bucket: ""
+s3_bucket: ""
+azure_container: ""
+max_concurrent_requests: 5
+downstream_base_url: http://localhost
+cache:
+  memory_threshold: 75
+latency_failover:
+  default_max_latency_ms: 200
+  path_rules: []
+storage_backend: local
+persistence:
+  queue_capacity: 500
+  full_policy: block
+  journal_path: ./journal/persist.log
+storage_backend_failures: 0
+backend_retry_interval_secs: 0
+"#;
+
+        let path = temp_config_path("persistence.yaml");
+        write(&path, yaml).unwrap();
+        let config = Config::from_file(&path).expect("should parse persistence settings");
+        assert_eq!(config.persistence.queue_capacity, 500);
+        assert_eq!(config.persistence.full_policy, QueueFullPolicy::Block);
+        assert_eq!(config.persistence.journal_path, "./journal/persist.log");
+        // Unset fields keep their defaults
+        assert_eq!(config.persistence.max_retries, 3);
+
+        let path = temp_config_path("persistence_invalid.yaml");
+        write(&path, yaml.replace("queue_capacity: 500", "queue_capacity: 0")).unwrap();
+        assert!(Config::from_file(&path).is_err(), "An empty queue must be rejected");
+    }
+
     #[test]
     fn test_latency_failover_default_only() {
         let yaml = r#"
@@ -139,7 +322,7 @@ backend_retry_interval_secs: 120
         let path = temp_config_path("backend_enum.yaml");
         write(&path, yaml).unwrap();
         let config = Config::from_file(&path).unwrap();
-        assert_eq!(config.storage_backend, StorageBackend::Azure);
+        assert_eq!(config.storage_backend, Some(StorageBackend::Azure));
         assert_eq!(config.storage_backend_failures, 4);
         assert_eq!(config.backend_retry_interval_secs, 120);
     }
@@ -157,24 +340,26 @@ backend_retry_interval_secs: 120
                 memory_threshold: 90,
                 refresh_percentage: 10,
                 ttl_seconds: 300,
+                ..Default::default()
             },
             latency_failover: LatencyFailover {
                 default_max_latency_ms: 200,
                 path_rules: vec![],
             },
-            storage_backend: StorageBackend::Local,
+            storage_backend: Some(StorageBackend::Local),
             storage_backend_failures: 5,
             backend_retry_interval_secs: 60,
             ignored_headers: None,
             proxy_port: 3000,
             admin_port: 3001,
+            ..Default::default()
         };
 
         CONFIG.get_or_init(|| config);
 
         let actual = CONFIG.get().unwrap();
         assert_eq!(actual.cache.memory_threshold, 90);
-        assert_eq!(actual.storage_backend, StorageBackend::Local);
+        assert_eq!(actual.storage_backend, Some(StorageBackend::Local));
         assert_eq!(actual.storage_backend_failures, 5);
         assert_eq!(actual.backend_retry_interval_secs, 60);
     }
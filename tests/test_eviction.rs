@@ -14,8 +14,7 @@
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use cachebolt::{eviction::{start_background_eviction_task, start_background_eviction_task_with}, memory::memory::{maybe_evict_if_needed, MEMORY_CACHE}};
+    use cachebolt::eviction::{start_background_eviction_task, start_background_eviction_task_with};
     use std::sync::{Arc, Mutex};
     use tokio::time::{self, Duration};
     use tokio::task;
@@ -69,7 +68,7 @@ mod tests {
             seq.remove(0)
         };
 
-        let _ = task::spawn({
+        let _handle = task::spawn({
             let triggered = triggered_clone;
             async move {
                 start_background_eviction_task_with(get_mocked);
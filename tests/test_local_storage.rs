@@ -0,0 +1,197 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use cachebolt::config::{CONFIG, Config, LocalStorageSettings, StorageBackend as BackendKind};
+    use cachebolt::memory::memory::CachedResponse;
+    use cachebolt::storage::StorageBackend;
+    use cachebolt::storage::local::{LocalBackend, build_local_cache_path, enforce_quota};
+    use ctor::ctor;
+    use once_cell::sync::Lazy;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    /// Cache root, removed with the test process.
+    static DIR: Lazy<tempfile::TempDir> = Lazy::new(|| tempfile::tempdir().unwrap());
+
+    /// Serializes tests, since most of them look at the whole directory.
+    static LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// Quota of the test app: room for about three 1 KiB entries.
+    const MAX_BYTES: u64 = 3500;
+
+    #[ctor]
+    fn init() {
+        let _ = CONFIG.set(Config {
+            app_id: "local-test".into(),
+            local_storage: LocalStorageSettings {
+                root: DIR.path().join("cache").display().to_string(),
+                max_bytes: MAX_BYTES,
+                cleanup_target_percent: 70,
+            },
+            storage_backend: Some(BackendKind::Local),
+            ..Default::default()
+        });
+    }
+
+    async fn backend() -> LocalBackend {
+        LocalBackend.init().await.unwrap();
+        LocalBackend.delete_all().await.unwrap();
+        LocalBackend
+    }
+
+    fn app_dir() -> PathBuf {
+        DIR.path().join("cache").join("local-test")
+    }
+
+    /// An entry of roughly `size` bytes on disk (random bodies do not compress).
+    fn entry(size: usize) -> CachedResponse {
+        let now = chrono::Utc::now();
+        let body: Vec<u8> = (0..size).map(|_| rand::random::<u8>()).collect();
+        CachedResponse {
+            status: 200,
+            body: Bytes::from(body),
+            headers: vec![],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
+    fn all_files(dir: &std::path::Path) -> Vec<PathBuf> {
+        let mut files = vec![];
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(all_files(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    #[tokio::test]
+    async fn test_entries_are_sharded_and_written_atomically() {
+        let _guard = LOCK.lock().await;
+        let disk = backend().await;
+
+        disk.put("k1", entry(10)).await.unwrap();
+        disk.put("k1", entry(20)).await.unwrap();
+
+        let path = build_local_cache_path("k1").unwrap();
+        let shard = path.strip_prefix(app_dir()).unwrap();
+        let levels: Vec<_> = shard.iter().map(|c| c.to_str().unwrap().len()).collect();
+        assert_eq!(levels, vec![2, 2, "k1.gz".len()], "two shard levels: {shard:?}");
+
+        // No temporary file is left next to the entry
+        assert_eq!(all_files(&app_dir()), vec![path]);
+        assert_eq!(disk.get("k1").await.unwrap().unwrap().body.len(), 20);
+
+        let listed = disk.list().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].key, "k1");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_keys_are_rejected() {
+        let _guard = LOCK.lock().await;
+        let disk = backend().await;
+
+        for key in ["../escape", "a/b", ".hidden", ""] {
+            assert!(disk.put(key, entry(1)).await.is_err(), "{key:?} is not a file name");
+        }
+        assert!(all_files(&app_dir()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_flat_layout_files_are_still_served() {
+        let _guard = LOCK.lock().await;
+        let disk = backend().await;
+
+        // A file written by the former flat layout
+        disk.put("old", entry(10)).await.unwrap();
+        let flat = app_dir().join("old.gz");
+        fs::rename(build_local_cache_path("old").unwrap(), &flat).unwrap();
+
+        assert!(disk.get("old").await.unwrap().is_some());
+        assert_eq!(disk.list().await.unwrap().len(), 1);
+
+        // Rewriting the key moves it to its shard
+        disk.put("old", entry(10)).await.unwrap();
+        assert!(!flat.exists());
+        assert_eq!(disk.list().await.unwrap().len(), 1);
+
+        assert!(disk.delete("old").await.unwrap());
+        assert!(!disk.delete("old").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_stale_temporary_files_are_removed_on_init() {
+        let _guard = LOCK.lock().await;
+        let disk = backend().await;
+
+        let path = build_local_cache_path("crashed").unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let stale = path.with_file_name(".crashed.1-0.tmp");
+        let fresh = path.with_file_name(".crashed.1-1.tmp");
+        fs::write(&stale, b"partial").unwrap();
+        fs::write(&fresh, b"in progress").unwrap();
+        let file = fs::File::options().write(true).open(&stale).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(7200)).unwrap();
+
+        disk.init().await.unwrap();
+        assert!(!stale.exists());
+        assert!(fresh.exists(), "a write may still be in progress");
+        assert!(disk.list().await.unwrap().is_empty(), "temporary files are not entries");
+    }
+
+    #[tokio::test]
+    async fn test_quota_evicts_least_recently_read_entries() {
+        let _guard = LOCK.lock().await;
+        let disk = backend().await;
+
+        for key in ["a", "b", "c"] {
+            disk.put(key, entry(1000)).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        // "a" becomes the most recently read entry
+        assert!(disk.get("a").await.unwrap().is_some());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // Going over the quota starts a cleanup in the background, which
+        // brings usage under 70% of the quota, oldest reads first
+        disk.put("d", entry(1000)).await.unwrap();
+        for _ in 0..100 {
+            if disk.list().await.unwrap().len() <= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let mut keys: Vec<_> = disk.list().await.unwrap().into_iter().map(|o| o.key).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "d"]);
+        assert!(disk.usage().await.unwrap().bytes <= MAX_BYTES * 70 / 100);
+        assert_eq!(enforce_quota().await.unwrap(), 0, "already under the quota");
+    }
+}
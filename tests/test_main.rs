@@ -14,16 +14,8 @@
 
 #[cfg(test)]
 mod tests {
-    use super::*;
     use std::fs;
-    use std::sync::Once;
     use cachebolt::config::{Config, StorageBackend};
-    
-    struct Args {
-        config: String,
-    }
-
-    static INIT: Once = Once::new();
 
     fn write_temp_config(contents: &str, filename: &str) -> String {
         let path = format!("tests/{}", filename);
@@ -51,6 +43,8 @@ latency_failover:
     - pattern: "^/api/test"
       max_latency_ms: 100
 storage_backend: s3
+storage_backend_failures: 0
+backend_retry_interval_secs: 0
 "#;
         let path = write_temp_config(yaml, "valid_config.yaml");
         let cfg = Config::from_file(&path).expect("Config should parse");
@@ -58,7 +52,7 @@ storage_backend: s3
         assert_eq!(cfg.app_id, "test-app");
         assert_eq!(cfg.cache.memory_threshold, 80);
         assert_eq!(cfg.latency_failover.path_rules.len(), 1);
-        assert_eq!(cfg.storage_backend, StorageBackend::S3);
+        assert_eq!(cfg.storage_backend, Some(StorageBackend::S3));
     }
 
     #[test]
@@ -78,6 +72,8 @@ latency_failover:
   default_max_latency_ms: 100
   path_rules: []
 storage_backend: gcs
+storage_backend_failures: 0
+backend_retry_interval_secs: 0
 "#;
         let path = write_temp_config(yaml, "invalid_config.yaml");
         let result = Config::from_file(&path);
@@ -14,10 +14,9 @@
 
 #[cfg(test)]
 mod tests {
-    use super::*;
     use cachebolt::{
         config::{CacheSettings, Config, LatencyFailover, MaxLatencyRule, StorageBackend, CONFIG},
-        memory::memory::{get_from_memory, get_memory_usage_kib, load_into_memory, maybe_evict_if_needed, CachedResponse, MEMORY_CACHE},
+        memory::memory::{get_from_memory, get_memory_usage_kib, load_into_memory, maybe_evict_if_needed, purge_expired, CachedResponse, MEMORY_CACHE},
     };
     use bytes::Bytes;
     use ctor::ctor;
@@ -39,6 +38,7 @@ mod tests {
                 memory_threshold: threshold,
                 refresh_percentage: 10,
                 ttl_seconds: 60,
+                ..Default::default()
             },
             latency_failover: LatencyFailover {
                 default_max_latency_ms: 200,
@@ -47,12 +47,13 @@ mod tests {
                     max_latency_ms: 100,
                 }],
             },
-            storage_backend: StorageBackend::Local,
+            storage_backend: Some(StorageBackend::Local),
             storage_backend_failures: 0,
             backend_retry_interval_secs: 0,
             ignored_headers: None,
             proxy_port: 3000,
             admin_port: 3001,
+            ..Default::default()
         };
 
         // Set config only once
@@ -64,9 +65,17 @@ mod tests {
         setup_config(90);
         let key = "test-key".to_string();
         let value = CachedResponse {
+            status: 200,
             body: Bytes::from("hello world"),
             headers: vec![("Content-Type".into(), "text/plain".into())],
             inserted_at: chrono::Utc::now(),
+            expires_at: chrono::Utc::now() + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
         };
 
         load_into_memory(vec![(key.clone(), value.clone())]).await;
@@ -83,17 +92,24 @@ mod tests {
         setup_config(100); // High threshold to avoid eviction
         let key = "low-mem".to_string();
         let value = CachedResponse {
+            status: 200,
             body: Bytes::from("safe"),
             headers: vec![("x".into(), "y".into())],
             inserted_at: chrono::Utc::now(),
+            expires_at: chrono::Utc::now() + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
         };
 
         load_into_memory(vec![(key.clone(), value)]).await;
 
-        let mut cache = MEMORY_CACHE.write().await;
-        let initial_len = cache.len();
-        maybe_evict_if_needed(&mut cache).await;
-        assert_eq!(cache.len(), initial_len);
+        let initial_len = MEMORY_CACHE.len().await;
+        maybe_evict_if_needed(&MEMORY_CACHE).await;
+        assert_eq!(MEMORY_CACHE.len().await, initial_len);
     }
 
     #[tokio::test]
@@ -111,6 +127,103 @@ mod tests {
         assert!(total >= used, "Total memory should be >= used memory");
     }
 
+    #[test]
+    fn test_cached_response_freshness() {
+        let now = chrono::Utc::now();
+        let fresh = CachedResponse {
+            status: 200,
+            body: Bytes::from("fresh"),
+            headers: vec![],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        };
+        let stale = CachedResponse {
+            expires_at: now - chrono::Duration::seconds(1),
+            ..fresh.clone()
+        };
+
+        assert!(fresh.is_fresh(), "Entry within its lifetime should be fresh");
+        assert!(!stale.is_fresh(), "Entry past expires_at should be stale");
+    }
+
+    #[tokio::test]
+    async fn test_stale_entries_follow_expired_policy() {
+        setup_config(90);
+        let now = chrono::Utc::now();
+        let stale = CachedResponse {
+            status: 200,
+            body: Bytes::from("stale"),
+            headers: vec![],
+            inserted_at: now - chrono::Duration::seconds(120),
+            expires_at: now - chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        };
+        let must_revalidate = CachedResponse {
+            must_revalidate: true,
+            ..stale.clone()
+        };
+
+        load_into_memory(vec![
+            ("stale-ok".to_string(), stale),
+            ("stale-revalidate".to_string(), must_revalidate.clone()),
+        ])
+        .await;
+
+        // Default policy is serve_stale_on_error without a max staleness
+        assert!(get_from_memory("stale-ok").await.is_some());
+        assert!(get_from_memory("stale-revalidate").await.is_none());
+
+        load_into_memory(vec![("stale-sweep".to_string(), must_revalidate)]).await;
+        assert!(purge_expired().await >= 1);
+        assert!(MEMORY_CACHE.get("stale-sweep").await.is_none());
+        assert!(MEMORY_CACHE.get("stale-ok").await.is_some());
+    }
+
+    #[test]
+    fn test_stale_windows() {
+        let now = chrono::Utc::now();
+        let stale = CachedResponse {
+            status: 200,
+            body: Bytes::from("stale"),
+            headers: vec![],
+            inserted_at: now - chrono::Duration::seconds(60),
+            expires_at: now - chrono::Duration::seconds(10),
+            must_revalidate: false,
+            stale_while_revalidate: 30,
+            stale_if_error: 5,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        };
+
+        assert!(stale.can_serve_while_revalidating());
+        assert!(!stale.can_serve_on_error(), "10s past expiry is outside a 5s window");
+
+        let revalidate = CachedResponse {
+            must_revalidate: true,
+            ..stale.clone()
+        };
+        assert!(!revalidate.can_serve_while_revalidating());
+
+        let no_window = CachedResponse {
+            expires_at: now + chrono::Duration::seconds(10),
+            stale_if_error: 0,
+            ..stale
+        };
+        assert!(!no_window.can_serve_on_error(), "stale-if-error is opt-in");
+    }
+
     #[tokio::test]
     async fn test_bulk_load_into_memory() {
         setup_config(95);
@@ -119,17 +232,33 @@ mod tests {
             (
                 "key-1".to_string(),
                 CachedResponse {
+                    status: 200,
                     body: Bytes::from("value-1"),
                     headers: vec![("a".into(), "1".into())],
                     inserted_at: chrono::Utc::now(),
+                    expires_at: chrono::Utc::now() + chrono::Duration::seconds(60),
+                    must_revalidate: false,
+                    stale_while_revalidate: 0,
+                    stale_if_error: 0,
+                    key_source: String::new(),
+                    tags: vec![],
+                    purged: false,
                 },
             ),
             (
                 "key-2".to_string(),
                 CachedResponse {
+                    status: 200,
                     body: Bytes::from("value-2"),
                     headers: vec![("b".into(), "2".into())],
                     inserted_at: chrono::Utc::now(),
+                    expires_at: chrono::Utc::now() + chrono::Duration::seconds(60),
+                    must_revalidate: false,
+                    stale_while_revalidate: 0,
+                    stale_if_error: 0,
+                    key_source: String::new(),
+                    tags: vec![],
+                    purged: false,
                 },
             ),
         ];
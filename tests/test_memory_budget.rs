@@ -0,0 +1,157 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use cachebolt::{
+        config::{CONFIG, CacheSettings, Config},
+        memory::memory::{CachedResponse, MemoryCache},
+    };
+    use ctor::ctor;
+
+    /// Room for three entries built by `entry(BODY)`.
+    const BODY: usize = 1000;
+
+    fn entry(body_len: usize) -> CachedResponse {
+        CachedResponse {
+            status: 200,
+            body: Bytes::from(vec![b'x'; body_len]),
+            headers: vec![("content-type".into(), "text/plain".into())],
+            inserted_at: chrono::Utc::now(),
+            expires_at: chrono::Utc::now() + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: "/item|".into(),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
+    fn weight(key: &str, body_len: usize) -> usize {
+        key.len() + entry(body_len).size_bytes()
+    }
+
+    #[ctor]
+    fn init_config() {
+        let _ = CONFIG.set(Config {
+            cache: CacheSettings {
+                memory_threshold: 100,
+                max_memory_bytes: 3 * weight("k0", BODY) + 10,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_size_accounts_for_body_and_headers() {
+        let small = entry(10);
+        let mut large = entry(10);
+        large.body = Bytes::from(vec![b'x'; 110]);
+        large.headers.push(("etag".into(), "\"v1\"".into()));
+        assert!(large.size_bytes() >= small.size_bytes() + 100 + "etag".len() + 4);
+    }
+
+    #[tokio::test]
+    async fn test_bytes_tracked_across_put_replace_and_pop() {
+        let cache = MemoryCache::new();
+        cache.put("k0".into(), entry(BODY)).await;
+        cache.put("k1".into(), entry(10)).await;
+        assert_eq!(cache.bytes().await, weight("k0", BODY) + weight("k1", 10));
+
+        // Replacing an entry swaps its weight
+        cache.put("k0".into(), entry(20)).await;
+        assert_eq!(cache.bytes().await, weight("k0", 20) + weight("k1", 10));
+
+        cache.pop("k1").await;
+        assert_eq!(cache.bytes().await, weight("k0", 20));
+
+        cache.clear().await;
+        assert_eq!(cache.bytes().await, 0);
+        assert_eq!(cache.len().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_frequent_entries_survive_one_off_inserts() {
+        let cache = MemoryCache::new();
+        cache.put("k0".into(), entry(BODY)).await;
+        cache.put("k1".into(), entry(BODY)).await;
+        cache.put("k2".into(), entry(BODY)).await;
+        assert_eq!(cache.len().await, 3);
+
+        // k0 is requested often, the new keys only once
+        for _ in 0..10 {
+            cache.get("k0").await;
+        }
+        for i in 3..10 {
+            cache.put(format!("k{i}"), entry(BODY)).await;
+        }
+
+        assert!(cache.len().await <= 3);
+        assert!(cache.get("k0").await.is_some(), "frequent entry kept");
+        assert!(cache.bytes().await <= CONFIG.get().unwrap().cache.max_memory_bytes);
+    }
+
+    #[tokio::test]
+    async fn test_entry_larger_than_budget_not_cached() {
+        let cache = MemoryCache::new();
+        cache.put("small".into(), entry(10)).await;
+        cache.put("huge".into(), entry(4 * BODY)).await;
+
+        assert!(cache.get("huge").await.is_none());
+        assert!(cache.get("small").await.is_some(), "existing entries are kept");
+        assert_eq!(cache.bytes().await, weight("small", 10));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_readers_and_writers() {
+        let cache = std::sync::Arc::new(MemoryCache::new());
+        let tasks = (0..8)
+            .map(|i| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    let key = format!("c{}", i % 2);
+                    for _ in 0..100 {
+                        cache.put(key.clone(), entry(10)).await;
+                        assert!(cache.get(&key).await.is_some());
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(cache.len().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_keys_oldest_first_orders_by_insertion_time() {
+        let cache = MemoryCache::new();
+        let now = chrono::Utc::now();
+        for (key, age) in [("middle", 20), ("newest", 10), ("oldest", 30)] {
+            let aged = CachedResponse {
+                inserted_at: now - chrono::Duration::seconds(age),
+                ..entry(10)
+            };
+            cache.put(key.into(), aged).await;
+        }
+
+        let keys = cache.keys_oldest_first();
+        let keys = keys.iter().map(|k| k.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["oldest", "middle", "newest"]);
+    }
+}
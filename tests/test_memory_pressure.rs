@@ -0,0 +1,99 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use cachebolt::config::MemorySource;
+    use cachebolt::memory::pressure::{cgroup_usage, percent, read_usage};
+    use std::fs;
+    use tempfile::TempDir;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn cgroup_v2(max: &str, current: u64, inactive_file: u64) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("memory.max"), format!("{max}\n")).unwrap();
+        fs::write(dir.path().join("memory.current"), format!("{current}\n")).unwrap();
+        fs::write(
+            dir.path().join("memory.stat"),
+            format!("anon 1234\nactive_file 10\ninactive_file {inactive_file}\n"),
+        )
+        .unwrap();
+        dir
+    }
+
+    fn cgroup_v1(limit: u64, usage: u64, inactive_file: u64) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let memory = dir.path().join("memory");
+        fs::create_dir(&memory).unwrap();
+        fs::write(memory.join("memory.limit_in_bytes"), format!("{limit}\n")).unwrap();
+        fs::write(memory.join("memory.usage_in_bytes"), format!("{usage}\n")).unwrap();
+        fs::write(
+            memory.join("memory.stat"),
+            format!("cache 1\ntotal_inactive_file {inactive_file}\n"),
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_cgroup_v2_working_set_against_limit() {
+        let dir = cgroup_v2(&(512 * MIB).to_string(), 300 * MIB, 44 * MIB);
+        let usage = cgroup_usage(dir.path()).expect("v2 limit detected");
+        assert_eq!(usage.source, MemorySource::Cgroup);
+        assert_eq!(usage.total_kib, 512 * 1024);
+        assert_eq!(usage.used_kib, 256 * 1024);
+        assert_eq!(usage.percent(), 50);
+    }
+
+    #[test]
+    fn test_cgroup_v1_working_set_against_limit() {
+        let dir = cgroup_v1(256 * MIB, 200 * MIB, 8 * MIB);
+        let usage = cgroup_usage(dir.path()).expect("v1 limit detected");
+        assert_eq!(usage.total_kib, 256 * 1024);
+        assert_eq!(usage.used_kib, 192 * 1024);
+    }
+
+    #[test]
+    fn test_unlimited_cgroup_falls_back_to_host() {
+        let v2 = cgroup_v2("max", 300 * MIB, 0);
+        assert!(cgroup_usage(v2.path()).is_none());
+        assert_eq!(read_usage(MemorySource::Auto, v2.path()).source, MemorySource::System);
+
+        // cgroup v1 reports "no limit" as a huge page-aligned number
+        let v1 = cgroup_v1(9_223_372_036_854_771_712, 300 * MIB, 0);
+        assert!(cgroup_usage(v1.path()).is_none());
+
+        let empty = TempDir::new().unwrap();
+        assert_eq!(read_usage(MemorySource::Cgroup, empty.path()).source, MemorySource::System);
+    }
+
+    #[test]
+    fn test_source_selection() {
+        let dir = cgroup_v2(&(512 * MIB).to_string(), 300 * MIB, 0);
+        assert_eq!(read_usage(MemorySource::Auto, dir.path()).source, MemorySource::Cgroup);
+        assert_eq!(read_usage(MemorySource::System, dir.path()).source, MemorySource::System);
+
+        let process = read_usage(MemorySource::Process, dir.path());
+        assert_eq!(process.source, MemorySource::Process);
+        assert_eq!(process.total_kib, 512 * 1024, "RSS is measured against the cgroup limit");
+        assert!(process.used_kib > 0);
+    }
+
+    #[test]
+    fn test_percent_of_zero_total_does_not_panic() {
+        assert_eq!(percent(0, 0), 0);
+        assert_eq!(percent(512, 1024), 50);
+    }
+}
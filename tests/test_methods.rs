@@ -0,0 +1,142 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, response::IntoResponse, routing::any};
+    use cachebolt::{
+        config::{
+            CONFIG, CacheMode, CacheSettings, Config, LatencyFailover, PostCacheRule,
+            PostCaching, StorageBackend,
+        },
+        proxy::proxy_handler,
+        rules::methods::is_cacheable_method,
+    };
+    use hyper::{Body, Method, Request};
+    use std::net::TcpListener;
+    use std::sync::Once;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static UPSTREAM_HITS: AtomicUsize = AtomicUsize::new(0);
+    static INIT: Once = Once::new();
+
+    /// Starts a mock downstream that echoes method and body, and points CONFIG at it.
+    fn setup() {
+        INIT.call_once(|| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(async move {
+                    let app = Router::new().route(
+                        "/*path",
+                        any(|method: Method, body: String| async move {
+                            UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                            ([("connection", "close")], format!("{method}:{body}"))
+                                .into_response()
+                        }),
+                    );
+                    axum::Server::from_tcp(listener)
+                        .unwrap()
+                        .serve(app.into_make_service())
+                        .await
+                        .unwrap();
+                });
+            });
+
+            let _ = CONFIG.set(Config {
+                app_id: "methods".into(),
+                max_concurrent_requests: 10,
+                downstream_base_url: format!("http://127.0.0.1:{port}"),
+                cache: CacheSettings {
+                    memory_threshold: 100,
+                    ttl_seconds: 60,
+                    mode: CacheMode::ReadThrough,
+                    ..Default::default()
+                },
+                latency_failover: LatencyFailover {
+                    default_max_latency_ms: 5000,
+                    path_rules: vec![],
+                },
+                post_caching: PostCaching {
+                    max_body_bytes: 16,
+                    path_rules: vec![PostCacheRule {
+                        pattern: "^/graphql".into(),
+                    }],
+                },
+                storage_backend: Some(StorageBackend::Local),
+                ..Default::default()
+            });
+        });
+    }
+
+    async fn send(method: Method, uri: &str, body: &'static str) -> String {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body))
+            .unwrap();
+        let resp = proxy_handler(req).await.into_response();
+        let bytes = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+
+    #[test]
+    fn test_cacheable_methods() {
+        setup();
+        assert!(is_cacheable_method(&Method::GET, "/anything"));
+        assert!(is_cacheable_method(&Method::HEAD, "/anything"));
+        assert!(is_cacheable_method(&Method::POST, "/graphql"));
+        assert!(!is_cacheable_method(&Method::POST, "/orders"));
+        assert!(!is_cacheable_method(&Method::PUT, "/graphql"));
+        assert!(!is_cacheable_method(&Method::DELETE, "/graphql"));
+        assert!(!is_cacheable_method(&Method::PATCH, "/graphql"));
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_forwarded_and_never_cached() {
+        setup();
+        for method in [Method::PUT, Method::PATCH, Method::DELETE, Method::POST] {
+            let before = UPSTREAM_HITS.load(Ordering::SeqCst);
+            let expected = format!("{method}:payload");
+            assert_eq!(send(method.clone(), "/orders/1", "payload").await, expected);
+            assert_eq!(send(method.clone(), "/orders/1", "payload").await, expected);
+            assert_eq!(UPSTREAM_HITS.load(Ordering::SeqCst), before + 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_opted_in_post_is_cached_by_body() {
+        setup();
+        let before = UPSTREAM_HITS.load(Ordering::SeqCst);
+        assert_eq!(send(Method::POST, "/graphql", "{q:1}").await, "POST:{q:1}");
+        assert_eq!(send(Method::POST, "/graphql", "{q:1}").await, "POST:{q:1}");
+        assert_eq!(UPSTREAM_HITS.load(Ordering::SeqCst), before + 1);
+
+        // A different body is a different cache key
+        assert_eq!(send(Method::POST, "/graphql", "{q:2}").await, "POST:{q:2}");
+        assert_eq!(UPSTREAM_HITS.load(Ordering::SeqCst), before + 2);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_post_body_is_not_cached() {
+        setup();
+        let body = "{query: 'a very long query'}";
+        let before = UPSTREAM_HITS.load(Ordering::SeqCst);
+        assert_eq!(send(Method::POST, "/graphql/big", body).await, format!("POST:{body}"));
+        assert_eq!(send(Method::POST, "/graphql/big", body).await, format!("POST:{body}"));
+        assert_eq!(UPSTREAM_HITS.load(Ordering::SeqCst), before + 2);
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use cachebolt::config::{CONFIG, Config, PersistenceSettings, QueueFullPolicy, WritePolicy};
+    use cachebolt::memory::memory::CachedResponse;
+    use cachebolt::storage::queue::{PersistQueue, read_journal};
+    use cachebolt::storage::tiered::TieredStorage;
+    use cachebolt::storage::{StorageBackend, StorageResult, StoredObject};
+    use cachebolt::test_util::install_backend;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    /// In-memory backend that fails its first `failures` writes and, when
+    /// gated, holds every write until a permit is added.
+    struct FakeBackend {
+        entries: Mutex<HashMap<String, CachedResponse>>,
+        failures: AtomicUsize,
+        attempts: AtomicUsize,
+        gate: Option<Semaphore>,
+    }
+
+    impl FakeBackend {
+        fn new(failures: usize) -> Arc<Self> {
+            Arc::new(FakeBackend {
+                entries: Mutex::new(HashMap::new()),
+                failures: AtomicUsize::new(failures),
+                attempts: AtomicUsize::new(0),
+                gate: None,
+            })
+        }
+
+        fn gated() -> Arc<Self> {
+            Arc::new(FakeBackend {
+                entries: Mutex::new(HashMap::new()),
+                failures: AtomicUsize::new(0),
+                attempts: AtomicUsize::new(0),
+                gate: Some(Semaphore::new(0)),
+            })
+        }
+
+        fn body(&self, key: &str) -> Option<Bytes> {
+            self.entries.lock().unwrap().get(key).map(|e| e.body.clone())
+        }
+    }
+
+    #[async_trait]
+    impl StorageBackend for FakeBackend {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+            Ok(self.entries.lock().unwrap().get(key).cloned())
+        }
+
+        async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            if let Some(gate) = &self.gate {
+                gate.acquire().await?.forget();
+            }
+            let failing = self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failing {
+                return Err("connection refused".into());
+            }
+            self.entries.lock().unwrap().insert(key.to_string(), entry);
+            Ok(())
+        }
+
+        async fn delete(&self, key: &str) -> StorageResult<bool> {
+            Ok(self.entries.lock().unwrap().remove(key).is_some())
+        }
+
+        async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+            Ok(vec![])
+        }
+
+        async fn delete_all(&self) -> StorageResult<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn health_check(&self) -> StorageResult<()> {
+            Ok(())
+        }
+    }
+
+    fn entry(body: &str) -> CachedResponse {
+        let now = chrono::Utc::now();
+        CachedResponse {
+            status: 200,
+            body: Bytes::from(body.to_string()),
+            headers: vec![("content-type".into(), "text/plain".into())],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
+    fn settings(journal: Option<&Path>) -> PersistenceSettings {
+        PersistenceSettings {
+            max_retries: 3,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 4,
+            journal_path: journal.map(|p| p.display().to_string()).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    const FLUSH: Duration = Duration::from_secs(2);
+
+    #[tokio::test]
+    async fn test_failed_writes_are_retried() {
+        let backend = FakeBackend::new(2);
+        let queue = PersistQueue::start(backend.clone(), settings(None));
+
+        queue.enqueue("k".into(), entry("v1")).await;
+        assert!(queue.flush(FLUSH).await);
+
+        assert_eq!(backend.body("k"), Some(Bytes::from("v1")));
+        assert_eq!(backend.attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_retries_without_journal_drop_the_entry() {
+        let backend = FakeBackend::new(usize::MAX);
+        let queue = PersistQueue::start(backend.clone(), settings(None));
+
+        queue.enqueue("k".into(), entry("v1")).await;
+        assert!(queue.flush(FLUSH).await);
+
+        assert_eq!(backend.body("k"), None);
+        assert_eq!(backend.attempts.load(Ordering::SeqCst), 4, "first try plus 3 retries");
+    }
+
+    #[tokio::test]
+    async fn test_spilled_entries_are_replayed_on_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal").join("persist.log");
+
+        let down = FakeBackend::new(usize::MAX);
+        let queue = PersistQueue::start(down.clone(), settings(Some(&journal)));
+        queue.enqueue("a".into(), entry("first")).await;
+        queue.enqueue("b".into(), entry("second")).await;
+        assert!(queue.flush(FLUSH).await);
+
+        let spilled = read_journal(&journal).await.unwrap();
+        let keys: Vec<_> = spilled.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["a", "b"]);
+
+        // Next run: the journal is queued again and removed
+        let up = FakeBackend::new(0);
+        let queue = PersistQueue::start(up.clone(), settings(Some(&journal)));
+        assert_eq!(queue.replay_journal().await, 2);
+        assert!(queue.flush(FLUSH).await);
+
+        assert_eq!(up.body("a"), Some(Bytes::from("first")));
+        assert_eq!(up.body("b"), Some(Bytes::from("second")));
+        assert!(!journal.exists());
+        assert_eq!(queue.replay_journal().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_queued_entries_survive_a_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("persist.log");
+        let wal = dir.path().join("persist.log.wal");
+
+        // The backend never answers and the process dies with both entries queued
+        let hung = FakeBackend::gated();
+        let crashed = PersistQueue::start(hung, settings(Some(&journal)));
+        crashed.enqueue("a".into(), entry("first")).await;
+        crashed.enqueue("b".into(), entry("second")).await;
+        assert!(!journal.exists());
+        assert_eq!(read_journal(&wal).await.unwrap().len(), 2);
+
+        // Next run: the write-ahead log is moved into the journal and replayed
+        let up = FakeBackend::new(0);
+        let queue = PersistQueue::start(up.clone(), settings(Some(&journal)));
+        assert_eq!(queue.replay_journal().await, 2);
+        assert!(queue.flush(FLUSH).await);
+
+        assert_eq!(up.body("a"), Some(Bytes::from("first")));
+        assert_eq!(up.body("b"), Some(Bytes::from("second")));
+        assert!(!wal.exists(), "the log is cleared once the queue drains");
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_back_is_retried_by_the_queue() {
+        CONFIG
+            .set(Config {
+                persistence: settings(None),
+                ..Default::default()
+            })
+            .ok();
+        assert!(install_backend(FakeBackend::new(0)));
+
+        let (upper, lower) = (FakeBackend::new(0), FakeBackend::new(1));
+        let storage = TieredStorage::new(
+            vec![upper.clone(), lower.clone()],
+            WritePolicy::WriteBack,
+            Duration::from_millis(10),
+        );
+        storage.put("k", entry("v1")).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(upper.body("k"), Some(Bytes::from("v1")));
+        assert_eq!(lower.body("k"), Some(Bytes::from("v1")));
+        assert_eq!(lower.attempts.load(Ordering::SeqCst), 2, "the failed write-back is retried");
+    }
+
+    #[tokio::test]
+    async fn test_truncated_journal_keeps_complete_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("persist.log");
+
+        let queue = PersistQueue::start(FakeBackend::new(usize::MAX), settings(Some(&journal)));
+        queue.enqueue("a".into(), entry("first")).await;
+        queue.enqueue("b".into(), entry("second")).await;
+        assert!(queue.flush(FLUSH).await);
+
+        // Simulate a crash in the middle of the last append
+        let raw = std::fs::read(&journal).unwrap();
+        std::fs::write(&journal, &raw[..raw.len() - 3]).unwrap();
+
+        let spilled = read_journal(&journal).await.unwrap();
+        assert_eq!(spilled.len(), 1);
+        assert_eq!(spilled[0].1.body, Bytes::from("first"));
+    }
+
+    #[tokio::test]
+    async fn test_drop_policy_spills_when_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("persist.log");
+        let backend = FakeBackend::gated();
+        let queue = PersistQueue::start(
+            backend.clone(),
+            PersistenceSettings {
+                queue_capacity: 1,
+                full_policy: QueueFullPolicy::Drop,
+                ..settings(Some(&journal))
+            },
+        );
+
+        // The worker holds "a", "b" waits in the queue, "c" does not fit
+        queue.enqueue("a".into(), entry("a")).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        queue.enqueue("b".into(), entry("b")).await;
+        tokio::time::timeout(Duration::from_millis(100), queue.enqueue("c".into(), entry("c")))
+            .await
+            .expect("drop policy never waits");
+
+        let spilled = read_journal(&journal).await.unwrap();
+        assert_eq!(spilled.len(), 1);
+        assert_eq!(spilled[0].0, "c");
+
+        backend.gate.as_ref().unwrap().add_permits(2);
+        assert!(queue.flush(FLUSH).await);
+        assert!(backend.body("a").is_some() && backend.body("b").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_waits_for_room() {
+        let backend = FakeBackend::gated();
+        let queue = PersistQueue::start(
+            backend.clone(),
+            PersistenceSettings {
+                queue_capacity: 1,
+                full_policy: QueueFullPolicy::Block,
+                ..settings(None)
+            },
+        );
+
+        queue.enqueue("a".into(), entry("a")).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        queue.enqueue("b".into(), entry("b")).await;
+        let blocked =
+            tokio::time::timeout(Duration::from_millis(100), queue.enqueue("c".into(), entry("c"))).await;
+        assert!(blocked.is_err(), "block policy waits while the queue is full");
+
+        backend.gate.as_ref().unwrap().add_permits(3);
+        queue.enqueue("c".into(), entry("c")).await;
+        assert!(queue.flush(FLUSH).await);
+        assert!(backend.body("c").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_flush_timeout_spills_remaining_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("persist.log");
+        let backend = FakeBackend::gated();
+        let queue = PersistQueue::start(backend.clone(), settings(Some(&journal)));
+
+        queue.enqueue("a".into(), entry("a")).await;
+        queue.enqueue("b".into(), entry("b")).await;
+
+        // The backend never answers: both the in-flight and the queued entry are spilled
+        assert!(!queue.flush(Duration::from_millis(100)).await);
+        assert_eq!(queue.depth(), 0);
+
+        let mut keys: Vec<_> = read_journal(&journal).await.unwrap().into_iter().map(|(k, _)| k).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b"]);
+    }
+}
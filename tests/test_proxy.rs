@@ -14,19 +14,22 @@
 
 #[cfg(test)]
 mod tests {
-    use super::*;
     use axum::response::IntoResponse;
     use bytes::Bytes;
     use cachebolt::{
         config::{CONFIG, StorageBackend},
+        memory::memory::{CachedResponse, load_into_memory},
         proxy::{
             MAX_CONCURRENT_REQUESTS, SEMAPHORE, build_response, forward_request, hash_uri,
             proxy_handler, try_cache,
         },
     };
-    use hyper::{Body, Client, Request, Response, body::to_bytes};
+    use hyper::{Body, Request, body::to_bytes};
     use std::sync::Arc;
-    use tokio::sync::{Semaphore, mpsc};
+    use tokio::sync::{Mutex, Semaphore};
+
+    /// Serializes the tests that hold permits of the shared semaphore.
+    static SEMAPHORE_LOCK: Mutex<()> = Mutex::const_new(());
 
     #[tokio::test]
     async fn test_hash_uri_consistency() {
@@ -77,7 +80,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_concurrency_semaphore_limit_blocks() {
-        let original_limit = *MAX_CONCURRENT_REQUESTS;
+        let _original_limit = *MAX_CONCURRENT_REQUESTS;
         let semaphore = Arc::new(Semaphore::new(1));
 
         let permit1 = semaphore
@@ -110,17 +113,19 @@ mod tests {
                 memory_threshold: 90,
                 refresh_percentage: 10,
                 ttl_seconds: 300,
+                ..Default::default()
             },
             latency_failover: cachebolt::config::LatencyFailover {
                 default_max_latency_ms: 1000,
                 path_rules: vec![],
             },
-            storage_backend: StorageBackend::Local,
+            storage_backend: Some(StorageBackend::Local),
             storage_backend_failures: 0,
             backend_retry_interval_secs: 0,
             ignored_headers: None,
             proxy_port: 3000,
-            admin_port: 3001
+            admin_port: 3001,
+            ..Default::default()
         });
 
         let dummy_request = Request::builder()
@@ -135,8 +140,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_semaphore_enforces_limit() {
+        let _guard = SEMAPHORE_LOCK.lock().await;
         // Intenta adquirir más permisos de los permitidos
-        let permits = *MAX_CONCURRENT_REQUESTS + 1;
+        let _permits = *MAX_CONCURRENT_REQUESTS + 1;
         let mut acquired = Vec::new();
 
         for _ in 0..*MAX_CONCURRENT_REQUESTS {
@@ -167,17 +173,19 @@ mod tests {
                 memory_threshold: 90,
                 refresh_percentage: 10,
                 ttl_seconds: 300,
+                ..Default::default()
             },
             latency_failover: cachebolt::config::LatencyFailover {
                 default_max_latency_ms: 1000,
                 path_rules: vec![],
             },
-            storage_backend: StorageBackend::Local,
+            storage_backend: Some(StorageBackend::Local),
             storage_backend_failures: 0,
             backend_retry_interval_secs: 0,
             ignored_headers: None,
             proxy_port: 3000,
-            admin_port: 3001
+            admin_port: 3001,
+            ..Default::default()
         });
 
         let req = Request::builder()
@@ -192,6 +200,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_proxy_handler_concurrency_full_and_no_cache() {
+        let _guard = SEMAPHORE_LOCK.lock().await;
         let _ = CONFIG.set(cachebolt::config::Config {
             app_id: "x".into(),
             gcs_bucket: "".into(),
@@ -203,17 +212,19 @@ mod tests {
                 memory_threshold: 90,
                 refresh_percentage: 10,
                 ttl_seconds: 300,
+                ..Default::default()
             },
             latency_failover: cachebolt::config::LatencyFailover {
                 default_max_latency_ms: 1000,
                 path_rules: vec![],
             },
-            storage_backend: StorageBackend::Local,
+            storage_backend: Some(StorageBackend::Local),
             storage_backend_failures: 0,
             backend_retry_interval_secs: 0,
             ignored_headers: None,
             proxy_port: 3000,
-            admin_port: 3001
+            admin_port: 3001,
+            ..Default::default()
         });
 
         // Saturar manualmente
@@ -241,4 +252,49 @@ mod tests {
             body_str
         );
     }
+
+    #[tokio::test]
+    async fn test_concurrency_fallback_skips_soft_purged_entries() {
+        let _guard = SEMAPHORE_LOCK.lock().await;
+        let _ = CONFIG.set(cachebolt::config::Config {
+            app_id: "x".into(),
+            max_concurrent_requests: 1,
+            downstream_base_url: "http://127.0.0.1:9999".into(),
+            storage_backend: Some(StorageBackend::Local),
+            ..Default::default()
+        });
+
+        // Saturate every permit, whatever limit the semaphore was built with
+        let available = SEMAPHORE.available_permits() as u32;
+        let _permits = SEMAPHORE
+            .clone()
+            .try_acquire_many_owned(available)
+            .expect("should acquire");
+
+        // Under load, soft-purged entries are not served; fresh ones are
+        let now = chrono::Utc::now();
+        let entry = |purged| CachedResponse {
+            status: 200,
+            body: "cached".into(),
+            headers: vec![],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged,
+        };
+        load_into_memory(vec![
+            (hash_uri("/purged-under-load|"), entry(true)),
+            (hash_uri("/fresh-under-load|"), entry(false)),
+        ])
+        .await;
+        for (uri, status) in [("/purged-under-load", 502), ("/fresh-under-load", 200)] {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let resp = proxy_handler(req).await.into_response();
+            assert_eq!(resp.status(), status, "{uri}");
+        }
+    }
 }
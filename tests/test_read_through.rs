@@ -0,0 +1,276 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, response::IntoResponse, routing::get};
+    use cachebolt::{
+        config::{
+            CONFIG, CacheMode, CacheSettings, Config, ExpiredPolicy, LatencyFailover,
+            StorageBackend,
+        },
+        memory::memory::{CachedResponse, get_from_memory, load_into_memory},
+        proxy::{hash_uri, proxy_handler},
+    };
+    use hyper::{Body, Request, StatusCode};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Once;
+
+    static UPSTREAM_HITS: AtomicUsize = AtomicUsize::new(0);
+    static SLOW_HITS: AtomicUsize = AtomicUsize::new(0);
+    static INIT: Once = Once::new();
+
+    /// Starts a mock downstream on its own runtime and points CONFIG at it.
+    fn setup() {
+        INIT.call_once(|| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(async move {
+                    let app = Router::new()
+                        .route(
+                            "/no-store",
+                            get(|| async {
+                                UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                                (
+                                    [("connection", "close"), ("cache-control", "no-store")],
+                                    "from-upstream",
+                                )
+                                    .into_response()
+                            }),
+                        )
+                        .route(
+                            "/non-authoritative",
+                            get(|| async {
+                                UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                                (
+                                    StatusCode::NON_AUTHORITATIVE_INFORMATION,
+                                    [("connection", "close")],
+                                    "from-upstream",
+                                )
+                                    .into_response()
+                            }),
+                        )
+                        .route(
+                            "/slow",
+                            get(|| async {
+                                SLOW_HITS.fetch_add(1, Ordering::SeqCst);
+                                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                                ([("connection", "close")], "from-upstream").into_response()
+                            }),
+                        )
+                        .route(
+                            "/*path",
+                            get(|| async {
+                                UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                                ([("connection", "close")], "from-upstream").into_response()
+                            }),
+                        );
+                    axum::Server::from_tcp(listener)
+                        .unwrap()
+                        .serve(app.into_make_service())
+                        .await
+                        .unwrap();
+                });
+            });
+
+            let _ = CONFIG.set(Config {
+                app_id: "read-through".into(),
+                gcs_bucket: "".into(),
+                s3_bucket: "".into(),
+                azure_container: "".into(),
+                max_concurrent_requests: 10,
+                downstream_base_url: format!("http://127.0.0.1:{port}"),
+                cache: CacheSettings {
+                    memory_threshold: 100,
+                    refresh_percentage: 0,
+                    ttl_seconds: 60,
+                    mode: CacheMode::ReadThrough,
+                    expired_policy: ExpiredPolicy::Reject,
+                    ..Default::default()
+                },
+                latency_failover: LatencyFailover {
+                    default_max_latency_ms: 5000,
+                    path_rules: vec![],
+                },
+                storage_backend: Some(StorageBackend::Local),
+                storage_backend_failures: 0,
+                backend_retry_interval_secs: 0,
+                ignored_headers: None,
+                proxy_port: 3000,
+                admin_port: 3001,
+                ..Default::default()
+            });
+        });
+    }
+
+    async fn get_body(uri: &str) -> String {
+        let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let resp = proxy_handler(req).await.into_response();
+        let bytes = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_fresh_entry_served_without_upstream() {
+        setup();
+        let key = hash_uri("/fresh|");
+        load_into_memory(vec![(
+            key,
+            CachedResponse {
+                status: 200,
+                body: "from-cache".into(),
+                headers: vec![],
+                inserted_at: chrono::Utc::now(),
+                expires_at: chrono::Utc::now() + chrono::Duration::seconds(60),
+                must_revalidate: false,
+                stale_while_revalidate: 0,
+                stale_if_error: 0,
+                key_source: String::new(),
+                tags: vec![],
+                purged: false,
+            },
+        )])
+        .await;
+
+        let before = UPSTREAM_HITS.load(Ordering::SeqCst);
+        assert_eq!(get_body("/fresh").await, "from-cache");
+        assert_eq!(UPSTREAM_HITS.load(Ordering::SeqCst), before);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entry_is_forwarded() {
+        setup();
+        let key = hash_uri("/expired|");
+        load_into_memory(vec![(
+            key,
+            CachedResponse {
+                status: 200,
+                body: "old".into(),
+                headers: vec![],
+                inserted_at: chrono::Utc::now() - chrono::Duration::seconds(600),
+                expires_at: chrono::Utc::now() - chrono::Duration::seconds(540),
+                must_revalidate: false,
+                stale_while_revalidate: 0,
+                stale_if_error: 0,
+                key_source: String::new(),
+                tags: vec![],
+                purged: false,
+            },
+        )])
+        .await;
+
+        assert_eq!(get_body("/expired").await, "from-upstream");
+    }
+
+    #[tokio::test]
+    async fn test_soft_purged_entry_is_forwarded_and_replaced() {
+        setup();
+        let key = hash_uri("/soft-purged|");
+        load_into_memory(vec![(
+            key.clone(),
+            CachedResponse {
+                status: 200,
+                body: "purged".into(),
+                headers: vec![],
+                inserted_at: chrono::Utc::now(),
+                expires_at: chrono::Utc::now() + chrono::Duration::seconds(60),
+                must_revalidate: false,
+                stale_while_revalidate: 60,
+                stale_if_error: 0,
+                key_source: String::new(),
+                tags: vec![],
+                purged: true,
+            },
+        )])
+        .await;
+
+        // Neither served fresh nor while revalidating
+        assert_eq!(get_body("/soft-purged").await, "from-upstream");
+        assert!(!get_from_memory(&key).await.unwrap().purged);
+    }
+
+    #[tokio::test]
+    async fn test_reject_policy_drops_expired_entries() {
+        setup();
+        let key = hash_uri("/rejected|");
+        load_into_memory(vec![(
+            key.clone(),
+            CachedResponse {
+                status: 200,
+                body: "old".into(),
+                headers: vec![],
+                inserted_at: chrono::Utc::now() - chrono::Duration::seconds(600),
+                expires_at: chrono::Utc::now() - chrono::Duration::seconds(540),
+                must_revalidate: false,
+                stale_while_revalidate: 0,
+                stale_if_error: 0,
+                key_source: String::new(),
+                tags: vec![],
+                purged: false,
+            },
+        )])
+        .await;
+
+        assert!(get_from_memory(&key).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_miss_is_forwarded_then_served_from_memory() {
+        setup();
+        assert_eq!(get_body("/miss").await, "from-upstream");
+
+        let before = UPSTREAM_HITS.load(Ordering::SeqCst);
+        assert_eq!(get_body("/miss").await, "from-upstream");
+        assert_eq!(UPSTREAM_HITS.load(Ordering::SeqCst), before);
+    }
+
+    #[tokio::test]
+    async fn test_cached_entry_keeps_its_status() {
+        setup();
+        let request = || Request::builder().uri("/non-authoritative").body(Body::empty()).unwrap();
+        let first = proxy_handler(request()).await.into_response();
+        assert_eq!(first.status(), StatusCode::NON_AUTHORITATIVE_INFORMATION);
+        hyper::body::to_bytes(first.into_body()).await.unwrap();
+
+        let before = UPSTREAM_HITS.load(Ordering::SeqCst);
+        let cached = proxy_handler(request()).await.into_response();
+        assert_eq!(cached.status(), StatusCode::NON_AUTHORITATIVE_INFORMATION);
+        assert_eq!(UPSTREAM_HITS.load(Ordering::SeqCst), before);
+    }
+
+    #[tokio::test]
+    async fn test_no_store_response_is_not_cached() {
+        setup();
+        assert_eq!(get_body("/no-store").await, "from-upstream");
+        assert!(get_from_memory(&hash_uri("/no-store|")).await.is_none());
+
+        let before = UPSTREAM_HITS.load(Ordering::SeqCst);
+        assert_eq!(get_body("/no-store").await, "from-upstream");
+        assert_eq!(UPSTREAM_HITS.load(Ordering::SeqCst), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_are_coalesced() {
+        setup();
+        let (a, b, c) = tokio::join!(get_body("/slow"), get_body("/slow"), get_body("/slow"));
+        assert_eq!(a, "from-upstream");
+        assert_eq!(b, "from-upstream");
+        assert_eq!(c, "from-upstream");
+        assert_eq!(SLOW_HITS.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs against a throwaway `redis-server` (or the binary named by
+//! `REDIS_SERVER`, e.g. `valkey-server`) started on a free port. Tests that
+//! need the server are skipped when it is not installed, unless
+//! `CACHEBOLT_REDIS_TESTS` is set (as in CI), in which case they fail.
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use cachebolt::config::{CONFIG, CacheSettings, Config, StorageBackend as BackendKind};
+    use cachebolt::memory::memory::CachedResponse;
+    use cachebolt::storage::redis::RedisBackend;
+    use cachebolt::storage::{StorageBackend, retention_secs};
+    use ctor::{ctor, dtor};
+    use once_cell::sync::{Lazy, OnceCell};
+    use std::net::{TcpListener, TcpStream};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// Port picked for the test server before `CONFIG` is set.
+    static PORT: OnceCell<u16> = OnceCell::new();
+
+    /// The server process, or `None` if it could not be started.
+    static SERVER: Lazy<Mutex<Option<Child>>> = Lazy::new(|| Mutex::new(start_server()));
+
+    /// One runtime for every test: the Redis connection is global and its
+    /// driver task must outlive a single `#[tokio::test]` runtime.
+    static RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    });
+
+    /// Serializes tests, since `delete_all` wipes the whole namespace.
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    #[ctor]
+    fn init() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|l| l.local_addr())
+            .map(|a| a.port())
+            .unwrap();
+        PORT.set(port).unwrap();
+
+        let _ = CONFIG.set(Config {
+            app_id: "redis-test".into(),
+            redis_url: format!("redis://127.0.0.1:{port}"),
+            cache: CacheSettings {
+                memory_threshold: 100,
+                max_stale_seconds: 3600,
+                ..Default::default()
+            },
+            storage_backend: Some(BackendKind::Redis),
+            ..Default::default()
+        });
+    }
+
+    #[dtor]
+    fn stop_server() {
+        if let Some(mut child) = SERVER.lock().ok().and_then(|mut s| s.take()) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    fn start_server() -> Option<Child> {
+        let binary = std::env::var("REDIS_SERVER").unwrap_or_else(|_| "redis-server".into());
+        let port = PORT.get()?.to_string();
+        let mut child = Command::new(&binary)
+            .args(["--port", &port, "--save", "", "--appendonly", "no"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if TcpStream::connect(("127.0.0.1", PORT.get().copied()?)).is_ok() {
+                return Some(child);
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let _ = child.kill();
+        None
+    }
+
+    /// Runs `test` against the server, or skips it if none is available and
+    /// `CACHEBOLT_REDIS_TESTS` is not set.
+    fn with_server<F: std::future::Future<Output = ()>>(name: &str, test: impl FnOnce() -> F) {
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if SERVER.lock().unwrap().is_none() {
+            assert!(
+                std::env::var_os("CACHEBOLT_REDIS_TESTS").is_none(),
+                "{name}: CACHEBOLT_REDIS_TESTS is set but redis-server could not be started"
+            );
+            eprintln!("skipping {name}: redis-server is not available");
+            return;
+        }
+        RUNTIME.block_on(async {
+            RedisBackend.init().await.expect("connect to the test server");
+            test().await;
+        });
+    }
+
+    fn entry(body: &str, ttl_secs: i64) -> CachedResponse {
+        let now = chrono::Utc::now();
+        CachedResponse {
+            status: 200,
+            body: Bytes::from(body.to_string()),
+            headers: vec![("content-type".into(), "text/plain".into())],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(ttl_secs),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
+    #[test]
+    fn test_retention_follows_cache_lifetime() {
+        let now = chrono::Utc::now();
+        let mut e = entry("v", 60);
+        e.expires_at = now + chrono::Duration::seconds(60);
+
+        // Servable stale on error for max_stale_seconds past expiry
+        assert_eq!(retention_secs(&e, now), Some(60 + 3600));
+
+        // must-revalidate entries only live through their stale windows
+        e.must_revalidate = true;
+        e.stale_while_revalidate = 30;
+        assert_eq!(retention_secs(&e, now), Some(90));
+
+        // Never zero, even for entries already past every window
+        e.expires_at = now - chrono::Duration::seconds(600);
+        assert_eq!(retention_secs(&e, now), Some(1));
+    }
+
+    #[test]
+    fn test_put_get_delete_roundtrip() {
+        with_server("test_put_get_delete_roundtrip", || async {
+            RedisBackend.put("GET:/a", entry("hello", 60)).await.unwrap();
+
+            let found = RedisBackend.get("GET:/a").await.unwrap().expect("stored entry");
+            assert_eq!(found.body, Bytes::from("hello"));
+            assert_eq!(found.headers, vec![("content-type".into(), "text/plain".into())]);
+
+            assert!(RedisBackend.delete("GET:/a").await.unwrap());
+            assert!(!RedisBackend.delete("GET:/a").await.unwrap());
+            assert!(RedisBackend.get("GET:/a").await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn test_entries_expire_natively() {
+        with_server("test_entries_expire_natively", || async {
+            let mut short = entry("short", -3600);
+            short.must_revalidate = true;
+            RedisBackend.put("GET:/short", short).await.unwrap();
+            assert!(RedisBackend.get("GET:/short").await.unwrap().is_some());
+
+            tokio::time::sleep(Duration::from_millis(2100)).await;
+            assert!(RedisBackend.get("GET:/short").await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn test_list_and_delete_all_stay_in_namespace() {
+        with_server("test_list_and_delete_all_stay_in_namespace", || async {
+            RedisBackend.delete_all().await.unwrap();
+            RedisBackend.put("GET:/x", entry("x", 60)).await.unwrap();
+            RedisBackend.put("GET:/y", entry("yy", 60)).await.unwrap();
+
+            // A key of another application sharing the server
+            let client = redis::Client::open(CONFIG.get().unwrap().redis_url.as_str()).unwrap();
+            let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+            let _: () = redis::cmd("SET")
+                .arg("cachebolt:other-app:GET:/x")
+                .arg("v")
+                .query_async(&mut conn)
+                .await
+                .unwrap();
+
+            let mut listed = RedisBackend.list().await.unwrap();
+            listed.sort_by(|a, b| a.key.cmp(&b.key));
+            let keys: Vec<_> = listed.iter().map(|o| o.key.as_str()).collect();
+            assert_eq!(keys, vec!["GET:/x", "GET:/y"]);
+            assert!(listed.iter().all(|o| o.size > 0));
+
+            let mut deleted = RedisBackend.delete_all().await.unwrap();
+            deleted.sort();
+            assert_eq!(deleted, vec!["GET:/x", "GET:/y"]);
+            assert!(RedisBackend.list().await.unwrap().is_empty());
+            let other: Option<String> = redis::cmd("GET")
+                .arg("cachebolt:other-app:GET:/x")
+                .query_async(&mut conn)
+                .await
+                .unwrap();
+            assert_eq!(other.as_deref(), Some("v"));
+        });
+    }
+
+    #[test]
+    fn test_health_check() {
+        with_server("test_health_check", || async {
+            RedisBackend.health_check().await.unwrap();
+        });
+    }
+}
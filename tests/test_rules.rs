@@ -17,10 +17,16 @@ mod tests {
     use cachebolt::config::{
         CacheSettings, Config, LatencyFailover, MaxLatencyRule, StorageBackend, CONFIG
     };
+    use cachebolt::rules::cache_control::{
+        StaleWindows, UpstreamDirectives, freshness_for_response, get_stale_windows,
+        parse_http_date,
+    };
+    use cachebolt::rules::conditional::Conditionals;
     use cachebolt::rules::latency::{
         LATENCY_FAILS, get_max_latency_for_path, mark_latency_fail, should_failover,
     };
     use ctor::ctor;
+    use hyper::HeaderMap;
     use regex::Regex;
     use std::thread::sleep;
     use std::time::{Duration, Instant};
@@ -51,13 +57,15 @@ mod tests {
                 memory_threshold: 90,
                 refresh_percentage: 10,
                 ttl_seconds: 300,
+                ..Default::default()
             },
-            storage_backend: StorageBackend::Local,
+            storage_backend: Some(StorageBackend::Local),
             storage_backend_failures: 0,
             backend_retry_interval_secs: 0,
             ignored_headers: None,
             proxy_port: 3000,
-            admin_port: 3001
+            admin_port: 3001,
+            ..Default::default()
         };
 
         let _ = CONFIG.set(mock_config);
@@ -103,13 +111,15 @@ mod tests {
                 memory_threshold: 90,
                 refresh_percentage: 10,
                 ttl_seconds: 300,
+                ..Default::default()
             },
-            storage_backend: StorageBackend::Local,
+            storage_backend: Some(StorageBackend::Local),
             storage_backend_failures: 0,
             backend_retry_interval_secs: 0,
             ignored_headers: None,
             proxy_port: 3000,
-            admin_port: 3001
+            admin_port: 3001,
+            ..Default::default()
         };
 
         let result = cfg.latency_failover.path_rules.iter().find_map(|rule| {
@@ -148,5 +158,109 @@ mod tests {
         assert_eq!(get_max_latency_for_path("/any"), 1500);
     }
 
-  
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (k, v) in pairs {
+            map.append(*k, v.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_cache_control_directives_parsing() {
+        let d = UpstreamDirectives::from_headers(&headers(&[(
+            "cache-control",
+            "public, max-age=60, s-maxage=\"120\", must-revalidate",
+        )]));
+        assert_eq!(d.max_age, Some(60));
+        assert_eq!(d.s_maxage, Some(120));
+        assert!(d.must_revalidate);
+        assert!(d.is_storable());
+        assert_eq!(d.freshness_lifetime(), Some(120), "s-maxage wins over max-age");
+
+        let d = UpstreamDirectives::from_headers(&headers(&[("cache-control", "private")]));
+        assert!(!d.is_storable());
+
+        let d = UpstreamDirectives::from_headers(&headers(&[("cache-control", "no-store")]));
+        assert!(!d.is_storable());
+
+        let d = UpstreamDirectives::from_headers(&headers(&[("cache-control", "no-cache")]));
+        assert_eq!(d.freshness_lifetime(), Some(0));
+    }
+
+    #[test]
+    fn test_expires_relative_to_date_and_age() {
+        let d = UpstreamDirectives::from_headers(&headers(&[
+            ("date", "Wed, 21 Oct 2015 07:28:00 GMT"),
+            ("expires", "Wed, 21 Oct 2015 07:38:00 GMT"),
+            ("age", "100"),
+        ]));
+        assert_eq!(d.freshness_lifetime(), Some(500));
+
+        let d = UpstreamDirectives::from_headers(&headers(&[("expires", "0")]));
+        assert_eq!(d.freshness_lifetime(), Some(0), "Invalid Expires means already expired");
+
+        assert!(parse_http_date("not a date").is_none());
+    }
+
+    #[test]
+    fn test_freshness_for_response_uses_default_ttl() {
+        let f = freshness_for_response("/plain", &HeaderMap::new()).unwrap();
+        assert_eq!(f.ttl_secs, 300);
+        assert!(!f.must_revalidate);
+
+        let f = freshness_for_response("/short", &headers(&[("cache-control", "max-age=5")]));
+        assert_eq!(f.unwrap().ttl_secs, 5);
+
+        assert!(freshness_for_response("/secret", &headers(&[("cache-control", "private")])).is_none());
+    }
+
+    #[test]
+    fn test_stale_windows_from_upstream_directives() {
+        assert_eq!(get_stale_windows("/any"), StaleWindows::default());
+
+        let f = freshness_for_response(
+            "/swr",
+            &headers(&[(
+                "cache-control",
+                "max-age=5, stale-while-revalidate=30, stale-if-error=600",
+            )]),
+        )
+        .unwrap();
+        assert_eq!(f.stale_while_revalidate, 30);
+        assert_eq!(f.stale_if_error, 600);
+
+        let f = freshness_for_response("/plain", &HeaderMap::new()).unwrap();
+        assert_eq!(f.stale_while_revalidate, 0);
+        assert_eq!(f.stale_if_error, 0);
+    }
+
+    #[test]
+    fn test_conditionals_evaluation() {
+        let lm = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+        let c = Conditionals::from_headers(&headers(&[("if-none-match", "\"a\", W/\"b\"")]));
+        assert!(c.is_not_modified(Some("\"b\""), None), "Weak comparison");
+        assert!(!c.is_not_modified(Some("\"c\""), None));
+        assert!(!c.is_not_modified(None, Some(lm)));
+
+        let c = Conditionals::from_headers(&headers(&[("if-none-match", "*")]));
+        assert!(c.is_not_modified(Some("\"any\""), None));
+
+        let c = Conditionals::from_headers(&headers(&[("if-modified-since", lm)]));
+        assert!(c.is_not_modified(None, Some(lm)));
+        assert!(!c.is_not_modified(None, Some("Thu, 22 Oct 2015 07:28:00 GMT")));
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let c = Conditionals::from_headers(&headers(&[
+            ("if-none-match", "\"x\""),
+            ("if-modified-since", lm),
+        ]));
+        assert!(!c.is_not_modified(Some("\"y\""), Some(lm)));
+
+        assert_eq!(
+            Conditionals::from_headers(&headers(&[("if-modified-since", "garbage")])),
+            Conditionals::default()
+        );
+    }
 }
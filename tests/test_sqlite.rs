@@ -0,0 +1,181 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use cachebolt::config::{CONFIG, CacheSettings, Config, ExpiredPolicy, StorageBackend as BackendKind};
+    use cachebolt::memory::memory::CachedResponse;
+    use cachebolt::storage::sqlite::{SqliteBackend, purge_evicted};
+    use cachebolt::storage::{StorageBackend, StorageUsage};
+    use ctor::ctor;
+    use once_cell::sync::Lazy;
+    use std::time::Duration;
+
+    /// Database directory, removed with the test process.
+    static DIR: Lazy<tempfile::TempDir> = Lazy::new(|| tempfile::tempdir().unwrap());
+
+    /// Serializes tests, since most of them look at the whole table.
+    static LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[ctor]
+    fn init() {
+        let _ = CONFIG.set(Config {
+            app_id: "sqlite-test".into(),
+            sqlite_path: DIR.path().join("db").join("cache.sqlite3").display().to_string(),
+            cache: CacheSettings {
+                memory_threshold: 100,
+                expired_policy: ExpiredPolicy::Reject,
+                ..Default::default()
+            },
+            storage_backend: Some(BackendKind::Sqlite),
+            ..Default::default()
+        });
+    }
+
+    async fn backend() -> SqliteBackend {
+        SqliteBackend.init().await.unwrap();
+        SqliteBackend.delete_all().await.unwrap();
+        SqliteBackend
+    }
+
+    fn entry(source: &str, tags: &str) -> CachedResponse {
+        let now = chrono::Utc::now();
+        let mut headers = vec![("content-type".to_string(), "text/plain".to_string())];
+        if !tags.is_empty() {
+            headers.push(("Surrogate-Key".into(), tags.into()));
+        }
+        CachedResponse {
+            status: 200,
+            body: Bytes::from(format!("body of {source}")),
+            headers,
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: source.to_string(),
+            tags: tags.split_whitespace().map(str::to_string).collect(),
+            purged: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_put_get_delete_and_usage() {
+        let _guard = LOCK.lock().await;
+        let db = backend().await;
+
+        db.put("k1", entry("/a|", "")).await.unwrap();
+        db.put("k1", entry("/a|", "")).await.unwrap();
+        db.put("k2", entry("/b|", "")).await.unwrap();
+
+        let found = db.get("k1").await.unwrap().expect("stored entry");
+        assert_eq!(found.body, Bytes::from("body of /a|"));
+        assert_eq!(found.key_source, "/a|");
+
+        let usage = db.usage().await.unwrap();
+        assert_eq!(usage.entries, 2, "rewrites replace the row");
+        let listed = db.list().await.unwrap();
+        assert_eq!(usage.bytes, listed.iter().map(|o| o.size).sum::<u64>());
+
+        assert!(db.delete("k1").await.unwrap());
+        assert!(!db.delete("k1").await.unwrap());
+        assert!(db.get("k1").await.unwrap().is_none());
+        db.health_check().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_source_prefix_is_literal_and_case_sensitive() {
+        let _guard = LOCK.lock().await;
+        let db = backend().await;
+
+        db.put("k1", entry("/api/products/1|", "")).await.unwrap();
+        db.put("k2", entry("/api/products/2|", "")).await.unwrap();
+        db.put("k3", entry("/API/products/3|", "")).await.unwrap();
+        db.put("k4", entry("/api/[x]*|", "")).await.unwrap();
+        db.put("k5", entry("/api/x|", "")).await.unwrap();
+
+        let mut deleted = db.delete_source_prefix("/api/products/").await.unwrap();
+        deleted.sort();
+        assert_eq!(deleted, vec!["k1", "k2"]);
+        // Glob metacharacters are matched literally
+        assert_eq!(db.delete_source_prefix("/api/[x]*").await.unwrap(), vec!["k4"]);
+
+        let mut keys: Vec<_> = db.list().await.unwrap().into_iter().map(|o| o.key).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["k3", "k5"]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_tagged() {
+        let _guard = LOCK.lock().await;
+        let db = backend().await;
+
+        db.put("k1", entry("/p/42|", "product-42 category-7")).await.unwrap();
+        db.put("k2", entry("/c/7|", "category-7")).await.unwrap();
+        db.put("k3", entry("/p/43|", "product-43")).await.unwrap();
+
+        assert_eq!(db.delete_tagged(&["product-42".into()]).await.unwrap(), vec!["k1"]);
+        assert!(db.get("k2").await.unwrap().is_some());
+
+        // Tags are rewritten with the entry
+        db.put("k3", entry("/p/43|", "category-7")).await.unwrap();
+        assert!(db.delete_tagged(&["product-43".into()]).await.unwrap().is_empty());
+        assert_eq!(
+            db.delete_tagged(&["category-7".into(), "unknown".into()]).await.unwrap().len(),
+            2
+        );
+        assert_eq!(db.usage().await.unwrap(), StorageUsage::default());
+    }
+
+    #[tokio::test]
+    async fn test_entries_past_their_lifetime_are_hidden_and_purged() {
+        let _guard = LOCK.lock().await;
+        let db = backend().await;
+
+        let mut expired = entry("/old|", "");
+        expired.expires_at = chrono::Utc::now() - chrono::Duration::seconds(60);
+        db.put("old", expired).await.unwrap();
+        db.put("new", entry("/new|", "")).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        assert!(db.get("old").await.unwrap().is_none());
+        assert_eq!(db.list().await.unwrap().len(), 1);
+
+        assert_eq!(purge_evicted().await.unwrap(), 1);
+        assert!(db.get("new").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_delete_all_keeps_other_apps() {
+        let _guard = LOCK.lock().await;
+        let db = backend().await;
+        db.put("k1", entry("/a|", "")).await.unwrap();
+
+        // A row of another application sharing the file
+        let conn = rusqlite::Connection::open(&CONFIG.get().unwrap().sqlite_path).unwrap();
+        conn.execute(
+            "INSERT INTO entries (app_id, key, source, blob, size, stored_at, expires_at)
+             VALUES ('other-app', 'k1', '/a|', x'00', 1, 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(db.delete_all().await.unwrap(), vec!["k1"]);
+        let others: i64 = conn
+            .query_row("SELECT COUNT(*) FROM entries WHERE app_id = 'other-app'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(others, 1);
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, http::StatusCode, response::IntoResponse, routing::get};
+    use cachebolt::{
+        config::{
+            CONFIG, CacheMode, CacheSettings, Config, ExpiredPolicy, LatencyFailover,
+            StaleRule, StaleSettings, StorageBackend,
+        },
+        memory::memory::{CachedResponse, get_from_memory, load_into_memory},
+        proxy::{hash_uri, proxy_handler},
+    };
+    use hyper::{Body, Request};
+    use std::net::TcpListener;
+    use std::sync::Once;
+    use std::time::Duration;
+
+    static INIT: Once = Once::new();
+
+    /// Starts a mock downstream on its own runtime and points CONFIG at it.
+    fn setup() {
+        INIT.call_once(|| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(async move {
+                    let app = Router::new()
+                        .route(
+                            "/broken/*path",
+                            get(|| async {
+                                (
+                                    StatusCode::SERVICE_UNAVAILABLE,
+                                    [("connection", "close")],
+                                    "upstream-error",
+                                )
+                                    .into_response()
+                            }),
+                        )
+                        .route(
+                            "/slow/*path",
+                            get(|| async {
+                                tokio::time::sleep(Duration::from_millis(1000)).await;
+                                ([("connection", "close")], "too-late").into_response()
+                            }),
+                        )
+                        .route(
+                            "/*path",
+                            get(|| async {
+                                ([("connection", "close")], "from-upstream").into_response()
+                            }),
+                        );
+                    axum::Server::from_tcp(listener)
+                        .unwrap()
+                        .serve(app.into_make_service())
+                        .await
+                        .unwrap();
+                });
+            });
+
+            let _ = CONFIG.set(Config {
+                app_id: "stale".into(),
+                max_concurrent_requests: 10,
+                downstream_base_url: format!("http://127.0.0.1:{port}"),
+                cache: CacheSettings {
+                    memory_threshold: 100,
+                    ttl_seconds: 60,
+                    mode: CacheMode::ReadThrough,
+                    expired_policy: ExpiredPolicy::Reject,
+                    ..Default::default()
+                },
+                latency_failover: LatencyFailover {
+                    default_max_latency_ms: 5000,
+                    path_rules: vec![],
+                },
+                stale: StaleSettings {
+                    stale_while_revalidate_seconds: 0,
+                    stale_if_error_seconds: 0,
+                    upstream_timeout_ms: 300,
+                    path_rules: vec![StaleRule {
+                        pattern: "^/swr".into(),
+                        stale_while_revalidate_seconds: 60,
+                        stale_if_error_seconds: 0,
+                    }],
+                },
+                storage_backend: Some(StorageBackend::Local),
+                ..Default::default()
+            });
+        });
+    }
+
+    /// Entry that expired 10s ago with the given stale windows.
+    fn expired_entry(stale_while_revalidate: u64, stale_if_error: u64) -> CachedResponse {
+        CachedResponse {
+            status: 200,
+            body: "stale-copy".into(),
+            headers: vec![],
+            inserted_at: chrono::Utc::now() - chrono::Duration::seconds(70),
+            expires_at: chrono::Utc::now() - chrono::Duration::seconds(10),
+            must_revalidate: false,
+            stale_while_revalidate,
+            stale_if_error,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
+    async fn fetch(uri: &str) -> (u16, String) {
+        let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let resp = proxy_handler(req).await.into_response();
+        let status = resp.status().as_u16();
+        let bytes = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        (status, String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_stale_while_revalidate_refreshes_in_background() {
+        setup();
+        let key = hash_uri("/swr/item|");
+        load_into_memory(vec![(key.clone(), expired_entry(60, 0))]).await;
+
+        assert_eq!(fetch("/swr/item").await, (200, "stale-copy".into()));
+
+        // The background refresh replaces the entry with a fresh one
+        let mut refreshed = false;
+        for _ in 0..50 {
+            if let Some(entry) = get_from_memory(&key).await
+                && entry.is_fresh()
+            {
+                assert_eq!(entry.body, "from-upstream");
+                assert_eq!(entry.stale_while_revalidate, 60, "Path rule window is stored");
+                refreshed = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(refreshed, "Stale entry should be revalidated in the background");
+    }
+
+    #[tokio::test]
+    async fn test_stale_if_error_on_server_error() {
+        setup();
+        load_into_memory(vec![(hash_uri("/broken/item|"), expired_entry(0, 60))]).await;
+
+        assert_eq!(fetch("/broken/item").await, (200, "stale-copy".into()));
+    }
+
+    #[tokio::test]
+    async fn test_server_error_passes_through_without_window() {
+        setup();
+        load_into_memory(vec![(hash_uri("/broken/other|"), expired_entry(0, 0))]).await;
+
+        assert_eq!(fetch("/broken/other").await, (503, "upstream-error".into()));
+    }
+
+    #[tokio::test]
+    async fn test_stale_if_error_on_timeout() {
+        setup();
+        load_into_memory(vec![(hash_uri("/slow/item|"), expired_entry(0, 60))]).await;
+
+        assert_eq!(fetch("/slow/item").await, (200, "stale-copy".into()));
+    }
+}
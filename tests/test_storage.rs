@@ -14,21 +14,20 @@
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use azure_storage_blobs::blob;
     use bytes::Bytes;
     use cachebolt::config::{
         CONFIG, CacheSettings, Config, LatencyFailover, MaxLatencyRule, StorageBackend,
     };
-    use cachebolt::storage::local::CachedBlob;
+    use cachebolt::memory::memory::CachedResponse;
+    use cachebolt::storage::EntryMeta;
+    use cachebolt::storage::blob::LegacyBlob;
+    use cachebolt::storage::breaker::{ErrorClass, classify};
     use cachebolt::storage::local::*;
-    use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+    use flate2::{Compression, write::GzEncoder};
     use serde::Serialize;
-    use serde::ser::{Serialize as TraitSerialize, Serializer};
     use std::fs;
     use std::io::Write;
     use std::path::Path;
-    use tokio;
 
     fn init_config_for_tests() {
         if CONFIG.get().is_none() {
@@ -43,6 +42,7 @@ mod tests {
                     memory_threshold: 90,
                     refresh_percentage: 10,
                     ttl_seconds: 300,
+                    ..Default::default()
                 },
                 latency_failover: LatencyFailover {
                     default_max_latency_ms: 200,
@@ -51,17 +51,35 @@ mod tests {
                         max_latency_ms: 100,
                     }],
                 },
-                storage_backend: StorageBackend::Local,
+                storage_backend: Some(StorageBackend::Local),
                 storage_backend_failures: 0,     // <-- añadido
                 backend_retry_interval_secs: 0,  // <-- añadido
                 ignored_headers: None,
                 proxy_port: 3000,
                 admin_port: 3001,
+                ..Default::default()
             };
             let _ = CONFIG.set(config);
         }
     }
 
+    fn entry(data: Bytes, headers: Vec<(String, String)>) -> CachedResponse {
+        let now = chrono::Utc::now();
+        CachedResponse {
+            status: 200,
+            body: data,
+            headers,
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(300),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
     #[tokio::test]
     async fn test_store_and_load_cache_roundtrip() {
         init_config_for_tests();
@@ -72,26 +90,32 @@ mod tests {
             ("X-Test".to_string(), "true".to_string()),
         ];
 
-        store_in_cache(key.to_string(), data.clone(), headers.clone()).await;
+        let stored = entry(data.clone(), headers.clone());
+        store_in_cache(key.to_string(), stored.clone()).await.unwrap();
 
-        let result = load_from_cache(key).await;
+        let result = load_from_cache(key).await.unwrap();
         assert!(result.is_some(), "Expected cached value to be returned");
 
-        let (loaded_data, loaded_headers) = result.unwrap();
-        assert_eq!(loaded_data, data);
-        assert_eq!(loaded_headers, headers);
-
-        if let Some(path) = build_local_cache_path(key) {
-            if Path::new(&path).exists() {
-                let _ = fs::remove_file(path);
-            }
+        let loaded = result.unwrap();
+        assert_eq!(loaded.body, data);
+        assert_eq!(loaded.headers, headers);
+        assert_eq!(loaded.inserted_at.timestamp(), stored.inserted_at.timestamp());
+        assert_eq!(loaded.expires_at.timestamp(), stored.expires_at.timestamp());
+        assert!(loaded.is_fresh());
+
+        if let Some(path) = build_local_cache_path(key)
+            && Path::new(&path).exists()
+        {
+            let raw = fs::read(&path).unwrap();
+            assert!(raw.starts_with(cachebolt::storage::blob::MAGIC), "Expected a blob envelope on disk");
+            let _ = fs::remove_file(path);
         }
     }
 
     #[tokio::test]
     async fn test_load_from_nonexistent_cache() {
         init_config_for_tests();
-        let result = load_from_cache("nonexistent_key_12345").await;
+        let result = load_from_cache("nonexistent_key_12345").await.unwrap();
         assert!(result.is_none(), "Expected None for missing cache file");
     }
 
@@ -102,8 +126,8 @@ mod tests {
         let data = Bytes::from(vec![0xFF, 0xFE, 0xFD]);
         let headers = vec![];
 
-        store_in_cache(key.to_string(), data, headers).await;
-        let result = load_from_cache(key).await;
+        store_in_cache(key.to_string(), entry(data, headers)).await.unwrap();
+        let result = load_from_cache(key).await.unwrap();
         assert!(result.is_some(), "Even invalid binary should be storable");
     }
 
@@ -116,12 +140,28 @@ mod tests {
             fs::write(&path, b"not gzip").unwrap();
 
             let result = load_from_cache("corrupt").await;
-            assert!(result.is_none(), "Should return None on corrupt gzip");
+            let Err(err) = result else { panic!("Should fail on corrupt gzip") };
+            assert_eq!(classify(&*err), ErrorClass::Corrupt, "A bad file must not trip the breaker");
 
             let _ = fs::remove_file(path);
         }
     }
 
+    #[tokio::test]
+    async fn test_load_reports_read_errors() {
+        init_config_for_tests();
+
+        if let Some(path) = build_local_cache_path("unreadable") {
+            // A directory in place of the file fails the read, not the lookup
+            fs::create_dir_all(&path).unwrap();
+
+            let result = load_from_cache("unreadable").await;
+            assert!(result.is_err(), "Expected a read error instead of a miss");
+
+            let _ = fs::remove_dir(path);
+        }
+    }
+
     #[tokio::test]
     async fn test_load_fails_on_invalid_json() {
         init_config_for_tests();
@@ -134,7 +174,7 @@ mod tests {
 
             fs::write(&path, compressed).unwrap();
             let result = load_from_cache("invalid_json").await;
-            assert!(result.is_none(), "Expected None for invalid JSON in gzip");
+            assert!(result.is_err(), "Expected an error for invalid JSON in gzip");
 
             let _ = fs::remove_file(path);
         }
@@ -159,7 +199,27 @@ mod tests {
             fs::write(&path, compressed).unwrap();
 
             let result = load_from_cache("invalid_base64").await;
-            assert!(result.is_none(), "Expected None for base64 decode error");
+            assert!(result.is_err(), "Expected an error for base64 decode error");
+
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_legacy_blob_without_expiry_is_expired() {
+        init_config_for_tests();
+
+        if let Some(path) = build_local_cache_path("legacy_blob") {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let legacy = r#"{"body":"SGVsbG8=","headers":[["X-Test","true"]]}"#;
+
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(legacy.as_bytes()).unwrap();
+            fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+            let loaded = load_from_cache("legacy_blob").await.unwrap().expect("legacy blob should load");
+            assert_eq!(loaded.body, Bytes::from("Hello"));
+            assert!(!loaded.is_fresh(), "Legacy blobs carry no expiry and count as expired");
 
             let _ = fs::remove_file(path);
         }
@@ -173,8 +233,8 @@ mod tests {
         let data = Bytes::from("invalid");
         let headers = vec![];
 
-        // Just ensure it doesn't panic or crash
-        store_in_cache(key.to_string(), data, headers).await;
+        // Reports the failure instead of panicking
+        assert!(store_in_cache(key.to_string(), entry(data, headers)).await.is_err());
     }
 
     #[tokio::test]
@@ -186,8 +246,8 @@ mod tests {
         let data = Bytes::from("data");
         let headers = vec![];
 
-        // No debe panicar, y debe salir silenciosamente
-        store_in_cache(key.to_string(), data, headers).await;
+        // No debe panicar, y debe devolver el error
+        assert!(store_in_cache(key.to_string(), entry(data, headers)).await.is_err());
     }
 
     #[tokio::test]
@@ -195,8 +255,8 @@ mod tests {
         use serde::ser::{Serialize, Serializer};
 
         init_config_for_tests();
-        let key = "fail_json_serialization";
-        let data = Bytes::from("data");
+        let _key = "fail_json_serialization";
+        let _data = Bytes::from("data");
 
         // Tipo inválido para forzar error de serialización
         struct NonSerializable;
@@ -239,19 +299,17 @@ mod tests {
 
         impl Write for FailingWriter {
             fn write(&mut self, _buf: &[u8]) -> IoResult<usize> {
-                Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "forced write error",
-                ))
+                Err(std::io::Error::other("forced write error"))
             }
             fn flush(&mut self) -> IoResult<()> {
                 Ok(())
             }
         }
 
-        let blob = CachedBlob {
+        let blob = LegacyBlob {
             body: "SGVsbG8=".to_string(),
             headers: vec![("X-Test".to_string(), "true".to_string())],
+            meta: EntryMeta::default(),
         };
 
         let json = serde_json::to_vec(&blob).expect("Must serialize");
@@ -278,10 +336,10 @@ mod tests {
         perms.set_mode(0o400); // Solo lectura
         fs::set_permissions(&path, perms).unwrap();
 
-        // Intenta escribir encima
+        // Intenta escribir encima (root ignora los permisos)
         let data = Bytes::from("data");
         let headers = vec![];
-        store_in_cache(key.to_string(), data, headers).await;
+        let _ = store_in_cache(key.to_string(), entry(data, headers)).await;
 
         // Limpieza
         let _ = fs::remove_file(path);
@@ -0,0 +1,382 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use axum::extract::{Json, Query};
+    use axum::response::{IntoResponse, Response};
+    use bytes::Bytes;
+    use cachebolt::{
+        admin::backends::get_backend_usage,
+        admin::clean::{
+            InvalidateKeysRequest, InvalidateParams, InvalidatePathsRequest, InvalidateUrlRequest,
+            PurgeParams, PurgeTagsRequest, invalidate_handler, invalidate_keys_handler,
+            invalidate_paths_handler, invalidate_url_handler, purge_tags_handler,
+        },
+        cache_key::{CacheKey, key_source},
+        config::{CONFIG, CacheSettings, Config, StorageBackend as BackendKind},
+        memory::memory::{CachedResponse, MEMORY_CACHE, peek_from_memory},
+        proxy::try_cache,
+        storage::{self, StorageBackend, StorageResult, StoredObject},
+        test_util,
+    };
+    use ctor::ctor;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// In-memory stand-in for a persistent backend.
+    #[derive(Default)]
+    struct FakeBackend {
+        entries: Mutex<HashMap<String, CachedResponse>>,
+        meta: Mutex<HashMap<String, Bytes>>,
+    }
+
+    #[async_trait]
+    impl StorageBackend for FakeBackend {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+            Ok(self.entries.lock().unwrap().get(key).cloned())
+        }
+
+        async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+            self.entries.lock().unwrap().insert(key.to_string(), entry);
+            Ok(())
+        }
+
+        async fn delete(&self, key: &str) -> StorageResult<bool> {
+            Ok(self.entries.lock().unwrap().remove(key).is_some())
+        }
+
+        async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+            Ok(self
+                .entries
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(key, entry)| StoredObject {
+                    key: key.clone(),
+                    last_modified: entry.inserted_at,
+                    size: entry.body.len() as u64,
+                })
+                .collect())
+        }
+
+        async fn delete_all(&self) -> StorageResult<Vec<String>> {
+            Ok(self.entries.lock().unwrap().drain().map(|(key, _)| key).collect())
+        }
+
+        async fn health_check(&self) -> StorageResult<()> {
+            Ok(())
+        }
+
+        async fn get_meta(&self, name: &str) -> StorageResult<Option<Bytes>> {
+            Ok(self.meta.lock().unwrap().get(name).cloned())
+        }
+
+        async fn put_meta(&self, name: &str, data: Bytes) -> StorageResult<()> {
+            self.meta.lock().unwrap().insert(name.to_string(), data);
+            Ok(())
+        }
+    }
+
+    static FAKE: once_cell::sync::Lazy<Arc<FakeBackend>> =
+        once_cell::sync::Lazy::new(|| Arc::new(FakeBackend::default()));
+
+    /// Serializes tests, since `invalidate_handler` wipes the whole backend.
+    static LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[ctor]
+    fn init() {
+        let _ = CONFIG.set(Config {
+            app_id: "fake-backend".into(),
+            cache: CacheSettings {
+                memory_threshold: 100,
+                ..Default::default()
+            },
+            storage_backend: Some(BackendKind::Local),
+            ..Default::default()
+        });
+        assert!(test_util::install_backend(FAKE.clone()));
+    }
+
+    fn entry(body: &str) -> CachedResponse {
+        let now = chrono::Utc::now();
+        CachedResponse {
+            status: 200,
+            body: Bytes::from(body.to_string()),
+            headers: vec![("content-type".into(), "text/plain".into())],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
+    fn sourced(source: &str) -> CachedResponse {
+        CachedResponse {
+            key_source: source.to_string(),
+            ..entry(source)
+        }
+    }
+
+    /// Stores an entry in memory and in the backend under the key of `source`.
+    async fn store_both(source: &str) -> String {
+        let key = CacheKey::new(source.to_string()).key;
+        MEMORY_CACHE.put(key.clone(), sourced(source)).await;
+        FAKE.put(&key, sourced(source)).await.unwrap();
+        key
+    }
+
+    fn hard() -> Query<PurgeParams> {
+        Query(PurgeParams::default())
+    }
+
+    async fn json(resp: Response) -> serde_json::Value {
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_installed_backend_is_used() {
+        assert_eq!(storage::backend().unwrap().name(), "fake");
+    }
+
+    #[tokio::test]
+    async fn test_usage_endpoint_reports_backend_size() {
+        let _guard = LOCK.lock().await;
+        FAKE.put("fake-usage", entry("sized")).await.unwrap();
+        let expected = FAKE.usage().await.unwrap();
+
+        let resp = get_backend_usage().await;
+        assert_eq!(resp.status(), 200);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["entries"], expected.entries);
+        assert_eq!(json["bytes"], expected.bytes);
+        assert!(expected.entries >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_reads_from_backend_and_promotes_to_memory() {
+        let _guard = LOCK.lock().await;
+        FAKE.put("fake-hit", entry("persisted")).await.unwrap();
+
+        let resp = try_cache("fake-hit").await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(body, "persisted");
+
+        assert!(peek_from_memory("fake-hit").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_fallback_miss_in_backend() {
+        let _guard = LOCK.lock().await;
+        let resp = try_cache("fake-miss").await.unwrap();
+        assert_eq!(resp.status(), 502);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_clears_backend() {
+        let _guard = LOCK.lock().await;
+        FAKE.put("fake-purge", entry("gone")).await.unwrap();
+
+        let resp = invalidate_handler(Query(InvalidateParams { backend: Some(true), soft: None }))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), 200);
+        assert!(FAKE.get("fake-purge").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_url_removes_its_key() {
+        let _guard = LOCK.lock().await;
+        let source = key_source("/inv/items/1?x=1", &hyper::HeaderMap::new(), None);
+        let key = store_both(&source).await;
+        let variant = format!("{source}|vary|accept:text/html");
+        FAKE.put("inv-variant", sourced(&variant)).await.unwrap();
+        let other = store_both("/inv/items/2|").await;
+
+        let resp = invalidate_url_handler(hard(), Json(InvalidateUrlRequest {
+            url: "http://cache.example/inv/items/1?x=1".into(),
+            headers: Default::default(),
+        }))
+        .await;
+        assert_eq!(resp.status(), 200);
+        let counts = json(resp).await;
+        assert_eq!(counts["memory"], 1);
+        assert_eq!(counts["backend"], 1);
+
+        assert!(peek_from_memory(&key).await.is_none());
+        assert!(FAKE.get(&key).await.unwrap().is_none());
+        // Only the vary strategy has variants, so nothing else is looked for
+        assert!(FAKE.get("inv-variant").await.unwrap().is_some());
+        FAKE.delete("inv-variant").await.unwrap();
+        assert!(peek_from_memory(&other).await.is_some());
+        assert!(FAKE.get(&other).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_keys_counts_deleted_entries() {
+        let _guard = LOCK.lock().await;
+        let a = store_both("/keys/a|").await;
+        let b = store_both("/keys/b|").await;
+
+        let resp = invalidate_keys_handler(hard(), Json(InvalidateKeysRequest {
+            keys: vec![a.clone(), "unknown".into()],
+        }))
+        .await;
+        let counts = json(resp).await;
+        assert_eq!((counts["memory"].clone(), counts["backend"].clone()), (1.into(), 1.into()));
+        assert!(FAKE.get(&a).await.unwrap().is_none());
+        assert!(FAKE.get(&b).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_paths_by_prefix_and_regex() {
+        let _guard = LOCK.lock().await;
+        store_both("/shop/products/1|accept:*/*").await;
+        store_both("POST /shop/products/search|accept:*/*|abc123").await;
+        let cart = store_both("/shop/cart|").await;
+        let report = store_both("/reports/2024.csv|").await;
+
+        let resp = invalidate_paths_handler(hard(), Json(InvalidatePathsRequest {
+            prefix: Some("/shop/products/".into()),
+            regex: None,
+        }))
+        .await;
+        let counts = json(resp).await;
+        assert_eq!((counts["memory"].clone(), counts["backend"].clone()), (2.into(), 2.into()));
+        assert!(FAKE.get(&cart).await.unwrap().is_some());
+
+        // The headers part of the key is not searched
+        let resp = invalidate_paths_handler(hard(), Json(InvalidatePathsRequest {
+            prefix: None,
+            regex: Some(r"\.csv$|accept".into()),
+        }))
+        .await;
+        let counts = json(resp).await;
+        assert_eq!((counts["memory"].clone(), counts["backend"].clone()), (1.into(), 1.into()));
+        assert!(FAKE.get(&report).await.unwrap().is_none());
+
+        for (prefix, regex) in [(None, None), (Some("/a".into()), Some("b".into())), (None, Some("(".into()))] {
+            let resp = invalidate_paths_handler(hard(), Json(InvalidatePathsRequest { prefix, regex })).await;
+            assert_eq!(resp.status(), 400);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_purge_tags_uses_the_memory_tag_index() {
+        let _guard = LOCK.lock().await;
+        let tagged = |source: &str, tags: &[&str]| CachedResponse {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..sourced(source)
+        };
+        for (key, tags) in [("tag-a", &["p1", "shop"][..]), ("tag-b", &["p2"]), ("tag-c", &["shop"])] {
+            MEMORY_CACHE.put(key.into(), tagged(key, tags)).await;
+            FAKE.put(key, tagged(key, tags)).await.unwrap();
+        }
+        // Re-storing without the tag takes the entry out of the purge
+        MEMORY_CACHE.put("tag-c".into(), tagged("tag-c", &["cart"])).await;
+
+        let resp = purge_tags_handler(hard(), Json(PurgeTagsRequest {
+            tags: vec!["shop".into(), "unknown".into()],
+        }))
+        .await;
+        let counts = json(resp).await;
+        assert_eq!((counts["memory"].clone(), counts["backend"].clone()), (1.into(), 2.into()));
+        assert!(peek_from_memory("tag-a").await.is_none());
+        assert!(peek_from_memory("tag-b").await.is_some());
+        assert!(peek_from_memory("tag-c").await.is_some());
+        assert!(FAKE.get("tag-b").await.unwrap().is_some());
+
+        // Removed entries leave the index
+        assert_eq!(MEMORY_CACHE.remove_tagged(&["p1".into()]).await, 0);
+        assert_eq!(MEMORY_CACHE.remove_tagged(&["p2".into()]).await, 1);
+
+        let resp = purge_tags_handler(hard(), Json(PurgeTagsRequest { tags: vec![] })).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_soft_purge_records_rules_and_keeps_entries_for_fallback() {
+        let _guard = LOCK.lock().await;
+        let soft = || Query(PurgeParams { soft: Some(true) });
+        let purged = store_both("/soft/items/1|").await;
+        let kept = store_both("/soft/cart|").await;
+
+        let resp = invalidate_paths_handler(
+            soft(),
+            Json(InvalidatePathsRequest {
+                prefix: Some("/soft/items/".into()),
+                regex: None,
+            }),
+        )
+        .await;
+        let counts = json(resp).await;
+        assert_eq!(counts["memory"], 1);
+        assert!(counts.get("backend").is_none(), "stored entries are not counted");
+
+        let cached = peek_from_memory(&purged).await.unwrap();
+        assert!(cached.purged && !cached.is_fresh() && !cached.can_serve_while_revalidating());
+        assert!(!FAKE.get(&purged).await.unwrap().unwrap().purged, "stored entries are not rewritten");
+        assert!(!peek_from_memory(&kept).await.unwrap().purged);
+
+        // Still served when the downstream fails, also once only the backend has it
+        assert_eq!(try_cache(&purged).await.unwrap().status(), 200);
+        MEMORY_CACHE.pop(&purged).await;
+        assert_eq!(try_cache(&purged).await.unwrap().status(), 200);
+        assert!(peek_from_memory(&purged).await.unwrap().purged, "the rule applies on read");
+
+        // Entries stored after the purge are not covered
+        let newer = CacheKey::new("/soft/items/2|".to_string()).key;
+        let later = chrono::Utc::now() + chrono::Duration::seconds(5);
+        FAKE.put(&newer, CachedResponse { inserted_at: later, ..sourced("/soft/items/2|") })
+            .await
+            .unwrap();
+        assert_eq!(try_cache(&newer).await.unwrap().status(), 200);
+        assert!(!peek_from_memory(&newer).await.unwrap().purged);
+
+        // Every endpoint takes the soft mode
+        let resp = invalidate_keys_handler(soft(), Json(InvalidateKeysRequest { keys: vec![kept.clone()] })).await;
+        assert_eq!(json(resp).await["memory"], 1);
+        MEMORY_CACHE.pop(&kept).await;
+        try_cache(&kept).await.unwrap();
+        assert!(peek_from_memory(&kept).await.unwrap().purged);
+
+        // A purge of everything replaces the earlier rules
+        let total = FAKE.entries.lock().unwrap().len();
+        invalidate_handler(Query(InvalidateParams { backend: Some(true), soft: Some(true) })).await;
+        assert_eq!(FAKE.entries.lock().unwrap().len(), total, "nothing is deleted");
+        assert!(MEMORY_CACHE.iter().all(|(_, entry)| entry.purged));
+        let rules: serde_json::Value =
+            serde_json::from_slice(&FAKE.meta.lock().unwrap()["purge-rules.json"]).unwrap();
+        assert_eq!(rules.as_array().unwrap().len(), 1);
+        assert_eq!(rules[0]["scope"], "all");
+
+        // Deleting every entry clears the rules
+        invalidate_handler(Query(InvalidateParams { backend: Some(true), soft: None })).await;
+        FAKE.put(&kept, sourced("/soft/cart|")).await.unwrap();
+        try_cache(&kept).await.unwrap();
+        assert!(!peek_from_memory(&kept).await.unwrap().purged);
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::StreamBody, response::IntoResponse, routing::get};
+    use bytes::Bytes;
+    use futures::StreamExt;
+    use cachebolt::{
+        config::{CONFIG, CacheMode, CacheSettings, Config, LatencyFailover, StorageBackend},
+        proxy::proxy_handler,
+    };
+    use hyper::{Body, Request};
+    use std::net::TcpListener;
+    use std::sync::Once;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    static UPSTREAM_HITS: AtomicUsize = AtomicUsize::new(0);
+    static INIT: Once = Once::new();
+
+    const MAX_CACHEABLE: usize = 1024;
+
+    /// Starts a mock downstream on its own runtime and points CONFIG at it.
+    fn setup() {
+        INIT.call_once(|| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(async move {
+                    let app = Router::new()
+                        .route(
+                            "/big",
+                            get(|| async {
+                                UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                                ([("connection", "close")], "x".repeat(4 * MAX_CACHEABLE))
+                                    .into_response()
+                            }),
+                        )
+                        .route(
+                            "/truncated",
+                            get(|| async {
+                                UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                                let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+                                    Ok(Bytes::from("partial")),
+                                    Err(std::io::Error::other("upstream reset")),
+                                ];
+                                // Fail only after the headers and first chunk went out
+                                let body = futures::stream::iter(chunks).then(|chunk| async {
+                                    tokio::time::sleep(Duration::from_millis(50)).await;
+                                    chunk
+                                });
+                                (
+                                    [("connection", "close"), ("content-length", "100")],
+                                    StreamBody::new(body),
+                                )
+                                    .into_response()
+                            }),
+                        )
+                        .route(
+                            "/*path",
+                            get(|| async {
+                                UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                                ([("connection", "close")], "small").into_response()
+                            }),
+                        );
+                    axum::Server::from_tcp(listener)
+                        .unwrap()
+                        .serve(app.into_make_service())
+                        .await
+                        .unwrap();
+                });
+            });
+
+            let _ = CONFIG.set(Config {
+                app_id: "streaming".into(),
+                max_concurrent_requests: 10,
+                downstream_base_url: format!("http://127.0.0.1:{port}"),
+                cache: CacheSettings {
+                    memory_threshold: 100,
+                    ttl_seconds: 60,
+                    mode: CacheMode::ReadThrough,
+                    max_cacheable_body_bytes: MAX_CACHEABLE,
+                    ..Default::default()
+                },
+                latency_failover: LatencyFailover {
+                    default_max_latency_ms: 5000,
+                    path_rules: vec![],
+                },
+                storage_backend: Some(StorageBackend::Local),
+                ..Default::default()
+            });
+        });
+    }
+
+    async fn fetch(uri: &str) -> Result<Bytes, axum::Error> {
+        let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let resp = proxy_handler(req).await.into_response();
+        hyper::body::to_bytes(resp.into_body()).await
+    }
+
+    fn hits() -> usize {
+        UPSTREAM_HITS.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_small_body_is_streamed_and_cached() {
+        setup();
+        let before = hits();
+        assert_eq!(fetch("/small").await.unwrap(), "small");
+        assert_eq!(fetch("/small").await.unwrap(), "small");
+        assert_eq!(hits(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_streamed_but_not_cached() {
+        setup();
+        let before = hits();
+        assert_eq!(fetch("/big").await.unwrap().len(), 4 * MAX_CACHEABLE);
+        assert_eq!(fetch("/big").await.unwrap().len(), 4 * MAX_CACHEABLE);
+        assert_eq!(hits(), before + 2);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_body_is_an_error_and_not_cached() {
+        setup();
+        let before = hits();
+        assert!(fetch("/truncated").await.is_err(), "truncation reaches the client");
+        assert!(fetch("/truncated").await.is_err());
+        assert_eq!(hits(), before + 2);
+    }
+}
@@ -0,0 +1,338 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use cachebolt::config::{
+        CONFIG, Config, StorageBackend as BackendKind, StorageSettings, WritePolicy,
+    };
+    use cachebolt::memory::memory::CachedResponse;
+    use cachebolt::storage::breaker::BreakerOpen;
+    use cachebolt::storage::tiered::TieredStorage;
+    use cachebolt::storage::{StorageBackend, StorageResult, StoredObject};
+    use ctor::ctor;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// In-memory tier that can be made to fail and counts its writes.
+    struct FakeTier {
+        name: &'static str,
+        entries: Mutex<HashMap<String, CachedResponse>>,
+        meta: Mutex<HashMap<String, Bytes>>,
+        puts: AtomicUsize,
+        failing: AtomicBool,
+    }
+
+    impl FakeTier {
+        fn new(name: &'static str) -> Arc<Self> {
+            Arc::new(FakeTier {
+                name,
+                entries: Mutex::new(HashMap::new()),
+                meta: Mutex::new(HashMap::new()),
+                puts: AtomicUsize::new(0),
+                failing: AtomicBool::new(false),
+            })
+        }
+
+        fn body(&self, key: &str) -> Option<Bytes> {
+            self.entries.lock().unwrap().get(key).map(|e| e.body.clone())
+        }
+
+        fn check(&self) -> StorageResult<()> {
+            if self.failing.load(Ordering::SeqCst) {
+                Err("connection refused".into())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[async_trait]
+    impl StorageBackend for FakeTier {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn get(&self, key: &str) -> StorageResult<Option<CachedResponse>> {
+            self.check()?;
+            Ok(self.entries.lock().unwrap().get(key).cloned())
+        }
+
+        async fn put(&self, key: &str, entry: CachedResponse) -> StorageResult<()> {
+            self.check()?;
+            self.puts.fetch_add(1, Ordering::SeqCst);
+            self.entries.lock().unwrap().insert(key.to_string(), entry);
+            Ok(())
+        }
+
+        async fn delete(&self, key: &str) -> StorageResult<bool> {
+            Ok(self.entries.lock().unwrap().remove(key).is_some())
+        }
+
+        async fn list(&self) -> StorageResult<Vec<StoredObject>> {
+            self.check()?;
+            Ok(self
+                .entries
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(key, entry)| StoredObject {
+                    key: key.clone(),
+                    last_modified: entry.inserted_at,
+                    size: entry.body.len() as u64,
+                })
+                .collect())
+        }
+
+        async fn delete_all(&self) -> StorageResult<Vec<String>> {
+            Ok(self.entries.lock().unwrap().drain().map(|(key, _)| key).collect())
+        }
+
+        async fn health_check(&self) -> StorageResult<()> {
+            self.check()
+        }
+
+        async fn get_meta(&self, name: &str) -> StorageResult<Option<Bytes>> {
+            self.check()?;
+            Ok(self.meta.lock().unwrap().get(name).cloned())
+        }
+
+        async fn put_meta(&self, name: &str, data: Bytes) -> StorageResult<()> {
+            self.check()?;
+            self.meta.lock().unwrap().insert(name.to_string(), data);
+            Ok(())
+        }
+    }
+
+    /// Breakers open after two consecutive failures and are never probed.
+    #[ctor]
+    fn init_config() {
+        let _ = CONFIG.set(Config {
+            storage_backend_failures: 1,
+            backend_retry_interval_secs: 0,
+            ..Default::default()
+        });
+    }
+
+    fn entry(body: &str) -> CachedResponse {
+        let now = chrono::Utc::now();
+        CachedResponse {
+            status: 200,
+            body: Bytes::from(body.to_string()),
+            headers: vec![],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
+    /// Two tiers with unique names, so each test gets its own breakers.
+    fn tiers(
+        upper: &'static str,
+        lower: &'static str,
+        policy: WritePolicy,
+        delay: Duration,
+    ) -> (Arc<FakeTier>, Arc<FakeTier>, TieredStorage) {
+        let (l2, l3) = (FakeTier::new(upper), FakeTier::new(lower));
+        let storage = TieredStorage::new(vec![l2.clone(), l3.clone()], policy, delay);
+        (l2, l3, storage)
+    }
+
+    #[tokio::test]
+    async fn test_lower_tier_hit_is_promoted() {
+        let (l2, l3, storage) =
+            tiers("promote-l2", "promote-l3", WritePolicy::WriteThrough, Duration::ZERO);
+        l3.put("k", entry("from bucket")).await.unwrap();
+
+        let found = storage.get("k").await.unwrap().expect("lower tier hit");
+        assert_eq!(found.body, Bytes::from("from bucket"));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(l2.body("k"), Some(Bytes::from("from bucket")));
+
+        // Served from the upper tier from now on
+        l3.failing.store(true, Ordering::SeqCst);
+        assert!(storage.get("k").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_miss_in_every_tier() {
+        let (_l2, _l3, storage) =
+            tiers("miss-l2", "miss-l3", WritePolicy::WriteThrough, Duration::ZERO);
+        assert!(storage.get("absent").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failing_tier_is_skipped_on_read() {
+        let (l2, l3, storage) =
+            tiers("skip-l2", "skip-l3", WritePolicy::WriteThrough, Duration::ZERO);
+        l2.failing.store(true, Ordering::SeqCst);
+        l3.put("k", entry("still here")).await.unwrap();
+
+        let found = storage.get("k").await.unwrap().expect("served by the lower tier");
+        assert_eq!(found.body, Bytes::from("still here"));
+
+        // No tier has the key and one failed: the error surfaces
+        assert!(storage.get("other").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_through_writes_every_tier() {
+        let (l2, l3, storage) =
+            tiers("through-l2", "through-l3", WritePolicy::WriteThrough, Duration::ZERO);
+        storage.put("k", entry("v1")).await.unwrap();
+
+        assert_eq!(l2.body("k"), Some(Bytes::from("v1")));
+        assert_eq!(l3.body("k"), Some(Bytes::from("v1")));
+    }
+
+    #[tokio::test]
+    async fn test_write_to_open_breaker_fails() {
+        let (l2, _l3, storage) =
+            tiers("open-l2", "open-l3", WritePolicy::WriteThrough, Duration::ZERO);
+        l2.failing.store(true, Ordering::SeqCst);
+        for _ in 0..2 {
+            assert!(storage.put("k", entry("v1")).await.is_err());
+        }
+
+        // The tier is back but its breaker is open: the write is skipped and
+        // reported as failed, so the persistence queue retries it
+        l2.failing.store(false, Ordering::SeqCst);
+        let err = storage.put("k", entry("v2")).await.unwrap_err();
+        assert!(err.is::<BreakerOpen>());
+        assert_eq!(l2.puts.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_write_back_delays_and_coalesces_lower_writes() {
+        let (l2, l3, storage) =
+            tiers("back-l2", "back-l3", WritePolicy::WriteBack, Duration::from_millis(100));
+
+        storage.put("k", entry("v1")).await.unwrap();
+        storage.put("k", entry("v2")).await.unwrap();
+        assert_eq!(l2.body("k"), Some(Bytes::from("v2")));
+        assert_eq!(l3.body("k"), None, "lower tier written after the delay");
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(l3.body("k"), Some(Bytes::from("v2")));
+        assert_eq!(l3.puts.load(Ordering::SeqCst), 1, "rewrites are coalesced");
+    }
+
+    #[tokio::test]
+    async fn test_delete_cancels_pending_write_back() {
+        let (l2, l3, storage) =
+            tiers("cancel-l2", "cancel-l3", WritePolicy::WriteBack, Duration::from_millis(50));
+
+        storage.put("k", entry("v1")).await.unwrap();
+        assert!(storage.delete("k").await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(l2.body("k"), None);
+        assert_eq!(l3.body("k"), None);
+    }
+
+    #[tokio::test]
+    async fn test_list_merges_tiers() {
+        let (l2, l3, storage) =
+            tiers("list-l2", "list-l3", WritePolicy::WriteThrough, Duration::ZERO);
+        l2.put("a", entry("a")).await.unwrap();
+        l3.put("a", entry("a")).await.unwrap();
+        l3.put("b", entry("b")).await.unwrap();
+        l2.put("c", entry("c")).await.unwrap();
+
+        let mut keys: Vec<_> = storage.list().await.unwrap().into_iter().map(|o| o.key).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+
+        // Every key removed from any tier, each once
+        let mut deleted = storage.delete_all().await.unwrap();
+        deleted.sort();
+        assert_eq!(deleted, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_prefix_and_tag_deletion_reach_every_tier() {
+        let (l2, l3, storage) =
+            tiers("purge-l2", "purge-l3", WritePolicy::WriteThrough, Duration::ZERO);
+        let tagged = |source: &str, tags: &str| CachedResponse {
+            headers: vec![("surrogate-key".into(), tags.into())],
+            key_source: source.into(),
+            tags: tags.split_whitespace().map(str::to_string).collect(),
+            ..entry(source)
+        };
+        storage.put("a", tagged("/products/1|", "p1 shop")).await.unwrap();
+        storage.put("b", tagged("/products/2|", "p2")).await.unwrap();
+        storage.put("c", tagged("/cart|", "shop")).await.unwrap();
+        l3.put("d", tagged("/products/4|", "p4")).await.unwrap();
+
+        // Both tiers use the trait's list-and-load defaults; a key removed
+        // from both tiers counts once, one held by a single tier counts too
+        let mut deleted = storage.delete_source_prefix("/products/").await.unwrap();
+        deleted.sort();
+        assert_eq!(deleted, vec!["a", "b", "d"]);
+        assert_eq!(storage.delete_tagged(&["shop".into()]).await.unwrap(), vec!["c"]);
+        assert_eq!(storage.usage().await.unwrap().entries, 0);
+        assert!(l2.entries.lock().unwrap().is_empty() && l3.entries.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_reaches_every_tier() {
+        let (l2, l3, storage) =
+            tiers("meta-l2", "meta-l3", WritePolicy::WriteThrough, Duration::ZERO);
+        storage.put_meta("rules", Bytes::from("v1")).await.unwrap();
+        assert_eq!(l2.meta.lock().unwrap()["rules"], "v1");
+        assert_eq!(l3.meta.lock().unwrap()["rules"], "v1");
+
+        // Read from the first tier holding a copy
+        l2.meta.lock().unwrap().clear();
+        assert_eq!(storage.get_meta("rules").await.unwrap().unwrap(), "v1");
+        assert!(storage.get_meta("absent").await.unwrap().is_none());
+
+        // A failing tier fails the write, the others still get it
+        l2.failing.store(true, Ordering::SeqCst);
+        assert!(storage.put_meta("rules", Bytes::from("v2")).await.is_err());
+        assert_eq!(l3.meta.lock().unwrap()["rules"], "v2");
+        assert_eq!(storage.get_meta("rules").await.unwrap().unwrap(), "v2");
+    }
+
+    #[test]
+    fn test_storage_tiers_fall_back_to_storage_backend() {
+        let single = Config {
+            storage_backend: Some(BackendKind::S3),
+            ..Default::default()
+        };
+        assert_eq!(single.storage_tiers(), vec![BackendKind::S3]);
+
+        let tiered = Config {
+            storage_backend: Some(BackendKind::S3),
+            storage: StorageSettings {
+                tiers: vec![BackendKind::Local, BackendKind::Gcs],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(tiered.storage_tiers(), vec![BackendKind::Local, BackendKind::Gcs]);
+
+        assert!(Config::default().storage_tiers().is_empty());
+    }
+}
@@ -0,0 +1,316 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::{Json, Query};
+    use axum::{Router, http::HeaderMap, response::IntoResponse, routing::get};
+    use bytes::Bytes;
+    use cachebolt::{
+        admin::clean::{InvalidateUrlRequest, PurgeParams, invalidate_url_handler},
+        cache_key::{
+            CacheKey, key_source, recorded_variants, resolve, vary_from_headers, vary_record,
+            variant,
+        },
+        config::{
+            CONFIG, CacheKeySettings, CacheMode, CacheSettings, Config, KeyStrategy,
+            LatencyFailover, StorageBackend,
+        },
+        memory::memory::{CachedResponse, peek_from_memory},
+        proxy::proxy_handler,
+        storage,
+    };
+    use hyper::{Body, Request};
+    use std::net::TcpListener;
+    use std::sync::Once;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static UPSTREAM_HITS: AtomicUsize = AtomicUsize::new(0);
+    static INIT: Once = Once::new();
+
+    /// Starts a mock downstream on its own runtime and points CONFIG at it.
+    fn setup() {
+        INIT.call_once(|| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(async move {
+                    let app = Router::new()
+                        .route(
+                            "/lang",
+                            get(|headers: HeaderMap| async move {
+                                UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                                let lang = headers
+                                    .get("accept-language")
+                                    .and_then(|v| v.to_str().ok())
+                                    .unwrap_or("none")
+                                    .to_string();
+                                (
+                                    [("connection", "close"), ("vary", "Accept-Language")],
+                                    format!("lang:{lang}"),
+                                )
+                                    .into_response()
+                            }),
+                        )
+                        .route(
+                            "/star",
+                            get(|| async {
+                                UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                                ([("connection", "close"), ("vary", "*")], "star").into_response()
+                            }),
+                        )
+                        .route(
+                            "/*path",
+                            get(|| async {
+                                UPSTREAM_HITS.fetch_add(1, Ordering::SeqCst);
+                                ([("connection", "close")], "plain").into_response()
+                            }),
+                        );
+                    axum::Server::from_tcp(listener)
+                        .unwrap()
+                        .serve(app.into_make_service())
+                        .await
+                        .unwrap();
+                });
+            });
+
+            let _ = CONFIG.set(Config {
+                app_id: "vary".into(),
+                max_concurrent_requests: 10,
+                downstream_base_url: format!("http://127.0.0.1:{port}"),
+                cache: CacheSettings {
+                    memory_threshold: 100,
+                    ttl_seconds: 60,
+                    mode: CacheMode::ReadThrough,
+                    ..Default::default()
+                },
+                latency_failover: LatencyFailover {
+                    default_max_latency_ms: 5000,
+                    path_rules: vec![],
+                },
+                cache_key: CacheKeySettings {
+                    strategy: KeyStrategy::Vary,
+                    header_allowlist: vec!["X-Tenant".into()],
+                    ..Default::default()
+                },
+                storage_backend: Some(StorageBackend::Local),
+                ..Default::default()
+            });
+        });
+    }
+
+    async fn get_body(uri: &str, headers: &[(&str, &str)]) -> String {
+        let mut builder = Request::builder().uri(uri);
+        for (k, v) in headers {
+            builder = builder.header(*k, *v);
+        }
+        let resp = proxy_handler(builder.body(Body::empty()).unwrap())
+            .await
+            .into_response();
+        let bytes = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+
+    fn hits() -> usize {
+        UPSTREAM_HITS.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_vary_header_parsing() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(vary_from_headers(&headers), Some(vec![]));
+
+        headers.append("vary", "Accept-Language, accept-encoding".parse().unwrap());
+        headers.append("vary", "Accept-Language".parse().unwrap());
+        assert_eq!(
+            vary_from_headers(&headers),
+            Some(vec!["accept-encoding".to_string(), "accept-language".to_string()])
+        );
+
+        headers.append("vary", "*".parse().unwrap());
+        assert_eq!(vary_from_headers(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_unlisted_headers_do_not_fragment_the_cache() {
+        setup();
+        let before = hits();
+        assert_eq!(get_body("/plain", &[("user-agent", "a"), ("x-request-id", "1")]).await, "plain");
+        assert_eq!(get_body("/plain", &[("user-agent", "b"), ("x-request-id", "2")]).await, "plain");
+        assert_eq!(hits(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_allowlisted_headers_are_part_of_the_key() {
+        setup();
+        let before = hits();
+        get_body("/tenant", &[("x-tenant", "a")]).await;
+        get_body("/tenant", &[("x-tenant", "b")]).await;
+        get_body("/tenant", &[("x-tenant", "a")]).await;
+        assert_eq!(hits(), before + 2);
+    }
+
+    #[tokio::test]
+    async fn test_vary_selects_the_variant() {
+        setup();
+        let before = hits();
+        assert_eq!(get_body("/lang", &[("accept-language", "en")]).await, "lang:en");
+        assert_eq!(get_body("/lang", &[("accept-language", "fr")]).await, "lang:fr");
+        assert_eq!(hits(), before + 2);
+
+        assert_eq!(
+            get_body("/lang", &[("accept-language", "en"), ("user-agent", "x")]).await,
+            "lang:en"
+        );
+        assert_eq!(get_body("/lang", &[("accept-language", "fr")]).await, "lang:fr");
+        assert_eq!(hits(), before + 2);
+    }
+
+    #[tokio::test]
+    async fn test_vary_star_is_not_cached() {
+        setup();
+        let before = hits();
+        get_body("/star", &[]).await;
+        get_body("/star", &[]).await;
+        assert_eq!(hits(), before + 2);
+    }
+
+    #[tokio::test]
+    async fn test_variants_are_found_through_the_persisted_vary_record() {
+        setup();
+        let mut request = HeaderMap::new();
+        request.insert("accept-language", "de".parse().unwrap());
+        let base = CacheKey::new(key_source("/restarted", &request, None));
+        let expected = variant(&base, &["accept-language".to_string()], &request);
+
+        let now = chrono::Utc::now();
+        let stored = CachedResponse {
+            status: 200,
+            body: Bytes::from("lang:de"),
+            headers: vec![("vary".into(), "Accept-Language".into())],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: expected.source.clone(),
+            tags: vec![],
+            purged: false,
+        };
+
+        // Only the persistent backend knows the variants, as after a restart
+        let backend = storage::backend().unwrap();
+        backend.put(&base.key, vary_record(&base, &expected, &stored, None)).await.unwrap();
+        assert!(peek_from_memory(&base.key).await.is_none());
+
+        assert_eq!(resolve(&base, &request).await, expected);
+        assert!(peek_from_memory(&base.key).await.is_some());
+
+        backend.delete(&base.key).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backend_is_asked_once_per_base_key() {
+        setup();
+        let mut request = HeaderMap::new();
+        request.insert("accept-language", "de".parse().unwrap());
+        let base = CacheKey::new(key_source("/asked-once", &request, None));
+        assert_eq!(resolve(&base, &request).await, base);
+
+        // A record stored behind this process's back is not looked up again
+        let stored = CachedResponse {
+            status: 200,
+            body: Bytes::from("lang:de"),
+            headers: vec![("vary".into(), "Accept-Language".into())],
+            inserted_at: chrono::Utc::now(),
+            expires_at: chrono::Utc::now() + chrono::Duration::seconds(60),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        };
+        let expected = variant(&base, &["accept-language".to_string()], &request);
+        let backend = storage::backend().unwrap();
+        backend.put(&base.key, vary_record(&base, &expected, &stored, None)).await.unwrap();
+        assert_eq!(resolve(&base, &request).await, base);
+        assert!(peek_from_memory(&base.key).await.is_none());
+
+        backend.delete(&base.key).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_vary_record_lists_every_variant() {
+        setup();
+        let base = CacheKey::new(key_source("/listed", &HeaderMap::new(), None));
+        let vary = ["accept-language".to_string()];
+        let mut request = HeaderMap::new();
+        request.insert("accept-language", "en".parse().unwrap());
+        let en = variant(&base, &vary, &request);
+        request.insert("accept-language", "fr".parse().unwrap());
+        let fr = variant(&base, &vary, &request);
+
+        let now = chrono::Utc::now();
+        let stored = |expires_in| CachedResponse {
+            status: 200,
+            body: Bytes::from("lang"),
+            headers: vec![("vary".into(), "Accept-Language".into())],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(expires_in),
+            must_revalidate: false,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: String::new(),
+            tags: vec![],
+            purged: false,
+        };
+
+        let first = vary_record(&base, &en, &stored(120), None);
+        let second = vary_record(&base, &fr, &stored(60), Some(&first));
+        let again = vary_record(&base, &en, &stored(60), Some(&second));
+        assert_eq!(recorded_variants(&again), vec![en.key, fr.key]);
+        assert_eq!(again.expires_at, first.expires_at, "lives as long as its longest variant");
+
+        // A response that does not vary lists nothing
+        let plain = CachedResponse { headers: vec![], ..stored(60) };
+        assert!(recorded_variants(&plain).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_url_removes_the_recorded_variants() {
+        setup();
+        assert_eq!(get_body("/lang?inv=1", &[("accept-language", "en")]).await, "lang:en");
+        assert_eq!(get_body("/lang?inv=1", &[("accept-language", "fr")]).await, "lang:fr");
+        let base = CacheKey::new(key_source("/lang?inv=1", &HeaderMap::new(), None));
+        assert_eq!(recorded_variants(&peek_from_memory(&base.key).await.unwrap()).len(), 2);
+
+        let resp = invalidate_url_handler(
+            Query(PurgeParams::default()),
+            Json(InvalidateUrlRequest { url: "/lang?inv=1".into(), headers: Default::default() }),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let counts: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(counts["memory"], 3, "the vary record and both variants");
+        assert!(peek_from_memory(&base.key).await.is_none());
+
+        let before = hits();
+        assert_eq!(get_body("/lang?inv=1", &[("accept-language", "fr")]).await, "lang:fr");
+        assert_eq!(hits(), before + 1);
+    }
+}
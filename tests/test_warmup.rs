@@ -0,0 +1,107 @@
+// Copyright (C) 2025 Matías Salinas (support@fenden.com)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use cachebolt::{
+        config::{CONFIG, CacheSettings, Config, StorageBackend, WarmupSettings},
+        memory::memory::{CachedResponse, peek_from_memory},
+        storage::{StoredObject, local},
+        warmup::{select_for_warmup, warm_up_memory_cache},
+    };
+    use ctor::ctor;
+    use std::time::{Duration, SystemTime};
+
+    #[ctor]
+    fn init_config() {
+        let _ = CONFIG.set(Config {
+            app_id: "warmup-test".into(),
+            cache: CacheSettings {
+                memory_threshold: 100,
+                ..Default::default()
+            },
+            storage_backend: Some(StorageBackend::Local),
+            warmup: WarmupSettings {
+                enabled: true,
+                max_entries: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    }
+
+    fn entry(body: &str, ttl_secs: i64, must_revalidate: bool) -> CachedResponse {
+        let now = chrono::Utc::now();
+        CachedResponse {
+            status: 200,
+            body: Bytes::from(body.to_string()),
+            headers: vec![("content-type".into(), "text/plain".into())],
+            inserted_at: now,
+            expires_at: now + chrono::Duration::seconds(ttl_secs),
+            must_revalidate,
+            stale_while_revalidate: 0,
+            stale_if_error: 0,
+            key_source: format!("/{body}|"),
+            tags: vec![],
+            purged: false,
+        }
+    }
+
+    /// Stores an entry on local disk and backdates its file by `age_secs`.
+    async fn store(key: &str, entry: CachedResponse, age_secs: u64) {
+        local::store_in_cache(key.to_string(), entry).await.unwrap();
+        let path = local::build_local_cache_path(key).unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_select_keeps_most_recent_entries() {
+        let object = |key: &str, age: i64| StoredObject {
+            key: key.into(),
+            last_modified: chrono::Utc::now() - chrono::Duration::seconds(age),
+            size: 10,
+        };
+
+        let selected = select_for_warmup(vec![object("old", 30), object("new", 1), object("mid", 10)], 2);
+        let keys = selected.iter().map(|o| o.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["new", "mid"]);
+
+        assert_eq!(select_for_warmup(vec![object("a", 1), object("b", 2)], 0).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_loads_recent_servable_entries() {
+        let _ = local::delete_all_from_cache().await;
+        store("warm-new", entry("new", 60, false), 10).await;
+        store("warm-expired", entry("expired", -60, true), 20).await;
+        store("warm-mid", entry("mid", 60, false), 30).await;
+        store("warm-old", entry("old", 60, false), 40).await;
+
+        let report = warm_up_memory_cache().await;
+
+        assert_eq!(report.listed, 4);
+        assert_eq!(report.loaded, 1, "only max_entries are considered");
+        assert_eq!(report.skipped, 1, "must-revalidate entry past expiry is skipped");
+        assert!(report.bytes > 0);
+
+        let loaded = peek_from_memory("warm-new").await.expect("newest entry warmed");
+        assert_eq!(loaded.body, Bytes::from("new"));
+        assert_eq!(loaded.key_source, "/new|");
+        assert!(peek_from_memory("warm-expired").await.is_none());
+        assert!(peek_from_memory("warm-old").await.is_none());
+    }
+}